@@ -1,4 +1,4 @@
-use crate::{ray::Ray, vec3d::Point3d};
+use crate::{Error, Result, ray::Ray, vec3d::Point3d};
 
 /// Axis-aligned bounding box
 #[derive(Debug, Clone, Copy)]
@@ -9,9 +9,20 @@ pub struct BoundingBox {
 }
 
 impl BoundingBox {
+    /// Creates a bounding box, panics if min is greater than max on any axis.
+    /// See `try_new` for a version that returns an error instead.
     pub fn new(min: Point3d, max: Point3d) -> Self {
+        match Self::try_new(min, max) {
+            Ok(bounding_box) => bounding_box,
+            Err(error) => panic!("{error}"),
+        }
+    }
+
+    /// Creates a bounding box, returns an error if min is greater than max on
+    /// any axis.
+    pub fn try_new(min: Point3d, max: Point3d) -> Result<Self> {
         if min.x > max.x || min.y > max.y || min.z > max.z {
-            panic!("min must be less than max; min: {min:?}, max: {max:?}")
+            return Err(Error::InvalidBoundingBox { min, max });
         }
         let len_x = max.x - min.x;
         let len_y = max.y - min.y;
@@ -24,11 +35,11 @@ impl BoundingBox {
             2
         };
 
-        BoundingBox {
+        Ok(BoundingBox {
             min,
             max,
             longest_axis,
-        }
+        })
     }
 
     /// Returns whether or not a ray intersects with a bounding box, see notes
@@ -74,6 +85,13 @@ mod bounding_box_tests {
 
     use super::*;
 
+    #[test]
+    fn try_new_should_return_error_when_min_is_greater_than_max() {
+        let result = BoundingBox::try_new(Vec3d::new(1.0, 4.0, 1.0), Vec3d::new(3.0, 3.0, 3.0));
+
+        assert!(matches!(result, Err(Error::InvalidBoundingBox { .. })));
+    }
+
     #[test]
     fn hit_should_return_true_when_ray_hits() {
         let b_box = BoundingBox::new(Vec3d::new(1.0, 1.0, 1.0), Vec3d::new(3.0, 3.0, 3.0));
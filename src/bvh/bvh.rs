@@ -2,6 +2,7 @@ use super::bounding_box::BoundingBox;
 use std::cmp::Ordering;
 
 use crate::{
+    Error, Result,
     hittable::{hit_record::HitRecord, hittable::Hittable},
     ray::Ray,
     utilities::surrounding_box_option,
@@ -28,9 +29,24 @@ pub struct BvhMetrics {
 }
 
 impl Bvh {
-    /// Builds a BVH from scene data.
-    pub fn build(time0: f64, time1: f64, items: Vec<Box<dyn Hittable>>) -> (Self, BvhMetrics) {
-        Self::build_internal(time0, time1, items, 0)
+    /// Builds a BVH from scene data. Fails if there are no items or if any of
+    /// the items do not have a bounding box.
+    pub fn build(
+        time0: f64,
+        time1: f64,
+        items: Vec<Box<dyn Hittable>>,
+    ) -> Result<(Self, BvhMetrics)> {
+        if items.is_empty() {
+            return Err(Error::EmptyBvh);
+        }
+        if items
+            .iter()
+            .any(|item| item.bounding_box(time0, time1).is_none())
+        {
+            return Err(Error::MissingBoundingBox);
+        }
+
+        Ok(Self::build_internal(time0, time1, items, 0))
     }
 
     fn build_internal(
@@ -167,7 +183,7 @@ impl Bvh {
 }
 
 impl Hittable for Bvh {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
         // Check if we hit the bounding box
         if !self.bounding_box.hit(ray, t_min, t_max) {
             return None;
@@ -180,7 +196,7 @@ impl Hittable for Bvh {
             (Some(left), None) => left.hit(ray, t_min, t_max),
             (None, Some(right)) => right.hit(ray, t_min, t_max),
             (Some(left), Some(right)) => {
-                let mut hit_record: Option<HitRecord> = None;
+                let mut hit_record: Option<HitRecord<'_>> = None;
                 let mut closest_so_far = t_max;
                 if let Some(hr) = left.hit(ray, t_min, closest_so_far) {
                     closest_so_far = hr.t;
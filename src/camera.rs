@@ -5,6 +5,7 @@ use crate::{
     vec3d::Vec3d,
 };
 
+#[derive(Debug, Clone, Copy)]
 pub struct Camera {
     /// Position of the camera.
     origin: Point3d,
@@ -17,10 +18,11 @@ pub struct Camera {
     u: Vec3d,
     /// Unit vertical
     v: Vec3d,
-    /// Unit direction the camera is pointing.
-    #[allow(dead_code)]
+    /// Unit direction opposite to the one the camera is pointing.
     w: Vec3d,
     lens_radius: f64,
+    /// Distance to the plane of focus, which the image is projected onto.
+    focus_dist: f64,
     /// Shutter open time.
     time0: f64,
     /// Shutter close time.
@@ -36,6 +38,7 @@ impl Camera {
     /// * `focus_distance`: Distance to plane of focus.
     /// * `time0`: Shutter open time.
     /// * `time1`: Shutter close time.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         look_from: Point3d,
         look_at: Point3d,
@@ -72,11 +75,15 @@ impl Camera {
             v,
             w,
             lens_radius,
+            focus_dist,
             time0,
             time1,
         }
     }
 
+    /// Returns the ray through the point `s`, `t` of the image, both from 0
+    /// at the bottom left corner to 1 at the top right, starting from a
+    /// random point on the lens.
     pub fn get_ray(&self, s: f64, t: f64) -> Ray {
         let rd = self.lens_radius * random_vec_in_unit_disc();
         let offset = rd.x * self.u + rd.y * self.v;
@@ -89,4 +96,114 @@ impl Camera {
             time: random_rng(self.time0, self.time1),
         }
     }
+
+    /// Returns a random point on the lens, where rays leave the camera from.
+    pub fn lens_point(&self) -> Point3d {
+        let rd = self.lens_radius * random_vec_in_unit_disc();
+        self.origin + rd.x * self.u + rd.y * self.v
+    }
+
+    /// Returns the position `s`, `t` on the image that light from `point`
+    /// reaching `lens_point` is seen at, the inverse of `get_ray`. `None` if
+    /// the point is behind the camera. Points outside the image have
+    /// positions outside 0 to 1.
+    pub fn image_position(&self, lens_point: Point3d, point: Point3d) -> Option<(f64, f64)> {
+        let direction = point - lens_point;
+        let forward = direction.dot(&(-1.0 * self.w));
+        if forward <= 0.0 {
+            return None;
+        }
+
+        // Where the ray crosses the plane of focus, which the lens is parallel to
+        let distance = (lens_point - self.lower_left_corner).dot(&self.w);
+        let focus_point = lens_point + (distance / forward) * direction;
+        let offset = focus_point - self.lower_left_corner;
+        Some((
+            offset.dot(&self.horizontal) / self.horizontal.len_squared(),
+            offset.dot(&self.vertical) / self.vertical.len_squared(),
+        ))
+    }
+
+    /// Returns the density, per unit solid angle, of rays leaving the lens in
+    /// `direction` when `s` and `t` are picked uniformly from 0 to 1.
+    pub fn direction_pdf(&self, direction: Vec3d) -> f64 {
+        let cos_theta = direction.unit_vector().dot(&(-1.0 * self.w));
+        if cos_theta <= 0.0 {
+            return 0.0;
+        }
+        // The image is the plane of focus, so the area of the image seen
+        // through a unit solid angle grows with the distance to it
+        let image_area = self.horizontal.len() * self.vertical.len();
+        self.focus_dist * self.focus_dist / (image_area * cos_theta.powi(3))
+    }
+}
+
+#[cfg(test)]
+mod camera_tests {
+    use crate::utilities::seed_random;
+
+    use super::*;
+
+    fn camera(aperture: f64) -> Camera {
+        Camera::new(
+            Point3d::new(1.0, 2.0, 3.0),
+            Point3d::new(0.0, 0.0, -2.0),
+            Vec3d::new(0.0, 1.0, 0.0),
+            40.0,
+            1.5,
+            aperture,
+            4.0,
+            0.0,
+            1.0,
+        )
+    }
+
+    #[test]
+    fn image_position_should_undo_get_ray() {
+        seed_random(3);
+        for aperture in [0.0, 0.5] {
+            let camera = camera(aperture);
+            for (s, t) in [(0.5, 0.5), (0.1, 0.9), (0.75, 0.2)] {
+                let ray = camera.get_ray(s, t);
+                let point = ray.at(2.5);
+
+                let (image_s, image_t) = camera.image_position(ray.origin, point).unwrap();
+                assert!((image_s - s).abs() < 1e-9, "{image_s} != {s}");
+                assert!((image_t - t).abs() < 1e-9, "{image_t} != {t}");
+            }
+        }
+    }
+
+    #[test]
+    fn image_position_should_be_none_behind_the_camera() {
+        let camera = camera(0.0);
+
+        assert_eq!(
+            camera.image_position(camera.origin, Point3d::new(2.0, 4.0, 8.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn direction_pdf_should_integrate_to_one_over_the_image() {
+        let camera = camera(0.0);
+
+        // Sum the solid angle of a grid of pixels times the density
+        let n = 200;
+        let mut total = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let s = (i as f64 + 0.5) / n as f64;
+                let t = (j as f64 + 0.5) / n as f64;
+                let direction = camera.get_ray(s, t).direction;
+                let distance = direction.len();
+                let cos_theta = direction.unit_vector().dot(&(-1.0 * camera.w));
+                let pixel_area = camera.horizontal.len() * camera.vertical.len() / (n * n) as f64;
+                let solid_angle = pixel_area * cos_theta / (distance * distance);
+                total += camera.direction_pdf(direction) * solid_angle;
+            }
+        }
+
+        assert!((total - 1.0).abs() < 1e-3, "{total}");
+    }
 }
@@ -0,0 +1,632 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum, error::ErrorKind};
+use rs_ray_tracer::{
+    render::{Aov, CropWindow, Filter, Integrator, LightSampling, TileOrder},
+    resolution::Resolution,
+    scene::{MaterialDescription, parse_material_text},
+};
+
+/// A ray tracer based on the "Ray Tracing in One Weekend" book series.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Render a scene to image files, one for each camera.
+    Render(Box<RenderArgs>),
+    /// Save a scene to a scene file. Randomly generated scenes are saved with
+    /// the layout generated for this run, so it can be rendered again later.
+    Export(ExportArgs),
+    /// List the built-in scenes and resolution presets.
+    List,
+}
+
+#[derive(Debug, Args)]
+pub struct RenderArgs {
+    /// Name of a built-in scene or path to a scene file.
+    #[arg(short, long, default_value = "final_scene")]
+    pub scene: String,
+
+    /// Resolution preset, defaults to the resolution suggested by the scene,
+    /// or `low` for scene files that do not suggest one.
+    #[arg(short, long, value_enum)]
+    pub resolution: Option<ResolutionPreset>,
+
+    /// Image width in pixels, overrides the resolution preset.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(2..))]
+    pub width: Option<u32>,
+
+    /// Image height in pixels, overrides the resolution preset.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(2..))]
+    pub height: Option<u32>,
+
+    /// Number of ray samples per pixel, overrides the resolution preset.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub samples: Option<u32>,
+
+    /// Max number of ray bounces, overrides the resolution preset.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_depth: Option<u32>,
+
+    /// Output image file. When several cameras are rendered the camera number
+    /// is added to the file name, e.g. `result_1.png`.
+    #[arg(short, long, default_value = "results/result.png")]
+    pub output: PathBuf,
+
+    /// Output image format, defaults to the extension of the output file.
+    #[arg(short, long, value_enum)]
+    pub format: Option<ImageFormat>,
+
+    /// Cameras to render, numbered from 1. Renders every camera in the scene
+    /// by default.
+    #[arg(
+        short,
+        long = "camera",
+        value_name = "NUMBER",
+        value_delimiter = ',',
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub cameras: Vec<u32>,
+
+    /// Number of threads to render with, defaults to the number of CPUs.
+    #[arg(short = 'j', long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub threads: Option<u32>,
+
+    /// Seed for the random number generator, makes randomly generated scenes
+    /// the same on every run.
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Replace every material except lights, written as in a scene file,
+    /// e.g. "lambertian 0.8 0.8 0.8" for a clay render.
+    #[arg(long, value_name = "MATERIAL", value_parser = parse_material)]
+    pub override_material: Option<MaterialDescription>,
+
+    /// How light reaching diffuse and glossy surfaces is found.
+    #[arg(long, value_enum, default_value = "power")]
+    pub light_sampling: LightSamplingArg,
+
+    /// How the light reaching the camera is found.
+    #[arg(long, value_enum, default_value = "path")]
+    pub integrator: IntegratorArg,
+
+    /// Find caustics with a photon map of this many photons per pass, rather
+    /// than by path tracing alone.
+    #[arg(long, value_name = "PHOTONS", requires = "caustic_radius")]
+    pub caustic_photons: Option<usize>,
+
+    /// Radius caustic photons are gathered from, in scene units.
+    #[arg(long, value_name = "RADIUS", requires = "caustic_photons", value_parser = parse_positive)]
+    pub caustic_radius: Option<f64>,
+
+    /// Shrink the caustic radius with each pass of a progressive render, more
+    /// quickly the lower it is, from 0 to 1, e.g. 0.7. The caustics then
+    /// sharpen as the render goes on.
+    #[arg(long, value_name = "ALPHA", requires = "caustic_photons", value_parser = parse_radius_reduction)]
+    pub caustic_radius_reduction: Option<f64>,
+
+    /// How samples are spread over the pixels around them, wider filters
+    /// anti-alias edges better but soften the image.
+    #[arg(long, value_enum, default_value = "box")]
+    pub filter: FilterArg,
+
+    /// How far the filter reaches, in pixels, at least 0.5. Defaults to 0.5
+    /// for box, 1 for tent, 1.5 for gaussian, 2 for mitchell and 3 for
+    /// lanczos.
+    #[arg(long, value_name = "PIXELS", value_parser = parse_filter_radius)]
+    pub filter_radius: Option<f64>,
+
+    /// Sample each pixel until its noise is below this level, from 0 to 1,
+    /// rather than taking the same number of samples for every pixel. The
+    /// number of samples is then the most taken for a pixel.
+    #[arg(long, value_parser = parse_noise_threshold)]
+    pub noise_threshold: Option<f64>,
+
+    /// Samples taken for every pixel before its noise is measured, used with
+    /// --noise-threshold.
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u32).range(2..))]
+    pub min_samples: u32,
+
+    /// Also save a heatmap of the number of samples taken for each pixel,
+    /// named after the output file with "_samples" added, e.g.
+    /// `result_samples.png`.
+    #[arg(long)]
+    pub sample_heatmap: bool,
+
+    /// Also save a copy of the image with its noise removed, named after the
+    /// output file with "_denoised" added, e.g. `result_denoised.png`. The
+    /// denoiser is guided by the albedo, normals and depth of the first hits,
+    /// which are rendered before the image. Snapshots of a progressive render
+    /// are denoised too.
+    #[arg(long)]
+    pub denoise: bool,
+
+    /// Also render passes of what the camera sees at the first surface each
+    /// sample hits, saved as OpenEXR files named after the output file with
+    /// the pass added, e.g. `result_depth.exr`.
+    #[arg(long = "aov", value_name = "PASS", value_enum, value_delimiter = ',')]
+    pub aovs: Vec<AovArg>,
+
+    /// Render in passes of this many samples per pixel over the whole image,
+    /// saving the image so far as it goes. Ctrl-C stops the render and saves
+    /// the image so far.
+    #[arg(long, value_name = "SAMPLES", value_parser = clap::value_parser!(u32).range(1..))]
+    pub progressive: Option<u32>,
+
+    /// Seconds between saving the image so far in a progressive render,
+    /// defaults to saving after every pass.
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 0,
+        requires = "progressive"
+    )]
+    pub snapshot_interval: u64,
+
+    /// File to save the samples of a progressive render to with each
+    /// snapshot. If the file exists the render carries on from it, or adds
+    /// more samples if it had finished. A checkpoint is not resumed if the
+    /// scene, or a model or image file it uses, has changed since. Scenes
+    /// generated randomly must be rendered with the same --seed to be resumed.
+    #[arg(long, value_name = "FILE", requires = "progressive")]
+    pub checkpoint: Option<PathBuf>,
+
+    /// Width and height in pixels of the tiles the image is rendered in.
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u32).range(1..))]
+    pub tile_size: u32,
+
+    /// Order the tiles are rendered in.
+    #[arg(long, value_enum, default_value = "scanline")]
+    pub tile_order: TileOrderArg,
+
+    /// Only render a region of the image, in pixels from the top left
+    /// corner. Only the region is saved unless --full-frame or --merge-into
+    /// is given.
+    #[arg(
+        long,
+        value_name = "X,Y,WIDTH,HEIGHT",
+        group = "crop_window",
+        value_parser = parse_crop
+    )]
+    pub crop: Option<CropWindow>,
+
+    /// Only render a region of the image, in fractions of the image width
+    /// and height from 0 to 1, counted from the top left corner.
+    #[arg(
+        long,
+        value_name = "X0,Y0,X1,Y1",
+        group = "crop_window",
+        value_parser = parse_crop_normalised
+    )]
+    pub crop_normalised: Option<CropWindow>,
+
+    /// Save the full size image with the region rendered and the rest black,
+    /// rather than only the region.
+    #[arg(long, requires = "crop_window")]
+    pub full_frame: bool,
+
+    /// Save the region rendered into a copy of an earlier render of the same
+    /// size, e.g. to render a noisy region again with more samples.
+    #[arg(long, value_name = "IMAGE", requires = "crop_window")]
+    pub merge_into: Option<PathBuf>,
+}
+
+fn parse_positive(text: &str) -> Result<f64, String> {
+    match text.parse::<f64>() {
+        Ok(value) if value > 0.0 && value.is_finite() => Ok(value),
+        Ok(_) => Err("must be greater than 0".to_string()),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+fn parse_filter_radius(text: &str) -> Result<f64, String> {
+    match text.parse::<f64>() {
+        Ok(radius) if radius >= 0.5 && radius.is_finite() => Ok(radius),
+        Ok(_) => Err("must be at least 0.5".to_string()),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+fn parse_radius_reduction(text: &str) -> Result<f64, String> {
+    match text.parse::<f64>() {
+        Ok(alpha) if alpha > 0.0 && alpha < 1.0 => Ok(alpha),
+        Ok(_) => Err("must be between 0 and 1".to_string()),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+fn parse_noise_threshold(text: &str) -> Result<f64, String> {
+    match text.parse::<f64>() {
+        Ok(threshold) if threshold.is_finite() && threshold > 0.0 => Ok(threshold),
+        Ok(_) => Err("must be a finite number greater than 0".to_string()),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+/// Parses four numbers separated by commas.
+fn parse_four<T: std::str::FromStr>(text: &str) -> Result<[T; 4], String>
+where
+    T::Err: std::fmt::Display,
+{
+    let values = text
+        .split(',')
+        .map(|value| value.trim().parse::<T>().map_err(|error| error.to_string()))
+        .collect::<Result<Vec<T>, String>>()?;
+    values
+        .try_into()
+        .map_err(|_| "expected four numbers separated by commas".to_string())
+}
+
+fn parse_crop(text: &str) -> Result<CropWindow, String> {
+    let [x, y, width, height] = parse_four::<usize>(text)?;
+    if width == 0 || height == 0 {
+        return Err("width and height must be greater than 0".to_string());
+    }
+    Ok(CropWindow::Pixels {
+        x,
+        y,
+        width,
+        height,
+    })
+}
+
+fn parse_crop_normalised(text: &str) -> Result<CropWindow, String> {
+    let [x0, y0, x1, y1] = parse_four::<f64>(text)?;
+    let window = CropWindow::Normalised { x0, y0, x1, y1 };
+    if !window.fits(1, 1) {
+        return Err("expected 0 <= X0 < X1 <= 1 and 0 <= Y0 < Y1 <= 1".to_string());
+    }
+    Ok(window)
+}
+
+fn parse_material(text: &str) -> Result<MaterialDescription, String> {
+    parse_material_text(text).map_err(|error| error.to_string())
+}
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    /// Name of a built-in scene or path to a scene file.
+    #[arg(short, long)]
+    pub scene: String,
+
+    /// Scene file to save to.
+    #[arg(short, long)]
+    pub output: PathBuf,
+
+    /// Seed for the random number generator, exports the same layout of a
+    /// randomly generated scene on every run.
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ResolutionPreset {
+    Low,
+    CornellSquare,
+    Medium,
+    High,
+}
+
+impl ResolutionPreset {
+    pub fn resolution(self) -> Resolution {
+        match self {
+            ResolutionPreset::Low => Resolution::low(),
+            ResolutionPreset::CornellSquare => Resolution::cornell_square(),
+            ResolutionPreset::Medium => Resolution::medium(),
+            ResolutionPreset::High => Resolution::high(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum LightSamplingArg {
+    /// Only follow the rays scattered by materials.
+    Material,
+    /// Only trace shadow rays to the lights.
+    Lights,
+    /// Both, weighted with the balance heuristic.
+    Balance,
+    /// Both, weighted with the power heuristic.
+    Power,
+}
+
+impl From<LightSamplingArg> for LightSampling {
+    fn from(arg: LightSamplingArg) -> Self {
+        match arg {
+            LightSamplingArg::Material => LightSampling::Material,
+            LightSamplingArg::Lights => LightSampling::Lights,
+            LightSamplingArg::Balance => LightSampling::Balance,
+            LightSamplingArg::Power => LightSampling::Power,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum IntegratorArg {
+    /// Follow paths from the camera.
+    Path,
+    /// Follow paths from both the camera and the lights and join them up,
+    /// for caustics and light reaching through small gaps.
+    Bidirectional,
+}
+
+impl From<IntegratorArg> for Integrator {
+    fn from(arg: IntegratorArg) -> Self {
+        match arg {
+            IntegratorArg::Path => Integrator::PathTracing,
+            IntegratorArg::Bidirectional => Integrator::Bidirectional,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum FilterArg {
+    /// Average the samples of each pixel.
+    Box,
+    /// Weight samples less the further they are from the pixel.
+    Tent,
+    /// A Gaussian, soft without jagged edges.
+    Gaussian,
+    /// The Mitchell-Netravali cubic, sharper than a Gaussian.
+    Mitchell,
+    /// A windowed sinc, the sharpest but it rings around bright edges.
+    Lanczos,
+}
+
+impl From<FilterArg> for Filter {
+    fn from(arg: FilterArg) -> Self {
+        match arg {
+            FilterArg::Box => Filter::Box,
+            FilterArg::Tent => Filter::Tent,
+            FilterArg::Gaussian => Filter::Gaussian,
+            FilterArg::Mitchell => Filter::Mitchell,
+            FilterArg::Lanczos => Filter::Lanczos,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum AovArg {
+    /// Distance from the camera.
+    Depth,
+    /// World space normal.
+    Normal,
+    /// Colour of the surface without lighting.
+    Albedo,
+    /// Object number, in the order of the scene.
+    ObjectId,
+    /// Material number, in the order materials appear in the image.
+    MaterialId,
+    /// Texture coordinates.
+    Uv,
+    /// Movement across the image while the shutter is open, in pixels.
+    Motion,
+    /// All of the passes.
+    All,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum TileOrderArg {
+    /// A row at a time from the top left corner.
+    Scanline,
+    /// Outwards from the centre of the image.
+    Spiral,
+    /// Along a Hilbert curve.
+    Hilbert,
+}
+
+impl From<TileOrderArg> for TileOrder {
+    fn from(arg: TileOrderArg) -> Self {
+        match arg {
+            TileOrderArg::Scanline => TileOrder::Scanline,
+            TileOrderArg::Spiral => TileOrder::Spiral,
+            TileOrderArg::Hilbert => TileOrder::Hilbert,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ImageFormat {
+    Png,
+    Ppm,
+}
+
+impl ImageFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Ppm => "ppm",
+        }
+    }
+}
+
+impl RenderArgs {
+    /// Applies the resolution arguments on top of the scene's resolution.
+    pub fn resolution(&self, scene_resolution: Resolution) -> Resolution {
+        let mut resolution = self
+            .resolution
+            .map_or(scene_resolution, ResolutionPreset::resolution);
+        if let Some(width) = self.width {
+            resolution.image_width = width as usize;
+        }
+        if let Some(height) = self.height {
+            resolution.image_height = height as usize;
+        }
+        if let Some(samples) = self.samples {
+            resolution.num_samples = samples as usize;
+        }
+        if let Some(max_depth) = self.max_depth {
+            resolution.max_depth = max_depth as usize;
+        }
+        resolution
+    }
+
+    pub fn crop_window(&self) -> Option<CropWindow> {
+        self.crop.or(self.crop_normalised)
+    }
+
+    pub fn snapshot_interval(&self) -> Duration {
+        Duration::from_secs(self.snapshot_interval)
+    }
+
+    /// Returns the output format, taken from the extension of the output file
+    /// if it is not given.
+    pub fn format(&self) -> Result<ImageFormat, clap::Error> {
+        let extension = self.output.extension().and_then(|e| e.to_str());
+        match (self.format, extension) {
+            (Some(format), _) => Ok(format),
+            (None, Some(extension)) => ImageFormat::from_str(extension, true).map_err(|_| {
+                argument_error(
+                    ErrorKind::InvalidValue,
+                    format!(
+                        "unknown image format '.{extension}' for output '{}', use --format to set one",
+                        self.output.display()
+                    ),
+                )
+            }),
+            (None, None) => Ok(ImageFormat::Png),
+        }
+    }
+
+    /// Returns the indexes of the cameras to render.
+    pub fn camera_indexes(&self, num_cameras: usize) -> Result<Vec<usize>, clap::Error> {
+        if self.cameras.is_empty() {
+            return Ok((0..num_cameras).collect());
+        }
+        self.cameras
+            .iter()
+            .map(|&number| match number as usize {
+                number if number <= num_cameras => Ok(number - 1),
+                number => Err(argument_error(
+                    ErrorKind::InvalidValue,
+                    format!(
+                        "camera {number} does not exist, scene '{}' has {num_cameras} camera(s)",
+                        self.scene
+                    ),
+                )),
+            })
+            .collect()
+    }
+
+    /// Returns the file to save the image for a camera to. `camera_index` is
+    /// only added to the file name if more than one camera is rendered.
+    pub fn output_file(
+        &self,
+        format: ImageFormat,
+        camera_index: usize,
+        num_rendered: usize,
+    ) -> PathBuf {
+        let stem = self
+            .output
+            .file_stem()
+            .map_or("result".into(), |stem| stem.to_string_lossy());
+        let file_name = match num_rendered {
+            1 => format!("{stem}.{}", format.extension()),
+            _ => format!("{stem}_{}.{}", camera_index + 1, format.extension()),
+        };
+        self.output
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(file_name)
+    }
+
+    /// Returns the checkpoint file for a camera, with `camera_index` added to
+    /// the file name if more than one camera is rendered.
+    pub fn checkpoint_file(&self, camera_index: usize, num_rendered: usize) -> Option<PathBuf> {
+        let checkpoint = self.checkpoint.as_ref()?;
+        if num_rendered == 1 {
+            return Some(checkpoint.clone());
+        }
+        let stem = checkpoint
+            .file_stem()
+            .map_or("checkpoint".into(), |stem| stem.to_string_lossy());
+        let file_name = match checkpoint.extension() {
+            Some(extension) => format!("{stem}_{}.{}", camera_index + 1, extension.display()),
+            None => format!("{stem}_{}", camera_index + 1),
+        };
+        Some(checkpoint.with_file_name(file_name))
+    }
+
+    /// Returns the file to save the denoised image for a camera to, the
+    /// image file with "_denoised" added to its name.
+    pub fn denoised_file(
+        &self,
+        format: ImageFormat,
+        camera_index: usize,
+        num_rendered: usize,
+    ) -> PathBuf {
+        let output_file = self.output_file(format, camera_index, num_rendered);
+        let stem = output_file
+            .file_stem()
+            .map_or("result".into(), |stem| stem.to_string_lossy());
+        output_file.with_file_name(format!("{stem}_denoised.{}", format.extension()))
+    }
+
+    /// Returns the passes to render alongside the image, without repeats.
+    pub fn aovs(&self) -> Vec<Aov> {
+        let mut aovs = Vec::new();
+        for &arg in &self.aovs {
+            let passes: &[Aov] = match arg {
+                AovArg::Depth => &[Aov::Depth],
+                AovArg::Normal => &[Aov::Normal],
+                AovArg::Albedo => &[Aov::Albedo],
+                AovArg::ObjectId => &[Aov::ObjectId],
+                AovArg::MaterialId => &[Aov::MaterialId],
+                AovArg::Uv => &[Aov::Uv],
+                AovArg::Motion => &[Aov::Motion],
+                AovArg::All => &Aov::ALL,
+            };
+            for &aov in passes {
+                if !aovs.contains(&aov) {
+                    aovs.push(aov);
+                }
+            }
+        }
+        aovs
+    }
+
+    /// Returns the OpenEXR file to save a pass of a camera to, the image file
+    /// with the name of the pass added to its name.
+    pub fn aov_file(
+        &self,
+        aov: Aov,
+        format: ImageFormat,
+        camera_index: usize,
+        num_rendered: usize,
+    ) -> PathBuf {
+        let output_file = self.output_file(format, camera_index, num_rendered);
+        let stem = output_file
+            .file_stem()
+            .map_or("result".into(), |stem| stem.to_string_lossy());
+        output_file.with_file_name(format!("{stem}_{}.exr", aov.name()))
+    }
+
+    /// Returns the file to save the sample heatmap for a camera to, the
+    /// image file with "_samples" added to its name.
+    pub fn sample_heatmap_file(
+        &self,
+        format: ImageFormat,
+        camera_index: usize,
+        num_rendered: usize,
+    ) -> PathBuf {
+        let output_file = self.output_file(format, camera_index, num_rendered);
+        let stem = output_file
+            .file_stem()
+            .map_or("result".into(), |stem| stem.to_string_lossy());
+        output_file.with_file_name(format!("{stem}_samples.{}", format.extension()))
+    }
+}
+
+/// Returns an error that is reported in the same way as errors found while
+/// parsing the arguments.
+pub fn argument_error(kind: ErrorKind, message: impl std::fmt::Display) -> clap::Error {
+    Cli::command().error(kind, message)
+}
@@ -1,8 +1,8 @@
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign};
 
-use crate::utilities::clamp;
+use crate::{Error, Result, utilities::clamp};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub struct RGB(pub f64, pub f64, pub f64);
 
 impl RGB {
@@ -39,15 +39,22 @@ impl RGB {
         RGB(r as f64 / 255.0, g as f64 / 255.0, b as f64 / 255.0)
     }
 
-    pub fn from_hash(hash: &str) -> Self {
-        assert!(hash.starts_with("#"));
-        assert_eq!(hash.len(), 7);
-
-        let r: usize = usize::from_str_radix(&hash[1..3], 16).unwrap();
-        let g: usize = usize::from_str_radix(&hash[3..5], 16).unwrap();
-        let b: usize = usize::from_str_radix(&hash[5..7], 16).unwrap();
-
-        Self::from_integers(r, g, b)
+    /// Parses a hash colour like "#ffd700".
+    pub fn from_hash(hash: &str) -> Result<Self> {
+        let valid = hash.len() == 7
+            && hash.starts_with('#')
+            && hash[1..].chars().all(|c| c.is_ascii_hexdigit());
+        if !valid {
+            return Err(Error::InvalidColour(hash.to_string()));
+        }
+
+        let component = |i: usize| usize::from_str_radix(&hash[i..i + 2], 16).unwrap();
+
+        Ok(Self::from_integers(
+            component(1),
+            component(3),
+            component(5),
+        ))
     }
 }
 
@@ -0,0 +1,117 @@
+use std::{fmt, io, path::PathBuf};
+
+use crate::vec3d::Point3d;
+
+/// Errors returned by the ray tracer, for example when an asset or scene file
+/// can not be read.
+#[derive(Debug)]
+pub enum Error {
+    /// A file could not be read or written.
+    Io { path: PathBuf, source: io::Error },
+    /// An image could not be decoded or encoded.
+    Image {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+    /// A file was read but its contents are not valid. `line` starts from 1.
+    Parse {
+        path: Option<PathBuf>,
+        line: usize,
+        message: String,
+    },
+    /// A colour hash that is not in the form "#rrggbb".
+    InvalidColour(String),
+    /// A bounding box where min is greater than max.
+    InvalidBoundingBox { min: Point3d, max: Point3d },
+    /// A BVH was built from an empty list of objects.
+    EmptyBvh,
+    /// A BVH was built from objects that do not have bounding boxes.
+    MissingBoundingBox,
+    /// Settings that can not be rendered, such as an image without any pixels.
+    InvalidSettings(String),
+    /// A material name that is not in the material library.
+    UnknownMaterial(String),
+    /// A texture name that is not in the material library.
+    UnknownTexture(String),
+    /// A named material or texture that is defined in terms of itself.
+    CyclicDefinition(String),
+    /// A checkpoint file that can not be read or resumed by this render.
+    InvalidCheckpoint(String),
+}
+
+/// Result type used throughout the ray tracer.
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Creates a parse error for a line without a known file, use `with_path`
+    /// to add the file.
+    pub fn parse(line: usize, message: impl Into<String>) -> Self {
+        Error::Parse {
+            path: None,
+            line,
+            message: message.into(),
+        }
+    }
+
+    /// Adds the file a parse error occurred in. Errors that already have a
+    /// file, such as errors in an included scene file, keep it.
+    pub fn with_path(self, file_path: impl Into<PathBuf>) -> Self {
+        match self {
+            Error::Parse {
+                path: None,
+                line,
+                message,
+            } => Error::Parse {
+                path: Some(file_path.into()),
+                line,
+                message,
+            },
+            error => error,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "{}: {source}", path.display()),
+            Error::Image { path, source } => write!(f, "{}: {source}", path.display()),
+            Error::Parse {
+                path: Some(path),
+                line,
+                message,
+            } => write!(f, "{}:{line}: {message}", path.display()),
+            Error::Parse {
+                path: None,
+                line,
+                message,
+            } => write!(f, "line {line}: {message}"),
+            Error::InvalidColour(hash) => write!(
+                f,
+                "invalid colour \"{hash}\", expected a hash colour like \"#ffd700\""
+            ),
+            Error::InvalidBoundingBox { min, max } => {
+                write!(f, "min must be less than max; min: {min:?}, max: {max:?}")
+            }
+            Error::EmptyBvh => write!(f, "can not build a BVH without any objects"),
+            Error::MissingBoundingBox => {
+                write!(f, "can not build a BVH from objects without bounding boxes")
+            }
+            Error::InvalidSettings(message) => write!(f, "invalid render settings: {message}"),
+            Error::UnknownMaterial(name) => write!(f, "unknown material '{name}'"),
+            Error::UnknownTexture(name) => write!(f, "unknown texture '{name}'"),
+            Error::CyclicDefinition(name) => write!(f, "'{name}' is defined in terms of itself"),
+            Error::InvalidCheckpoint(message) => write!(f, "invalid checkpoint: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Image { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -24,6 +24,12 @@ pub struct HitRecord<'a> {
     /// Whether or not this is the external face of the surface, this is useful
     /// to know for dielectrics.
     pub front_face: bool,
+    /// How fast the surface at the hit point is moving, in scene units per
+    /// unit of time. Zero for objects that do not move.
+    pub velocity: Vec3d,
+    /// Identifies the object hit among the objects of a scene, counted from
+    /// 1, see `Aov::ObjectId`. 0 until the renderer sets it.
+    pub object_id: usize,
 }
 
 impl<'a> HitRecord<'a> {
@@ -68,9 +74,17 @@ impl<'a> HitRecord<'a> {
             u,
             v,
             front_face,
+            velocity: Vec3d::new(0.0, 0.0, 0.0),
+            object_id: 0,
         }
     }
 
+    /// Sets how fast the surface at the hit point is moving.
+    pub fn with_velocity(mut self, velocity: Vec3d) -> Self {
+        self.velocity = velocity;
+        self
+    }
+
     pub fn get_face_normal(ray: &Ray, outward_normal: Vec3d) -> (bool, Vec3d) {
         let front_face = ray.direction.dot(&outward_normal) < 0.0;
         let normal = if front_face {
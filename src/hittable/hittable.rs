@@ -1,14 +1,56 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, sync::Arc};
 
-use crate::{bvh::bounding_box::BoundingBox, ray::Ray};
+use crate::{
+    bvh::bounding_box::BoundingBox,
+    ray::Ray,
+    vec3d::{Point3d, Vec3d},
+};
 
 use super::hit_record::HitRecord;
 
 /// Trait for all objects that can be hit by a ray. These objects need to be
 /// shared between threads so must also be Sync and Send.
 pub trait Hittable: Send + Sync {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>>;
     fn bounding_box(&self, time0: f64, time1: f64) -> Option<BoundingBox>;
+
+    /// Returns the probability density, over solid angle, of
+    /// `random_direction` picking `direction` from `origin`. Only objects that
+    /// can be sampled as lights implement this, the default is 0.
+    fn pdf_value(&self, _origin: Point3d, _direction: Vec3d) -> f64 {
+        0.0
+    }
+
+    /// Returns a random direction from `origin` towards the object.
+    fn random_direction(&self, _origin: Point3d) -> Vec3d {
+        Vec3d::new(1.0, 0.0, 0.0)
+    }
+
+    /// Returns a random point on the object's surface, as a hit record with
+    /// the outward normal, and the probability density of picking it per
+    /// unit area. Only objects that can be sampled as lights implement this,
+    /// the default is `None`.
+    fn sample_surface(&self) -> Option<(HitRecord<'_>, f64)> {
+        None
+    }
+
+    /// Returns the probability density, per unit area, of `sample_surface`
+    /// picking the point where `ray` hits the object at distance `t`.
+    fn surface_pdf(&self, _ray: &Ray, _t: f64) -> f64 {
+        0.0
+    }
+}
+
+/// Returns a Monte Carlo estimate of the integral of `object`'s `pdf_value`
+/// over all directions from `origin`, which is 1 for any object that can be
+/// sampled as a light.
+#[cfg(test)]
+pub(crate) fn pdf_integral(object: &dyn Hittable, origin: Point3d) -> f64 {
+    let samples = 200_000;
+    let sum: f64 = (0..samples)
+        .map(|_| object.pdf_value(origin, crate::utilities::random_unit_vec()))
+        .sum();
+    sum * 4.0 * std::f64::consts::PI / samples as f64
 }
 
 impl Debug for dyn Hittable {
@@ -16,3 +58,29 @@ impl Debug for dyn Hittable {
         f.debug_struct("dyn Hittable").finish()
     }
 }
+
+impl Hittable for Arc<dyn Hittable> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        self.as_ref().hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<BoundingBox> {
+        self.as_ref().bounding_box(time0, time1)
+    }
+
+    fn pdf_value(&self, origin: Point3d, direction: Vec3d) -> f64 {
+        self.as_ref().pdf_value(origin, direction)
+    }
+
+    fn random_direction(&self, origin: Point3d) -> Vec3d {
+        self.as_ref().random_direction(origin)
+    }
+
+    fn sample_surface(&self) -> Option<(HitRecord<'_>, f64)> {
+        self.as_ref().sample_surface()
+    }
+
+    fn surface_pdf(&self, ray: &Ray, t: f64) -> f64 {
+        self.as_ref().surface_pdf(ray, t)
+    }
+}
@@ -1,8 +1,11 @@
 use std::sync::Arc;
 
 use crate::{
-    bvh::bounding_box::BoundingBox, hittable::hittable::Hittable, ray::Ray,
-    utilities::surrounding_box_option,
+    bvh::bounding_box::BoundingBox,
+    hittable::hittable::Hittable,
+    ray::Ray,
+    utilities::{random_rng_int, surrounding_box_option},
+    vec3d::{Point3d, Vec3d},
 };
 
 use super::hit_record::HitRecord;
@@ -29,11 +32,15 @@ impl HittableList {
             bounding_box,
         }
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
 }
 
 impl Hittable for HittableList {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        let mut hit_record: Option<HitRecord> = None;
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let mut hit_record: Option<HitRecord<'_>> = None;
         let mut closest_so_far = t_max;
 
         for item in self.items.iter() {
@@ -49,4 +56,42 @@ impl Hittable for HittableList {
     fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<BoundingBox> {
         self.bounding_box
     }
+
+    /// Each item is equally likely to be picked by `random_direction`, so the
+    /// density is the average of the items' densities.
+    fn pdf_value(&self, origin: Point3d, direction: Vec3d) -> f64 {
+        let sum: f64 = self
+            .items
+            .iter()
+            .map(|item| item.pdf_value(origin, direction))
+            .sum();
+        sum / self.items.len() as f64
+    }
+
+    fn random_direction(&self, origin: Point3d) -> Vec3d {
+        let index = random_rng_int(0, self.items.len());
+        self.items[index].random_direction(origin)
+    }
+
+    /// Each item is equally likely to be picked, then a point on it.
+    fn sample_surface(&self) -> Option<(HitRecord<'_>, f64)> {
+        let index = random_rng_int(0, self.items.len());
+        let (hit_record, pdf) = self.items[index].sample_surface()?;
+        Some((hit_record, pdf / self.items.len() as f64))
+    }
+
+    /// The items hit at distance `t` are the ones `sample_surface` could
+    /// have picked the point on.
+    fn surface_pdf(&self, ray: &Ray, t: f64) -> f64 {
+        let sum: f64 = self
+            .items
+            .iter()
+            .filter(|item| {
+                item.hit(ray, 0.001, f64::MAX)
+                    .is_some_and(|hit| (hit.t - t).abs() <= 1e-9 * t.max(1.0))
+            })
+            .map(|item| item.surface_pdf(ray, t))
+            .sum();
+        sum / self.items.len() as f64
+    }
 }
@@ -65,10 +65,28 @@ impl<H: Hittable> RotateX<H> {
             },
         }
     }
+
+    /// Turns a point or direction into the object's frame.
+    fn to_object(&self, v: Vec3d) -> Vec3d {
+        Vec3d::new(
+            v.x,
+            self.sin_theta * v.z + self.cos_theta * v.y,
+            self.cos_theta * v.z - self.sin_theta * v.y,
+        )
+    }
+
+    /// Turns a point or direction from the object's frame back again.
+    fn to_world(&self, v: Vec3d) -> Vec3d {
+        Vec3d::new(
+            v.x,
+            -self.sin_theta * v.z + self.cos_theta * v.y,
+            self.cos_theta * v.z + self.sin_theta * v.y,
+        )
+    }
 }
 
 impl<H: Hittable + Clone> Hittable for RotateX<H> {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
         let origin = Vec3d::new(
             ray.origin.x,
             self.sin_theta * ray.origin.z + self.cos_theta * ray.origin.y,
@@ -94,17 +112,18 @@ impl<H: Hittable + Clone> Hittable for RotateX<H> {
                     -self.sin_theta * hr.normal.z + self.cos_theta * hr.normal.y,
                     self.cos_theta * hr.normal.z + self.sin_theta * hr.normal.y,
                 );
+                // The velocity turns with the object
+                let velocity = Vec3d::new(
+                    hr.velocity.x,
+                    -self.sin_theta * hr.velocity.z + self.cos_theta * hr.velocity.y,
+                    self.cos_theta * hr.velocity.z + self.sin_theta * hr.velocity.y,
+                );
                 let (front_face, normal) = HitRecord::get_face_normal(&rotated_ray, temp_normal);
 
-                Some(HitRecord::new(
-                    point,
-                    normal,
-                    hr.material,
-                    hr.t,
-                    hr.u,
-                    hr.v,
-                    front_face,
-                ))
+                Some(
+                    HitRecord::new(point, normal, hr.material, hr.t, hr.u, hr.v, front_face)
+                        .with_velocity(velocity),
+                )
             }
             None => None,
         }
@@ -113,4 +132,38 @@ impl<H: Hittable + Clone> Hittable for RotateX<H> {
     fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<BoundingBox> {
         self.bounding_box
     }
+
+    /// Turning the object does not change the size of the cone of directions
+    /// towards it, so the density is the object's in its own frame.
+    fn pdf_value(&self, origin: Point3d, direction: Vec3d) -> f64 {
+        self.object
+            .pdf_value(self.to_object(origin), self.to_object(direction))
+    }
+
+    fn random_direction(&self, origin: Point3d) -> Vec3d {
+        self.to_world(self.object.random_direction(self.to_object(origin)))
+    }
+
+    fn sample_surface(&self) -> Option<(HitRecord<'_>, f64)> {
+        let (hr, pdf) = self.object.sample_surface()?;
+        let hit_record = HitRecord::new(
+            self.to_world(hr.point),
+            self.to_world(hr.normal),
+            hr.material,
+            hr.t,
+            hr.u,
+            hr.v,
+            hr.front_face,
+        );
+        Some((hit_record, pdf))
+    }
+
+    fn surface_pdf(&self, ray: &Ray, t: f64) -> f64 {
+        let rotated_ray = Ray::new(
+            self.to_object(ray.origin),
+            self.to_object(ray.direction),
+            ray.time,
+        );
+        self.object.surface_pdf(&rotated_ray, t)
+    }
 }
@@ -2,6 +2,7 @@ use crate::{
     bvh::bounding_box::BoundingBox,
     hittable::{hit_record::HitRecord, hittable::Hittable},
     ray::Ray,
+    vec3d::{Point3d, Vec3d},
 };
 
 /// A scale instance to handle "scaling" a hittable object. Does not actually
@@ -19,10 +20,21 @@ impl<H: Hittable> Scale<H> {
     pub fn new(x: f64, y: f64, z: f64, object: H) -> Self {
         Self { x, y, z, object }
     }
+
+    /// Scales a point or direction into the object's frame.
+    fn to_object(&self, v: Vec3d) -> Vec3d {
+        v.scale(1.0 / self.x, 1.0 / self.y, 1.0 / self.z)
+    }
+
+    /// Returns how much larger a patch of the surface with the unit `normal`,
+    /// in the object's frame, is once scaled.
+    fn area_scale(&self, normal: Vec3d) -> f64 {
+        (self.x * self.y * self.z).abs() * self.to_object(normal).len()
+    }
 }
 
 impl<H: Hittable> Hittable for Scale<H> {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
         let scaled_ray = Ray::new(
             ray.origin.scale(1.0 / self.x, 1.0 / self.y, 1.0 / self.z),
             ray.direction
@@ -33,15 +45,18 @@ impl<H: Hittable> Hittable for Scale<H> {
         match self.object.hit(&scaled_ray, t_min, t_max) {
             Some(hr) => {
                 let (front_face, normal) = HitRecord::get_face_normal(&scaled_ray, hr.normal);
-                Some(HitRecord::new(
-                    hr.point.scale(self.x, self.y, self.z),
-                    normal,
-                    hr.material,
-                    hr.t,
-                    hr.u,
-                    hr.v,
-                    front_face,
-                ))
+                Some(
+                    HitRecord::new(
+                        hr.point.scale(self.x, self.y, self.z),
+                        normal,
+                        hr.material,
+                        hr.t,
+                        hr.u,
+                        hr.v,
+                        front_face,
+                    )
+                    .with_velocity(hr.velocity.scale(self.x, self.y, self.z)),
+                )
             }
             None => None,
         }
@@ -55,4 +70,117 @@ impl<H: Hittable> Hittable for Scale<H> {
             )
         })
     }
+
+    /// Directions are picked by scaling the object's directions, which
+    /// squeezes the density where the object is stretched and spreads it
+    /// where the object is squashed.
+    fn pdf_value(&self, origin: Point3d, direction: Vec3d) -> f64 {
+        let object_direction = self.to_object(direction);
+        let pdf = self
+            .object
+            .pdf_value(self.to_object(origin), object_direction);
+        let stretch = direction.len() / object_direction.len();
+        pdf * stretch * stretch * stretch / (self.x * self.y * self.z).abs()
+    }
+
+    fn random_direction(&self, origin: Point3d) -> Vec3d {
+        self.object
+            .random_direction(self.to_object(origin))
+            .scale(self.x, self.y, self.z)
+    }
+
+    fn sample_surface(&self) -> Option<(HitRecord<'_>, f64)> {
+        let (hr, pdf) = self.object.sample_surface()?;
+        let normal = hr.normal.unit_vector();
+        let hit_record = HitRecord::new(
+            hr.point.scale(self.x, self.y, self.z),
+            self.to_object(normal).unit_vector(),
+            hr.material,
+            hr.t,
+            hr.u,
+            hr.v,
+            hr.front_face,
+        );
+        Some((hit_record, pdf / self.area_scale(normal)))
+    }
+
+    /// The density depends on how the surface is stretched where the ray
+    /// hits it, so the object is hit again to find its normal there.
+    fn surface_pdf(&self, ray: &Ray, t: f64) -> f64 {
+        let scaled_ray = Ray::new(
+            self.to_object(ray.origin),
+            self.to_object(ray.direction),
+            ray.time,
+        );
+        let tolerance = 1e-6 * t.max(1.0);
+        match self.object.hit(&scaled_ray, t - tolerance, t + tolerance) {
+            Some(hr) => {
+                self.object.surface_pdf(&scaled_ray, t) / self.area_scale(hr.normal.unit_vector())
+            }
+            None => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod scale_tests {
+    use crate::{
+        colour::RGB,
+        hittable::hittable::pdf_integral,
+        materials::Diffuse,
+        objects::{RectangleXY, Sphere},
+        utilities::with_seeded_random,
+    };
+
+    use super::*;
+
+    /// A sphere stretched along x and squashed along z.
+    fn ellipsoid() -> Scale<Sphere<Diffuse>> {
+        let sphere = Sphere::new(
+            Point3d::new(1.0, 2.0, 3.0),
+            1.5,
+            Diffuse::new(RGB(0.5, 0.5, 0.5)),
+        );
+        Scale::new(2.0, 1.0, 0.5, sphere)
+    }
+
+    #[test]
+    fn pdf_value_should_account_for_stretched_directions() {
+        let ellipsoid = ellipsoid();
+        let origin = Point3d::new(0.0, 0.0, 0.0);
+
+        let integral = with_seeded_random(5, || pdf_integral(&ellipsoid, origin));
+
+        assert!((integral - 1.0).abs() < 0.03, "integral: {integral}");
+    }
+
+    #[test]
+    fn sample_surface_should_spread_points_over_the_scaled_area() {
+        let rectangle = RectangleXY::new(0.0, 1.0, 0.0, 2.0, 0.5, Diffuse::new(RGB(0.5, 0.5, 0.5)));
+        let scaled = Scale::new(2.0, 3.0, 4.0, rectangle);
+
+        let (hr, pdf) = with_seeded_random(5, || scaled.sample_surface()).unwrap();
+
+        assert!((pdf * 12.0 - 1.0).abs() < 1e-9, "pdf: {pdf}");
+        assert!((hr.point.z - 2.0).abs() < 1e-9);
+        assert!((hr.normal.z.abs() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn surface_pdf_should_match_sample_surface() {
+        let ellipsoid = ellipsoid();
+
+        with_seeded_random(5, || {
+            for _ in 0..100 {
+                let (hr, pdf) = ellipsoid.sample_surface().unwrap();
+                let origin = hr.point + 5.0 * hr.normal;
+                let ray = Ray::new(origin, hr.point - origin, 0.0);
+                let surface_pdf = ellipsoid.surface_pdf(&ray, 1.0);
+                assert!(
+                    (surface_pdf / pdf - 1.0).abs() < 1e-6,
+                    "{surface_pdf} != {pdf}"
+                );
+            }
+        });
+    }
 }
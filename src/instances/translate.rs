@@ -2,7 +2,7 @@ use crate::{
     bvh::bounding_box::BoundingBox,
     hittable::{hit_record::HitRecord, hittable::Hittable},
     ray::Ray,
-    vec3d::Vec3d,
+    vec3d::{Point3d, Vec3d},
 };
 
 /// A translate trait to handle "moving" a hittable object. Does not actually
@@ -21,21 +21,24 @@ impl<H: Hittable> Translate<H> {
 }
 
 impl<H: Hittable> Hittable for Translate<H> {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
         let moved_ray = Ray::new(ray.origin - self.offset, ray.direction, ray.time);
 
         match self.object.hit(&moved_ray, t_min, t_max) {
             Some(hr) => {
                 let (front_face, normal) = HitRecord::get_face_normal(&moved_ray, hr.normal);
-                Some(HitRecord::new(
-                    hr.point + self.offset,
-                    normal,
-                    hr.material,
-                    hr.t,
-                    hr.u,
-                    hr.v,
-                    front_face,
-                ))
+                Some(
+                    HitRecord::new(
+                        hr.point + self.offset,
+                        normal,
+                        hr.material,
+                        hr.t,
+                        hr.u,
+                        hr.v,
+                        front_face,
+                    )
+                    .with_velocity(hr.velocity),
+                )
             }
             None => None,
         }
@@ -46,4 +49,31 @@ impl<H: Hittable> Hittable for Translate<H> {
             .bounding_box(time0, time1)
             .map(|bb| BoundingBox::new(bb.min + self.offset, bb.max + self.offset))
     }
+
+    fn pdf_value(&self, origin: Point3d, direction: Vec3d) -> f64 {
+        self.object.pdf_value(origin - self.offset, direction)
+    }
+
+    fn random_direction(&self, origin: Point3d) -> Vec3d {
+        self.object.random_direction(origin - self.offset)
+    }
+
+    fn sample_surface(&self) -> Option<(HitRecord<'_>, f64)> {
+        let (hr, pdf) = self.object.sample_surface()?;
+        let hit_record = HitRecord::new(
+            hr.point + self.offset,
+            hr.normal,
+            hr.material,
+            hr.t,
+            hr.u,
+            hr.v,
+            hr.front_face,
+        );
+        Some((hit_record, pdf))
+    }
+
+    fn surface_pdf(&self, ray: &Ray, t: f64) -> f64 {
+        let moved_ray = Ray::new(ray.origin - self.offset, ray.direction, ray.time);
+        self.object.surface_pdf(&moved_ray, t)
+    }
 }
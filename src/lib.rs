@@ -1,14 +1,20 @@
 pub mod bvh;
 pub mod camera;
 pub mod colour;
+pub mod error;
 pub mod hittable;
 pub mod instances;
 pub mod materials;
 pub mod objects;
+pub mod onb;
 pub mod ray;
 pub mod render;
 pub mod resolution;
+pub mod scene;
+pub mod scenes;
 pub mod textures;
 pub mod utilities;
 pub mod vec3d;
 pub mod volumes;
+
+pub use error::{Error, Result};
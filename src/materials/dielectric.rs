@@ -1,11 +1,11 @@
 use crate::{
     colour::RGB,
     hittable::hit_record::HitRecord,
-    ray::Ray,
     utilities::{random, reflect_vec, refract_vec},
+    vec3d::Vec3d,
 };
 
-use super::material::Material;
+use super::material::{Material, ScatterFlags, ScatterSample};
 
 #[derive(Debug, Clone, Copy)]
 pub struct Dielectric {
@@ -26,29 +26,49 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Ray, RGB)> {
+    /// Light is either reflected or refracted, picked by how much of it is
+    /// reflected, so `pdf` of the sample is the chance of that pick.
+    fn sample(&self, hit_record: &HitRecord, wo: &Vec3d) -> Option<ScatterSample> {
         let refraction_ratio = if hit_record.front_face {
             1.0 / self.refraction_index
         } else {
             self.refraction_index
         };
 
-        let unit_direction = ray_in.direction.unit_vector();
+        let unit_direction = -1.0 * *wo;
         let cos_theta = f64::min(-unit_direction.dot(&hit_record.normal), 1.0);
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
-        let cannot_refract = refraction_ratio * sin_theta > 1.0;
+        let reflectance = if refraction_ratio * sin_theta > 1.0 {
+            1.0
+        } else {
+            Self::reflectance(cos_theta, refraction_ratio)
+        };
+
+        let (direction, pdf, flags) = if reflectance > random() {
+            (
+                reflect_vec(&unit_direction, &hit_record.normal),
+                reflectance,
+                ScatterFlags::REFLECTION,
+            )
+        } else {
+            (
+                refract_vec(&unit_direction, &hit_record.normal, refraction_ratio),
+                1.0 - reflectance,
+                ScatterFlags::TRANSMISSION,
+            )
+        };
 
-        let new_direction =
-            if cannot_refract || Self::reflectance(cos_theta, refraction_ratio) > random() {
-                reflect_vec(&unit_direction, &hit_record.normal)
-            } else {
-                refract_vec(&unit_direction, &hit_record.normal, refraction_ratio)
-            };
+        Some(ScatterSample {
+            direction: direction.unit_vector(),
+            attenuation: RGB(1.0, 1.0, 1.0),
+            pdf,
+            flags: ScatterFlags::SPECULAR | flags,
+        })
+    }
 
-        Some((
-            Ray::new(hit_record.point, new_direction, ray_in.time),
-            RGB(1.0, 1.0, 1.0),
-        ))
+    /// Glass lets all light through, so it is white.
+    fn albedo(&self, _hit_record: &HitRecord) -> RGB {
+        RGB(1.0, 1.0, 1.0)
     }
 }
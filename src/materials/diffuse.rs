@@ -1,6 +1,10 @@
-use crate::{colour::RGB, hittable::hit_record::HitRecord, ray::Ray, utilities::random_unit_vec};
+use std::f64::consts::PI;
 
-use super::material::Material;
+use crate::{
+    colour::RGB, hittable::hit_record::HitRecord, utilities::random_unit_vec, vec3d::Vec3d,
+};
+
+use super::material::{Material, ScatterFlags, ScatterSample};
 
 #[derive(Debug, Clone, Copy)]
 pub struct Diffuse {
@@ -14,12 +18,30 @@ impl Diffuse {
 }
 
 impl Material for Diffuse {
-    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Ray, RGB)> {
+    fn sample(&self, hit_record: &HitRecord, wo: &Vec3d) -> Option<ScatterSample> {
         let mut scatter_direction = hit_record.normal + random_unit_vec();
         if scatter_direction.near_zero() {
             scatter_direction = hit_record.normal
         }
-        let ray_out = Ray::new(hit_record.point, scatter_direction, ray_in.time);
-        Some((ray_out, self.albedo))
+        let direction = scatter_direction.unit_vector();
+        Some(ScatterSample {
+            direction,
+            attenuation: self.albedo,
+            pdf: self.pdf(hit_record, wo, &direction),
+            flags: ScatterFlags::DIFFUSE | ScatterFlags::REFLECTION,
+        })
+    }
+
+    fn eval(&self, hit_record: &HitRecord, wo: &Vec3d, wi: &Vec3d) -> RGB {
+        self.pdf(hit_record, wo, wi) * self.albedo
+    }
+
+    /// Scattered rays follow a cosine distribution around the normal.
+    fn pdf(&self, hit_record: &HitRecord, _wo: &Vec3d, wi: &Vec3d) -> f64 {
+        (hit_record.normal.dot(wi) / PI).max(0.0)
+    }
+
+    fn albedo(&self, _hit_record: &HitRecord) -> RGB {
+        self.albedo
     }
 }
@@ -1,12 +1,11 @@
 use crate::{
     colour::RGB,
     hittable::hit_record::HitRecord,
-    ray::Ray,
     textures::{SolidColour, Texture},
-    vec3d::Point3d,
+    vec3d::{Point3d, Vec3d},
 };
 
-use super::material::Material;
+use super::material::{Material, ScatterSample};
 
 #[derive(Debug, Clone, Copy)]
 pub struct DiffuseLight<Tex: Texture> {
@@ -26,11 +25,22 @@ impl DiffuseLight<SolidColour> {
 }
 
 impl<Tex: Texture> Material for DiffuseLight<Tex> {
-    fn scatter(&self, _ray_in: &Ray, _hit_record: &HitRecord) -> Option<(Ray, RGB)> {
+    /// Lights do not scatter light, they only emit it.
+    fn sample(&self, _hit_record: &HitRecord, _wo: &Vec3d) -> Option<ScatterSample> {
         None
     }
 
     fn emitted(&self, u: f64, v: f64, p: Point3d) -> RGB {
         self.emit.value(u, v, &p)
     }
+
+    fn is_emissive(&self) -> bool {
+        true
+    }
+
+    /// The emitted colour, with channels brighter than white clipped to it.
+    fn albedo(&self, hit_record: &HitRecord) -> RGB {
+        let RGB(r, g, b) = self.emitted(hit_record.u, hit_record.v, hit_record.point);
+        RGB(r.min(1.0), g.min(1.0), b.min(1.0))
+    }
 }
@@ -1,12 +1,14 @@
+use std::f64::consts::PI;
+
 use crate::{
     colour::RGB,
     hittable::hit_record::HitRecord,
-    ray::Ray,
     textures::{SolidColour, Texture},
-    utilities::random_vec_in_unit_sphere,
+    utilities::random_unit_vec,
+    vec3d::Vec3d,
 };
 
-use super::material::Material;
+use super::material::{Material, ScatterFlags, ScatterSample};
 
 /// An isotropic material that scatters rays in a random direction, used for
 /// volumes like fog and smoke.
@@ -28,12 +30,37 @@ impl Isotropic<SolidColour> {
 }
 
 impl<Tex: Texture> Material for Isotropic<Tex> {
-    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Ray, RGB)> {
-        let scattered = Ray::new(hit_record.point, random_vec_in_unit_sphere(), ray_in.time);
+    fn sample(&self, hit_record: &HitRecord, wo: &Vec3d) -> Option<ScatterSample> {
+        let direction = random_unit_vec();
         let attenuation = self
             .albedo
             .value(hit_record.u, hit_record.v, &hit_record.point);
+        Some(ScatterSample {
+            direction,
+            attenuation,
+            pdf: self.pdf(hit_record, wo, &direction),
+            flags: ScatterFlags::DIFFUSE | ScatterFlags::REFLECTION | ScatterFlags::TRANSMISSION,
+        })
+    }
+
+    fn eval(&self, hit_record: &HitRecord, wo: &Vec3d, wi: &Vec3d) -> RGB {
+        let albedo = self
+            .albedo
+            .value(hit_record.u, hit_record.v, &hit_record.point);
+        self.pdf(hit_record, wo, wi) * albedo
+    }
+
+    /// Scattered rays are equally likely to go in any direction.
+    fn pdf(&self, _hit_record: &HitRecord, _wo: &Vec3d, _wi: &Vec3d) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn is_volume(&self) -> bool {
+        true
+    }
 
-        Some((scattered, attenuation))
+    fn albedo(&self, hit_record: &HitRecord) -> RGB {
+        self.albedo
+            .value(hit_record.u, hit_record.v, &hit_record.point)
     }
 }
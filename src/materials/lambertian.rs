@@ -1,12 +1,14 @@
+use std::f64::consts::PI;
+
 use crate::{
     colour::RGB,
     hittable::hit_record::HitRecord,
-    ray::Ray,
     textures::{SolidColour, Texture},
     utilities::random_unit_vec,
+    vec3d::Vec3d,
 };
 
-use super::material::Material;
+use super::material::{Material, ScatterFlags, ScatterSample};
 
 /// Lambertian reflectance is the property that defines an ideal "matte" or
 /// diffusely reflecting surface. This material is very similar to the Diffuse
@@ -30,15 +32,37 @@ impl Lambertian<SolidColour> {
 }
 
 impl<Tex: Texture> Material for Lambertian<Tex> {
-    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Ray, RGB)> {
+    fn sample(&self, hit_record: &HitRecord, wo: &Vec3d) -> Option<ScatterSample> {
         let mut scatter_direction = hit_record.normal + random_unit_vec();
         if scatter_direction.near_zero() {
             scatter_direction = hit_record.normal
         }
-        let ray_out = Ray::new(hit_record.point, scatter_direction, ray_in.time);
+        let direction = scatter_direction.unit_vector();
         let attenuation = self
             .albedo
             .value(hit_record.u, hit_record.v, &hit_record.point);
-        Some((ray_out, attenuation))
+        Some(ScatterSample {
+            direction,
+            attenuation,
+            pdf: self.pdf(hit_record, wo, &direction),
+            flags: ScatterFlags::DIFFUSE | ScatterFlags::REFLECTION,
+        })
+    }
+
+    fn eval(&self, hit_record: &HitRecord, wo: &Vec3d, wi: &Vec3d) -> RGB {
+        let albedo = self
+            .albedo
+            .value(hit_record.u, hit_record.v, &hit_record.point);
+        self.pdf(hit_record, wo, wi) * albedo
+    }
+
+    /// Scattered rays follow a cosine distribution around the normal.
+    fn pdf(&self, hit_record: &HitRecord, _wo: &Vec3d, wi: &Vec3d) -> f64 {
+        (hit_record.normal.dot(wi) / PI).max(0.0)
+    }
+
+    fn albedo(&self, hit_record: &HitRecord) -> RGB {
+        self.albedo
+            .value(hit_record.u, hit_record.v, &hit_record.point)
     }
 }
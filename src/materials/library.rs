@@ -0,0 +1,135 @@
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use crate::textures::Texture;
+
+use super::material::Material;
+
+/// Materials and textures shared by name. Objects hold an `Arc` handle to a
+/// material rather than their own copy of it, so a material used by many
+/// objects, such as the triangles of a model, is only stored once.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use rs_ray_tracer::{colour::RGB, materials::{Diffuse, MaterialLibrary, Metal}};
+/// let mut library = MaterialLibrary::new();
+/// library.add_material("gold", Arc::new(Metal::new(RGB(1.0, 0.84, 0.0), 0.8)));
+///
+/// // A clay render, every material except lights is replaced
+/// library.set_override(Arc::new(Diffuse::new(RGB(0.8, 0.8, 0.8))));
+/// let gold = library.material("gold").unwrap();
+/// ```
+#[derive(Clone, Default)]
+pub struct MaterialLibrary {
+    materials: HashMap<String, Arc<dyn Material>>,
+    textures: HashMap<String, Arc<dyn Texture>>,
+    override_material: Option<Arc<dyn Material>>,
+}
+
+impl MaterialLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a material, replacing any material with the same name, and
+    /// returns a handle to it.
+    pub fn add_material(
+        &mut self,
+        name: impl Into<String>,
+        material: Arc<dyn Material>,
+    ) -> Arc<dyn Material> {
+        self.materials.insert(name.into(), material.clone());
+        self.resolve(material)
+    }
+
+    /// Returns a handle to the material with the given name, or the override
+    /// material if one is set.
+    pub fn material(&self, name: &str) -> Option<Arc<dyn Material>> {
+        self.materials
+            .get(name)
+            .map(|material| self.resolve(material.clone()))
+    }
+
+    /// Adds a texture, replacing any texture with the same name, and returns a
+    /// handle to it.
+    pub fn add_texture(
+        &mut self,
+        name: impl Into<String>,
+        texture: Arc<dyn Texture>,
+    ) -> Arc<dyn Texture> {
+        self.textures.insert(name.into(), texture.clone());
+        texture
+    }
+
+    pub fn texture(&self, name: &str) -> Option<Arc<dyn Texture>> {
+        self.textures.get(name).cloned()
+    }
+
+    /// Replaces every material except lights, so the scene is still lit. The
+    /// override applies to materials looked up in the library and to
+    /// materials passed to `resolve`.
+    pub fn set_override(&mut self, material: Arc<dyn Material>) {
+        self.override_material = Some(material);
+    }
+
+    pub fn override_material(&self) -> Option<&Arc<dyn Material>> {
+        self.override_material.as_ref()
+    }
+
+    /// Returns the override material in place of `material` if one is set and
+    /// `material` is not a light, otherwise returns `material`.
+    pub fn resolve(&self, material: Arc<dyn Material>) -> Arc<dyn Material> {
+        match &self.override_material {
+            Some(override_material) if !material.is_emissive() => override_material.clone(),
+            _ => material,
+        }
+    }
+}
+
+impl fmt::Debug for MaterialLibrary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut materials: Vec<&String> = self.materials.keys().collect();
+        let mut textures: Vec<&String> = self.textures.keys().collect();
+        materials.sort();
+        textures.sort();
+        f.debug_struct("MaterialLibrary")
+            .field("materials", &materials)
+            .field("textures", &textures)
+            .field("has_override", &self.override_material.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod library_tests {
+    use crate::{
+        colour::RGB,
+        materials::{Diffuse, DiffuseLight, Metal},
+    };
+
+    use super::*;
+
+    #[test]
+    fn material_should_return_shared_handle() {
+        let mut library = MaterialLibrary::new();
+        let gold = library.add_material("gold", Arc::new(Metal::new(RGB(1.0, 0.84, 0.0), 0.8)));
+
+        assert!(Arc::ptr_eq(&gold, &library.material("gold").unwrap()));
+        assert!(library.material("silver").is_none());
+    }
+
+    #[test]
+    fn override_should_replace_every_material_except_lights() {
+        let mut library = MaterialLibrary::new();
+        library.add_material("gold", Arc::new(Metal::new(RGB(1.0, 0.84, 0.0), 0.8)));
+        let light = library.add_material(
+            "light",
+            Arc::new(DiffuseLight::build_from_colour(RGB(4.0, 4.0, 4.0))),
+        );
+        let clay: Arc<dyn Material> = Arc::new(Diffuse::new(RGB(0.8, 0.8, 0.8)));
+
+        library.set_override(clay.clone());
+
+        assert!(Arc::ptr_eq(&library.material("gold").unwrap(), &clay));
+        assert!(Arc::ptr_eq(&library.material("light").unwrap(), &light));
+    }
+}
@@ -1,15 +1,283 @@
-use crate::{colour::RGB, hittable::hit_record::HitRecord, ray::Ray, vec3d::Point3d};
+use std::{ops::BitOr, sync::Arc};
+
+use crate::{
+    colour::RGB,
+    hittable::hit_record::HitRecord,
+    ray::Ray,
+    vec3d::{Point3d, Vec3d},
+};
 
 /// Represents the material of and object, it describes how light will reflect
 /// or is emitted by the object. Materials need to be shared between threads
 /// safely so they must also implement Send and Sync.
+///
+/// Scattering is described by directions leaving the hit point: `wo` points
+/// back towards where the ray came from and `wi` is the direction light
+/// arrives from, both unit vectors.
 pub trait Material: Send + Sync {
+    /// Picks a direction to scatter light in for a ray arriving from `wo`.
+    /// Returns `None` if the ray is absorbed.
+    fn sample(&self, hit_record: &HitRecord, wo: &Vec3d) -> Option<ScatterSample>;
+
+    /// Returns the light scattered towards `wo` for each unit of light
+    /// arriving from `wi`, per unit solid angle and including the cosine
+    /// term. Materials that only scatter into single directions, such as
+    /// mirrors and glass, return black for every pair of directions.
+    fn eval(&self, _hit_record: &HitRecord, _wo: &Vec3d, _wi: &Vec3d) -> RGB {
+        RGB(0.0, 0.0, 0.0)
+    }
+
+    /// Returns the probability density, over solid angle, of `sample`
+    /// picking `wi`. Like `eval`, this is 0 for materials that only scatter
+    /// into single directions.
+    fn pdf(&self, _hit_record: &HitRecord, _wo: &Vec3d, _wi: &Vec3d) -> f64 {
+        0.0
+    }
+
     /// Returns scattered ray and an attenuation colour
-    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Ray, RGB)>;
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Ray, RGB)> {
+        let wo = -1.0 * ray_in.direction.unit_vector();
+        self.sample(hit_record, &wo).map(|sample| {
+            let ray_out = Ray::new(hit_record.point, sample.direction, ray_in.time);
+            (ray_out, sample.attenuation)
+        })
+    }
 
     /// Return the colour of emitted light. Defaults to black for non-emissive
     /// materials.
     fn emitted(&self, _u: f64, _v: f64, _p: Point3d) -> RGB {
         RGB(0.0, 0.0, 0.0)
     }
+
+    /// Returns true for materials that emit light.
+    fn is_emissive(&self) -> bool {
+        false
+    }
+
+    /// Returns true for materials of volumes such as smoke, which scatter
+    /// light at points inside the volume rather than at a surface. `eval`
+    /// has no cosine term for them and the normal of their hits is
+    /// arbitrary.
+    fn is_volume(&self) -> bool {
+        false
+    }
+
+    /// Returns the colour of the surface at a hit without any lighting, as
+    /// seen in the albedo pass of a render. Defaults to black.
+    fn albedo(&self, _hit_record: &HitRecord) -> RGB {
+        RGB(0.0, 0.0, 0.0)
+    }
+
+    /// Returns an address that identifies the material. Handles to the same
+    /// material, such as the `Arc` of a named material, share it.
+    fn identity(&self) -> usize {
+        self as *const Self as *const () as usize
+    }
+}
+
+impl Material for Arc<dyn Material> {
+    fn sample(&self, hit_record: &HitRecord, wo: &Vec3d) -> Option<ScatterSample> {
+        self.as_ref().sample(hit_record, wo)
+    }
+
+    fn eval(&self, hit_record: &HitRecord, wo: &Vec3d, wi: &Vec3d) -> RGB {
+        self.as_ref().eval(hit_record, wo, wi)
+    }
+
+    fn pdf(&self, hit_record: &HitRecord, wo: &Vec3d, wi: &Vec3d) -> f64 {
+        self.as_ref().pdf(hit_record, wo, wi)
+    }
+
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Ray, RGB)> {
+        self.as_ref().scatter(ray_in, hit_record)
+    }
+
+    fn emitted(&self, u: f64, v: f64, p: Point3d) -> RGB {
+        self.as_ref().emitted(u, v, p)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.as_ref().is_emissive()
+    }
+
+    fn is_volume(&self) -> bool {
+        self.as_ref().is_volume()
+    }
+
+    fn albedo(&self, hit_record: &HitRecord) -> RGB {
+        self.as_ref().albedo(hit_record)
+    }
+
+    fn identity(&self) -> usize {
+        self.as_ref().identity()
+    }
+}
+
+/// A direction picked by `Material::sample`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScatterSample {
+    /// The unit direction `wi` light is scattered from.
+    pub direction: Vec3d,
+    /// The colour the light is multiplied by, `eval` divided by `pdf`.
+    pub attenuation: RGB,
+    /// The probability density of picking `direction`. For specular
+    /// scattering this is instead the probability of picking `direction`
+    /// over the material's other specular directions, such as reflecting
+    /// rather than refracting.
+    pub pdf: f64,
+    pub flags: ScatterFlags,
+}
+
+/// The kind of scattering a sample came from, combined with `|`, e.g.
+/// `ScatterFlags::GLOSSY | ScatterFlags::REFLECTION`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ScatterFlags(u8);
+
+impl ScatterFlags {
+    /// Light scattered back to the side it arrived from.
+    pub const REFLECTION: ScatterFlags = ScatterFlags(1);
+    /// Light passing through to the other side.
+    pub const TRANSMISSION: ScatterFlags = ScatterFlags(1 << 1);
+    /// Light scattered over a wide range of directions.
+    pub const DIFFUSE: ScatterFlags = ScatterFlags(1 << 2);
+    /// Light scattered around a preferred direction.
+    pub const GLOSSY: ScatterFlags = ScatterFlags(1 << 3);
+    /// Light scattered into a single direction. The direction can only be
+    /// found by sampling, so `eval` and `pdf` are 0 for it.
+    pub const SPECULAR: ScatterFlags = ScatterFlags(1 << 4);
+
+    /// Returns true if every flag in `other` is set.
+    pub fn contains(self, other: ScatterFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns true for scattering into a single direction, which light
+    /// sampling can not find.
+    pub fn is_specular(self) -> bool {
+        self.contains(ScatterFlags::SPECULAR)
+    }
+}
+
+impl BitOr for ScatterFlags {
+    type Output = ScatterFlags;
+
+    fn bitor(self, rhs: ScatterFlags) -> Self::Output {
+        ScatterFlags(self.0 | rhs.0)
+    }
+}
+
+#[cfg(test)]
+mod material_tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+    use crate::{
+        materials::{Dielectric, Diffuse, DiffuseLight, Isotropic, Lambertian, Metal},
+        utilities::{random_unit_vec, with_seeded_random},
+    };
+
+    const SAMPLES: usize = 100_000;
+
+    fn hit_record(material: &dyn Material) -> HitRecord<'_> {
+        HitRecord::new(
+            Point3d::new(0.0, 0.0, 0.0),
+            Vec3d::new(0.0, 1.0, 0.0),
+            material,
+            1.0,
+            0.5,
+            0.5,
+            true,
+        )
+    }
+
+    /// Checks that samples agree with `eval` and `pdf`, and that the pdf
+    /// integrates to the share of rays that are not absorbed.
+    fn check_sampling(material: &dyn Material) {
+        let hr = hit_record(material);
+        let wo = Vec3d::new(1.0, 1.0, 0.0).unit_vector();
+
+        with_seeded_random(1, || {
+            let mut scattered = 0;
+            let mut pdf_integral = 0.0;
+            for _ in 0..SAMPLES {
+                if let Some(sample) = material.sample(&hr, &wo) {
+                    scattered += 1;
+                    let pdf = material.pdf(&hr, &wo, &sample.direction);
+                    assert!((sample.pdf - pdf).abs() <= 1e-9 * pdf, "{sample:?}, {pdf}");
+                    let eval = material.eval(&hr, &wo, &sample.direction);
+                    assert!((eval.0 / pdf - sample.attenuation.0).abs() < 1e-9);
+                }
+                pdf_integral += 4.0 * PI * material.pdf(&hr, &wo, &random_unit_vec());
+            }
+
+            let scattered = scattered as f64 / SAMPLES as f64;
+            let pdf_integral = pdf_integral / SAMPLES as f64;
+            assert!(
+                (pdf_integral - scattered).abs() < 0.03,
+                "pdf integral: {pdf_integral}, scattered: {scattered}"
+            );
+        });
+    }
+
+    #[test]
+    fn diffuse_materials_should_sample_their_pdf() {
+        let colour = RGB(0.2, 0.4, 0.6);
+        check_sampling(&Lambertian::build_from_colour(colour));
+        check_sampling(&Diffuse::new(colour));
+        check_sampling(&Isotropic::build_from_colour(colour));
+    }
+
+    #[test]
+    fn fuzzy_metal_should_sample_its_pdf() {
+        for fuzz in [0.3, 1.0, 1.5] {
+            let metal = Metal::new(RGB(0.8, 0.6, 0.2), fuzz);
+            let hr = hit_record(&metal);
+            let wo = Vec3d::new(1.0, 1.0, 0.0).unit_vector();
+            let sample = with_seeded_random(1, || (0..).find_map(|_| metal.sample(&hr, &wo)));
+            assert_eq!(
+                sample.unwrap().flags,
+                ScatterFlags::GLOSSY | ScatterFlags::REFLECTION
+            );
+
+            check_sampling(&metal);
+        }
+    }
+
+    #[test]
+    fn specular_materials_should_only_be_found_by_sampling() {
+        let wo = Vec3d::new(1.0, 1.0, 0.0).unit_vector();
+        let mirror = Metal::new(RGB(0.8, 0.8, 0.8), 0.0);
+        let hr = hit_record(&mirror);
+        let sample = mirror.sample(&hr, &wo).unwrap();
+        assert!(sample.flags.is_specular());
+        assert!((sample.direction - Vec3d::new(-1.0, 1.0, 0.0).unit_vector()).len() < 1e-12);
+        assert_eq!(mirror.pdf(&hr, &wo, &sample.direction), 0.0);
+        assert_eq!(mirror.eval(&hr, &wo, &sample.direction), RGB(0.0, 0.0, 0.0));
+
+        let glass = Dielectric::new(1.5);
+        let hr = hit_record(&glass);
+        with_seeded_random(1, || {
+            for _ in 0..100 {
+                let sample = glass.sample(&hr, &wo).unwrap();
+                assert!(sample.flags.is_specular());
+                assert!(
+                    sample.flags.contains(ScatterFlags::REFLECTION)
+                        != sample.flags.contains(ScatterFlags::TRANSMISSION)
+                );
+                assert!(sample.pdf > 0.0 && sample.pdf <= 1.0);
+                assert_eq!(glass.pdf(&hr, &wo, &sample.direction), 0.0);
+            }
+        });
+    }
+
+    #[test]
+    fn lights_should_not_scatter() {
+        let light = DiffuseLight::build_from_colour(RGB(4.0, 4.0, 4.0));
+        let hr = hit_record(&light);
+        let wo = Vec3d::new(0.0, 1.0, 0.0);
+
+        assert!(light.sample(&hr, &wo).is_none());
+        assert_eq!(light.eval(&hr, &wo, &wo), RGB(0.0, 0.0, 0.0));
+        assert_eq!(light.pdf(&hr, &wo, &wo), 0.0);
+    }
 }
@@ -1,11 +1,13 @@
+use std::f64::consts::PI;
+
 use crate::{
     colour::RGB,
     hittable::hit_record::HitRecord,
-    ray::Ray,
     utilities::{random_vec_in_unit_sphere, reflect_vec},
+    vec3d::Vec3d,
 };
 
-use super::material::Material;
+use super::material::{Material, ScatterFlags, ScatterSample};
 
 #[derive(Debug, Clone, Copy)]
 pub struct Metal {
@@ -17,16 +19,64 @@ impl Metal {
     pub fn new(albedo: RGB, fuzz: f64) -> Self {
         Metal { albedo, fuzz }
     }
+
+    /// Returns the probability density of `wi` being picked by fuzzing the
+    /// mirror direction `reflected`, which is picking a point uniformly in a
+    /// ball of radius `fuzz` around the tip of `reflected` and scattering
+    /// towards it. The density is the volume of the ball along `wi`, found
+    /// from where `wi` enters and leaves the ball.
+    fn fuzz_pdf(&self, reflected: &Vec3d, wi: &Vec3d) -> f64 {
+        let b = wi.dot(reflected);
+        let discriminant = b * b - (1.0 - self.fuzz * self.fuzz);
+        if discriminant < 0.0 {
+            return 0.0;
+        }
+        let t_in = (b - discriminant.sqrt()).max(0.0);
+        let t_out = b + discriminant.sqrt();
+        if t_out <= 0.0 {
+            return 0.0;
+        }
+        (t_out.powi(3) - t_in.powi(3)) / (4.0 * PI * self.fuzz.powi(3))
+    }
 }
 
 impl Material for Metal {
-    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Ray, RGB)> {
-        let reflected_direction = reflect_vec(&ray_in.direction.unit_vector(), &hit_record.normal)
-            + self.fuzz * random_vec_in_unit_sphere();
-        let reflected_ray = Ray::new(hit_record.point, reflected_direction, ray_in.time);
-        if reflected_ray.direction.dot(&hit_record.normal) > 0.0 {
-            return Some((reflected_ray, self.albedo));
+    /// Rays scattered below the surface are absorbed.
+    fn sample(&self, hit_record: &HitRecord, wo: &Vec3d) -> Option<ScatterSample> {
+        let reflected = reflect_vec(&(-1.0 * *wo), &hit_record.normal);
+        let direction = (reflected + self.fuzz * random_vec_in_unit_sphere()).unit_vector();
+        if direction.dot(&hit_record.normal) <= 0.0 {
+            return None;
         }
-        None
+
+        let (pdf, flags) = if self.fuzz > 0.0 {
+            (self.fuzz_pdf(&reflected, &direction), ScatterFlags::GLOSSY)
+        } else {
+            (1.0, ScatterFlags::SPECULAR)
+        };
+        Some(ScatterSample {
+            direction,
+            attenuation: self.albedo,
+            pdf,
+            flags: flags | ScatterFlags::REFLECTION,
+        })
+    }
+
+    fn eval(&self, hit_record: &HitRecord, wo: &Vec3d, wi: &Vec3d) -> RGB {
+        self.pdf(hit_record, wo, wi) * self.albedo
+    }
+
+    /// A metal without fuzz is a mirror, which only scatters into the mirror
+    /// direction.
+    fn pdf(&self, hit_record: &HitRecord, wo: &Vec3d, wi: &Vec3d) -> f64 {
+        if self.fuzz <= 0.0 || wi.dot(&hit_record.normal) <= 0.0 {
+            return 0.0;
+        }
+        let reflected = reflect_vec(&(-1.0 * *wo), &hit_record.normal);
+        self.fuzz_pdf(&reflected, wi)
+    }
+
+    fn albedo(&self, _hit_record: &HitRecord) -> RGB {
+        self.albedo
     }
 }
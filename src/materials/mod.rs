@@ -3,6 +3,7 @@ mod diffuse;
 mod diffuse_light;
 mod isotropic;
 mod lambertian;
+mod library;
 mod material;
 mod metal;
 
@@ -11,5 +12,6 @@ pub use diffuse::Diffuse;
 pub use diffuse_light::DiffuseLight;
 pub use isotropic::Isotropic;
 pub use lambertian::Lambertian;
-pub use material::Material;
+pub use library::MaterialLibrary;
+pub use material::{Material, ScatterFlags, ScatterSample};
 pub use metal::Metal;
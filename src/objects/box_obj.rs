@@ -79,7 +79,7 @@ impl BoxObj {
 }
 
 impl Hittable for BoxObj {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
         self.sides.hit(ray, t_min, t_max)
     }
 
@@ -1,9 +1,12 @@
 use std::fs::File;
 use std::io::{self, BufRead};
+use std::path::PathBuf;
+use std::sync::Arc;
 
 use crate::bvh::bvh::BvhMetrics;
 use crate::hittable::{hit_record::HitRecord, hittable::Hittable};
 use crate::{
+    Error, Result,
     bvh::{bounding_box::BoundingBox, bvh::Bvh},
     materials::Material,
     ray::Ray,
@@ -23,28 +26,27 @@ impl Model {
         Self { bvh }
     }
 
-    pub fn build<TMaterial>(file_name: &str, material: TMaterial) -> (Model, BvhMetrics)
-    where
-        TMaterial: Material + Clone + 'static,
-    {
+    /// Builds a model from a PLY file made of triangles. The triangles share
+    /// the one material rather than each holding a copy of it.
+    pub fn build(file_name: &str, material: Arc<dyn Material>) -> Result<(Model, BvhMetrics)> {
         let time0 = 0.0;
         let time1 = 0.0;
 
-        let triangles: Vec<Box<dyn Hittable>> = read_ply_file(file_name)
+        let triangles: Vec<Box<dyn Hittable>> = read_ply_file(file_name)?
             .iter()
             .map(|tri| {
                 Box::new(Triangle::new(tri.0, tri.1, tri.2, material.clone())) as Box<dyn Hittable>
             })
             .collect();
 
-        let (bvh, bvh_metrics) = Bvh::build(time0, time1, triangles);
+        let (bvh, bvh_metrics) = Bvh::build(time0, time1, triangles)?;
 
-        (Self::new(bvh), bvh_metrics)
+        Ok((Self::new(bvh), bvh_metrics))
     }
 }
 
 impl Hittable for Model {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
         self.bvh.hit(ray, t_min, t_max)
     }
 
@@ -53,9 +55,39 @@ impl Hittable for Model {
     }
 }
 
-fn read_ply_file(file_name: &str) -> Vec<(Vec3d, Vec3d, Vec3d)> {
-    let file = File::open(file_name).expect("Error opening file");
-    let mut lines = io::BufReader::new(file).lines();
+fn read_ply_file(file_name: &str) -> Result<Vec<(Vec3d, Vec3d, Vec3d)>> {
+    let file = File::open(file_name).map_err(|source| Error::Io {
+        path: file_name.into(),
+        source,
+    })?;
+    parse_ply(io::BufReader::new(file)).map_err(|error| match error {
+        Error::Io { source, .. } => Error::Io {
+            path: file_name.into(),
+            source,
+        },
+        error => error.with_path(file_name),
+    })
+}
+
+fn parse_ply(reader: impl BufRead) -> Result<Vec<(Vec3d, Vec3d, Vec3d)>> {
+    let mut lines = reader.lines().enumerate().map(|(i, line)| {
+        line.map(|line| (i + 1, line)).map_err(|source| Error::Io {
+            path: PathBuf::new(),
+            source,
+        })
+    });
+    let mut line_number = 0;
+    let mut next_line = |expected: &str| match lines.next() {
+        Some(Ok((number, line))) => {
+            line_number = number;
+            Ok((number, line))
+        }
+        Some(Err(error)) => Err(error),
+        None => Err(Error::parse(
+            line_number + 1,
+            format!("unexpected end of file, expected {expected}"),
+        )),
+    };
 
     // Read header
     let mut num_vertices: usize = 0;
@@ -63,33 +95,46 @@ fn read_ply_file(file_name: &str) -> Vec<(Vec3d, Vec3d, Vec3d)> {
 
     // Iterate over file till the 'end header' line extracting necessary
     // information
-    loop {
-        let line = lines.next().unwrap().unwrap();
+    let header_end = loop {
+        let (number, line) = next_line("end_header")?;
         match line.as_str() {
             line if line.starts_with("element vertex ") => {
-                num_vertices = line[15..].parse().unwrap();
+                num_vertices = parse_count(number, &line[15..])?;
             }
             line if line.starts_with("element face ") => {
-                num_faces = line[13..].parse().unwrap();
+                num_faces = parse_count(number, &line[13..])?;
             }
             "end_header" => {
-                break;
+                break number;
             }
             _ => {} // Ignore all other lines of header
         }
-    }
+    };
 
     // Check we read necessary data from header
-    assert_ne!(num_vertices, 0);
-    assert_ne!(num_faces, 0);
+    if num_vertices == 0 || num_faces == 0 {
+        return Err(Error::parse(
+            header_end,
+            "header must have at least one vertex and one face element",
+        ));
+    }
 
     // Read vertices
     let mut vertices = Vec::<Vec3d>::new();
     for _ in 0..num_vertices {
         // 0 0 0
-        let line = lines.next().unwrap().unwrap();
-        let parts: Vec<f64> = line.trim().split(" ").map(|x| x.parse().unwrap()).collect();
-        assert_eq!(parts.len(), 3);
+        let (number, line) = next_line("a vertex")?;
+        let parts: Vec<f64> = line
+            .split_whitespace()
+            .map(|x| x.parse())
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|_| Error::parse(number, format!("invalid vertex \"{line}\"")))?;
+        if parts.len() != 3 {
+            return Err(Error::parse(
+                number,
+                format!("expected 3 numbers for a vertex, found {}", parts.len()),
+            ));
+        }
         vertices.push(Vec3d::new(parts[0], parts[1], parts[2]));
     }
 
@@ -97,12 +142,81 @@ fn read_ply_file(file_name: &str) -> Vec<(Vec3d, Vec3d, Vec3d)> {
     let mut triangles = Vec::<(Vec3d, Vec3d, Vec3d)>::new();
     for _ in 0..num_faces {
         // 3 0 1 3
-        let line = lines.next().unwrap().unwrap();
-        assert!(line.starts_with("3 "));
-        let parts: Vec<usize> = line.trim().split(" ").map(|x| x.parse().unwrap()).collect();
-        assert_eq!(parts.len(), 4);
-        triangles.push((vertices[parts[1]], vertices[parts[2]], vertices[parts[3]]));
+        let (number, line) = next_line("a face")?;
+        let parts: Vec<usize> = line
+            .split_whitespace()
+            .map(|x| x.parse())
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|_| Error::parse(number, format!("invalid face \"{line}\"")))?;
+        if parts.len() != 4 || parts[0] != 3 {
+            return Err(Error::parse(
+                number,
+                "only triangle faces are supported, expected \"3 A B C\"",
+            ));
+        }
+        let vertex = |i: usize| {
+            vertices.get(i).copied().ok_or_else(|| {
+                Error::parse(
+                    number,
+                    format!("vertex {i} does not exist, there are {num_vertices} vertices"),
+                )
+            })
+        };
+        triangles.push((vertex(parts[1])?, vertex(parts[2])?, vertex(parts[3])?));
+    }
+
+    Ok(triangles)
+}
+
+fn parse_count(line_number: usize, count: &str) -> Result<usize> {
+    count
+        .trim()
+        .parse()
+        .map_err(|_| Error::parse(line_number, format!("invalid element count \"{count}\"")))
+}
+
+#[cfg(test)]
+mod model_tests {
+    use super::*;
+
+    const PLY_HEADER: &str = "ply
+format ascii 1.0
+element vertex 3
+property float x
+property float y
+property float z
+element face 1
+property list uchar int vertex_indices
+end_header
+";
+
+    #[test]
+    fn parse_ply_should_read_triangles() {
+        let ply = format!("{PLY_HEADER}0 0 0\n1 0 0\n0 1 0\n3 0 1 2\n");
+        let triangles = parse_ply(ply.as_bytes()).unwrap();
+
+        assert_eq!(triangles.len(), 1);
+        assert_eq!(triangles[0].1, Vec3d::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn parse_ply_should_report_line_of_missing_vertex() {
+        let ply = format!("{PLY_HEADER}0 0 0\n1 0 0\n0 1 0\n3 0 1 7\n");
+        match parse_ply(ply.as_bytes()) {
+            Err(Error::Parse { line, message, .. }) => {
+                assert_eq!(line, 13);
+                assert!(message.contains("vertex 7"), "{message}");
+            }
+            result => panic!("expected a parse error, got {result:?}"),
+        }
     }
 
-    triangles
+    #[test]
+    fn parse_ply_should_report_truncated_file() {
+        let ply = format!("{PLY_HEADER}0 0 0\n1 0 0\n");
+        match parse_ply(ply.as_bytes()) {
+            Err(Error::Parse { line, .. }) => assert_eq!(line, 12),
+            result => panic!("expected a parse error, got {result:?}"),
+        }
+    }
 }
@@ -41,13 +41,21 @@ impl<M: Material> MovingSphere<M> {
         self.center0
             + ((time - self.time0) / (self.time1 - self.time0)) * (self.center1 - self.center0)
     }
+
+    /// Returns how fast the sphere moves, in scene units per unit of time.
+    pub fn velocity(&self) -> Vec3d {
+        if self.time1 == self.time0 {
+            return Vec3d::new(0.0, 0.0, 0.0);
+        }
+        (self.center1 - self.center0) / (self.time1 - self.time0)
+    }
 }
 
 impl<M> Hittable for MovingSphere<M>
 where
     M: Material + Clone,
 {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
         let oc = ray.origin - self.center(ray.time);
         let a = ray.direction.len_squared();
         let half_b = oc.dot(&ray.direction);
@@ -72,15 +80,10 @@ where
         let (u, v) = get_sphere_uv(outward_normal);
         let (front_face, normal) = HitRecord::get_face_normal(ray, outward_normal);
 
-        Some(HitRecord::new(
-            point,
-            normal,
-            &self.material,
-            root,
-            u,
-            v,
-            front_face,
-        ))
+        Some(
+            HitRecord::new(point, normal, &self.material, root, u, v, front_face)
+                .with_velocity(self.velocity()),
+        )
     }
 
     fn bounding_box(&self, time0: f64, time1: f64) -> Option<BoundingBox> {
@@ -53,7 +53,7 @@ impl Pyramid {
 }
 
 impl Hittable for Pyramid {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
         self.sides.hit(ray, t_min, t_max)
     }
 
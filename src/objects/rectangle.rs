@@ -3,6 +3,7 @@ use crate::{
     hittable::{hit_record::HitRecord, hittable::Hittable},
     materials::Material,
     ray::Ray,
+    utilities::random_rng,
     vec3d::Point3d,
     vec3d::Vec3d,
 };
@@ -19,7 +20,7 @@ impl<M> Hittable for Rectangle<M>
 where
     M: Material + Clone,
 {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
         match self {
             Rectangle::XY(rectangle_xy) => rectangle_xy.hit(ray, t_min, t_max),
             Rectangle::XZ(rectangle_xz) => rectangle_xz.hit(ray, t_min, t_max),
@@ -34,6 +35,52 @@ where
             Rectangle::YZ(rectangle_yz) => rectangle_yz.bounding_box(time0, time1),
         }
     }
+
+    fn pdf_value(&self, origin: Point3d, direction: Vec3d) -> f64 {
+        match self {
+            Rectangle::XY(rectangle_xy) => rectangle_xy.pdf_value(origin, direction),
+            Rectangle::XZ(rectangle_xz) => rectangle_xz.pdf_value(origin, direction),
+            Rectangle::YZ(rectangle_yz) => rectangle_yz.pdf_value(origin, direction),
+        }
+    }
+
+    fn random_direction(&self, origin: Point3d) -> Vec3d {
+        match self {
+            Rectangle::XY(rectangle_xy) => rectangle_xy.random_direction(origin),
+            Rectangle::XZ(rectangle_xz) => rectangle_xz.random_direction(origin),
+            Rectangle::YZ(rectangle_yz) => rectangle_yz.random_direction(origin),
+        }
+    }
+
+    fn sample_surface(&self) -> Option<(HitRecord<'_>, f64)> {
+        match self {
+            Rectangle::XY(rectangle_xy) => rectangle_xy.sample_surface(),
+            Rectangle::XZ(rectangle_xz) => rectangle_xz.sample_surface(),
+            Rectangle::YZ(rectangle_yz) => rectangle_yz.sample_surface(),
+        }
+    }
+
+    fn surface_pdf(&self, ray: &Ray, t: f64) -> f64 {
+        match self {
+            Rectangle::XY(rectangle_xy) => rectangle_xy.surface_pdf(ray, t),
+            Rectangle::XZ(rectangle_xz) => rectangle_xz.surface_pdf(ray, t),
+            Rectangle::YZ(rectangle_yz) => rectangle_yz.surface_pdf(ray, t),
+        }
+    }
+}
+
+/// Returns the probability density, over solid angle, of picking `direction`
+/// from `origin` by choosing a point uniformly on a rectangle. `hit` is where
+/// the direction meets the rectangle, if it does.
+fn rectangle_pdf_value(hit: Option<HitRecord>, direction: Vec3d, area: f64) -> f64 {
+    match hit {
+        Some(hr) => {
+            let distance_squared = hr.t * hr.t * direction.len_squared();
+            let cosine = (direction.dot(&hr.normal) / direction.len()).abs();
+            distance_squared / (cosine * area)
+        }
+        None => 0.0,
+    }
 }
 
 /// Axis-aligned rectangle for X-Y plane
@@ -64,7 +111,7 @@ impl<M> Hittable for RectangleXY<M>
 where
     M: Material + Clone,
 {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
         let t = (self.k - ray.origin.z) / ray.direction.z;
         if t < t_min || t > t_max {
             return None;
@@ -105,6 +152,42 @@ where
             Point3d::new(self.x1, self.y1, self.k + 0.0001),
         ))
     }
+
+    fn pdf_value(&self, origin: Point3d, direction: Vec3d) -> f64 {
+        let hit = self.hit(&Ray::new(origin, direction, 0.0), 0.001, f64::INFINITY);
+        let area = (self.x1 - self.x0) * (self.y1 - self.y0);
+        rectangle_pdf_value(hit, direction, area)
+    }
+
+    fn random_direction(&self, origin: Point3d) -> Vec3d {
+        let x = random_rng(self.x0, self.x1);
+        let y = random_rng(self.y0, self.y1);
+        Point3d::new(x, y, self.k) - origin
+    }
+
+    fn sample_surface(&self) -> Option<(HitRecord<'_>, f64)> {
+        let x = random_rng(self.x0, self.x1);
+        let y = random_rng(self.y0, self.y1);
+        let u = (x - self.x0) / (self.x1 - self.x0);
+        let v = (y - self.y0) / (self.y1 - self.y0);
+        let hit_record = HitRecord::new(
+            Point3d::new(x, y, self.k),
+            Vec3d::new(0.0, 0.0, 1.0),
+            &self.material,
+            0.0,
+            u,
+            v,
+            true,
+        );
+        Some((
+            hit_record,
+            1.0 / ((self.x1 - self.x0) * (self.y1 - self.y0)),
+        ))
+    }
+
+    fn surface_pdf(&self, _ray: &Ray, _t: f64) -> f64 {
+        1.0 / ((self.x1 - self.x0) * (self.y1 - self.y0))
+    }
 }
 
 /// Axis-aligned rectangle for X-Z plane
@@ -135,7 +218,7 @@ impl<M> Hittable for RectangleXZ<M>
 where
     M: Material + Clone,
 {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
         let t = (self.k - ray.origin.y) / ray.direction.y;
         if t < t_min || t > t_max {
             return None;
@@ -176,6 +259,42 @@ where
             Point3d::new(self.x1, self.k + 0.0001, self.z1),
         ))
     }
+
+    fn pdf_value(&self, origin: Point3d, direction: Vec3d) -> f64 {
+        let hit = self.hit(&Ray::new(origin, direction, 0.0), 0.001, f64::INFINITY);
+        let area = (self.x1 - self.x0) * (self.z1 - self.z0);
+        rectangle_pdf_value(hit, direction, area)
+    }
+
+    fn random_direction(&self, origin: Point3d) -> Vec3d {
+        let x = random_rng(self.x0, self.x1);
+        let z = random_rng(self.z0, self.z1);
+        Point3d::new(x, self.k, z) - origin
+    }
+
+    fn sample_surface(&self) -> Option<(HitRecord<'_>, f64)> {
+        let x = random_rng(self.x0, self.x1);
+        let z = random_rng(self.z0, self.z1);
+        let u = (x - self.x0) / (self.x1 - self.x0);
+        let v = (z - self.z0) / (self.z1 - self.z0);
+        let hit_record = HitRecord::new(
+            Point3d::new(x, self.k, z),
+            Vec3d::new(0.0, 1.0, 0.0),
+            &self.material,
+            0.0,
+            u,
+            v,
+            true,
+        );
+        Some((
+            hit_record,
+            1.0 / ((self.x1 - self.x0) * (self.z1 - self.z0)),
+        ))
+    }
+
+    fn surface_pdf(&self, _ray: &Ray, _t: f64) -> f64 {
+        1.0 / ((self.x1 - self.x0) * (self.z1 - self.z0))
+    }
 }
 
 /// Axis-aligned rectangle for Y-Z plane
@@ -206,7 +325,7 @@ impl<M> Hittable for RectangleYZ<M>
 where
     M: Material + Clone,
 {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
         let t = (self.k - ray.origin.x) / ray.direction.x;
         if t < t_min || t > t_max {
             return None;
@@ -242,4 +361,40 @@ where
             Point3d::new(self.k + 0.0001, self.y1, self.z1),
         ))
     }
+
+    fn pdf_value(&self, origin: Point3d, direction: Vec3d) -> f64 {
+        let hit = self.hit(&Ray::new(origin, direction, 0.0), 0.001, f64::INFINITY);
+        let area = (self.y1 - self.y0) * (self.z1 - self.z0);
+        rectangle_pdf_value(hit, direction, area)
+    }
+
+    fn random_direction(&self, origin: Point3d) -> Vec3d {
+        let y = random_rng(self.y0, self.y1);
+        let z = random_rng(self.z0, self.z1);
+        Point3d::new(self.k, y, z) - origin
+    }
+
+    fn sample_surface(&self) -> Option<(HitRecord<'_>, f64)> {
+        let y = random_rng(self.y0, self.y1);
+        let z = random_rng(self.z0, self.z1);
+        let u = (y - self.y0) / (self.y1 - self.y0);
+        let v = (z - self.z0) / (self.z1 - self.z0);
+        let hit_record = HitRecord::new(
+            Point3d::new(self.k, y, z),
+            Vec3d::new(1.0, 0.0, 0.0),
+            &self.material,
+            0.0,
+            u,
+            v,
+            true,
+        );
+        Some((
+            hit_record,
+            1.0 / ((self.y1 - self.y0) * (self.z1 - self.z0)),
+        ))
+    }
+
+    fn surface_pdf(&self, _ray: &Ray, _t: f64) -> f64 {
+        1.0 / ((self.y1 - self.y0) * (self.z1 - self.z0))
+    }
 }
@@ -2,8 +2,9 @@ use crate::{
     bvh::bounding_box::BoundingBox,
     hittable::{hit_record::HitRecord, hittable::Hittable},
     materials::Material,
+    onb::Onb,
     ray::Ray,
-    utilities::get_sphere_uv,
+    utilities::{get_sphere_uv, random, random_unit_vec},
     vec3d::Point3d,
     vec3d::Vec3d,
 };
@@ -29,7 +30,7 @@ impl<M> Hittable for Sphere<M>
 where
     M: Material + Clone,
 {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
         let oc = ray.origin - self.center;
         let a = ray.direction.len_squared();
         let half_b = oc.dot(&ray.direction);
@@ -66,9 +67,128 @@ where
     }
 
     fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<BoundingBox> {
+        // The radius can be negative to flip the normals, e.g. for the inside
+        // of a hollow glass sphere.
+        let radius = self.radius.abs();
         Some(BoundingBox::new(
-            self.center - Vec3d::new(self.radius, self.radius, self.radius),
-            self.center + Vec3d::new(self.radius, self.radius, self.radius),
+            self.center - Vec3d::new(radius, radius, radius),
+            self.center + Vec3d::new(radius, radius, radius),
         ))
     }
+
+    /// Directions are picked uniformly from the cone of directions that hit
+    /// the sphere. From inside the sphere every direction hits it, so they are
+    /// picked uniformly from all directions.
+    fn pdf_value(&self, origin: Point3d, direction: Vec3d) -> f64 {
+        let ray = Ray::new(origin, direction, 0.0);
+        if self.hit(&ray, 0.001, f64::INFINITY).is_none() {
+            return 0.0;
+        }
+        match cos_theta_max(self.radius, (self.center - origin).len_squared()) {
+            Some(cos_theta_max) => 1.0 / (2.0 * std::f64::consts::PI * (1.0 - cos_theta_max)),
+            None => 1.0 / (4.0 * std::f64::consts::PI),
+        }
+    }
+
+    fn random_direction(&self, origin: Point3d) -> Vec3d {
+        let direction = self.center - origin;
+        match cos_theta_max(self.radius, direction.len_squared()) {
+            Some(cos_theta_max) => {
+                Onb::build_from_w(&direction).local(random_in_cone(cos_theta_max))
+            }
+            None => random_unit_vec(),
+        }
+    }
+
+    /// Points are picked uniformly over the sphere.
+    fn sample_surface(&self) -> Option<(HitRecord<'_>, f64)> {
+        let outward_normal = random_unit_vec();
+        let point = self.center + self.radius.abs() * outward_normal;
+        let outward_normal = (point - self.center) / self.radius;
+        let (u, v) = get_sphere_uv(outward_normal);
+        let hit_record = HitRecord::new(point, outward_normal, &self.material, 0.0, u, v, true);
+        Some((hit_record, self.surface_pdf_value()))
+    }
+
+    fn surface_pdf(&self, _ray: &Ray, _t: f64) -> f64 {
+        self.surface_pdf_value()
+    }
+}
+
+impl<M: Material> Sphere<M> {
+    /// Returns the density per unit area of picking any point on the sphere.
+    fn surface_pdf_value(&self) -> f64 {
+        1.0 / (4.0 * std::f64::consts::PI * self.radius * self.radius)
+    }
+}
+
+/// Returns the cosine of the half angle of the cone of directions that hit a
+/// sphere, or `None` if the origin is inside the sphere.
+fn cos_theta_max(radius: f64, distance_squared: f64) -> Option<f64> {
+    let radius_squared = radius * radius;
+    (distance_squared > radius_squared).then(|| (1.0 - radius_squared / distance_squared).sqrt())
+}
+
+/// Returns a random direction within a cone around the z-axis.
+fn random_in_cone(cos_theta_max: f64) -> Vec3d {
+    let phi = 2.0 * std::f64::consts::PI * random();
+    let z = 1.0 + random() * (cos_theta_max - 1.0);
+    let sin_theta = (1.0 - z * z).sqrt();
+    Vec3d::new(phi.cos() * sin_theta, phi.sin() * sin_theta, z)
+}
+
+#[cfg(test)]
+mod sphere_tests {
+    use std::f64::consts::PI;
+
+    use crate::{
+        colour::RGB, hittable::hittable::pdf_integral, materials::Diffuse,
+        utilities::with_seeded_random,
+    };
+
+    use super::*;
+
+    fn sphere() -> Sphere<Diffuse> {
+        Sphere::new(
+            Point3d::new(1.0, 2.0, 3.0),
+            1.5,
+            Diffuse::new(RGB(0.5, 0.5, 0.5)),
+        )
+    }
+
+    #[test]
+    fn pdf_value_should_integrate_to_one_over_all_directions() {
+        let integral =
+            with_seeded_random(5, || pdf_integral(&sphere(), Point3d::new(0.0, 0.0, 0.0)));
+
+        assert!((integral - 1.0).abs() < 0.02, "integral: {integral}");
+    }
+
+    #[test]
+    fn sample_surface_should_pick_points_on_the_sphere() {
+        let sphere = sphere();
+
+        with_seeded_random(5, || {
+            for _ in 0..100 {
+                let (hr, pdf) = sphere.sample_surface().unwrap();
+                let offset = hr.point - Point3d::new(1.0, 2.0, 3.0);
+                assert!((offset.len() - 1.5).abs() < 1e-9);
+                assert!((hr.normal.dot(&offset) - 1.5).abs() < 1e-9);
+                assert!((pdf * 4.0 * PI * 1.5 * 1.5 - 1.0).abs() < 1e-9);
+            }
+        });
+    }
+
+    #[test]
+    fn random_direction_should_point_at_the_sphere() {
+        let sphere = sphere();
+        let origin = Point3d::new(0.0, 0.0, 0.0);
+
+        with_seeded_random(5, || {
+            for _ in 0..1000 {
+                let direction = sphere.random_direction(origin);
+                assert!(sphere.pdf_value(origin, direction) > 0.0);
+            }
+        });
+    }
 }
@@ -41,7 +41,7 @@ impl<M> Hittable for Triangle<M>
 where
     M: Material + Clone,
 {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
         match moller_trumbore_triangle_intersection(ray, self, t_min, t_max) {
             Some((t, u, v, intersection_point, outward_normal)) => {
                 let (front_face, normal) = HitRecord::get_face_normal(ray, outward_normal);
@@ -0,0 +1,48 @@
+use crate::vec3d::Vec3d;
+
+/// An orthonormal basis, three unit vectors at right angles to each other.
+/// Used to turn directions sampled around the z-axis into directions around
+/// some other vector, such as a surface normal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Onb {
+    pub u: Vec3d,
+    pub v: Vec3d,
+    pub w: Vec3d,
+}
+
+impl Onb {
+    /// Builds a basis where `w` points along `n`.
+    pub fn build_from_w(n: &Vec3d) -> Self {
+        let w = n.unit_vector();
+        let a = if w.x.abs() > 0.9 {
+            Vec3d::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3d::new(1.0, 0.0, 0.0)
+        };
+        let v = w.cross(&a).unit_vector();
+        let u = w.cross(&v);
+        Self { u, v, w }
+    }
+
+    /// Converts a vector from basis coordinates to world coordinates.
+    pub fn local(&self, a: Vec3d) -> Vec3d {
+        a.x * self.u + a.y * self.v + a.z * self.w
+    }
+}
+
+#[cfg(test)]
+mod onb_tests {
+    use super::*;
+
+    #[test]
+    fn local_should_map_z_to_w() {
+        let n = Vec3d::new(1.0, 2.0, -3.0);
+        let onb = Onb::build_from_w(&n);
+
+        let w = onb.local(Vec3d::new(0.0, 0.0, 1.0));
+
+        assert!((w - n.unit_vector()).len() < 1e-12);
+        assert!(onb.u.dot(&onb.v).abs() < 1e-12);
+        assert!(onb.u.dot(&onb.w).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,366 @@
+use crate::{
+    bvh::{bounding_box::BoundingBox, bvh::Bvh},
+    camera::Camera,
+    colour::RGB,
+    hittable::{hit_record::HitRecord, hittable::Hittable},
+    ray::Ray,
+    vec3d::Vec3d,
+};
+
+use super::{
+    filter::{FilteredSamples, PixelFilter, TileSplats},
+    image::Image,
+    settings::RenderSettings,
+    tiles::TileRect,
+};
+
+/// Mixed into the seeds of the samples of AOVs, to keep them apart from the
+/// seeds of the image's samples.
+pub(super) const AOV_SEEDS: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// An arbitrary output variable, a pass rendered alongside the image with
+/// what the camera sees at the first surface each sample hits, e.g. for
+/// compositing or as a guide for a denoiser. Passes are sampled and spread
+/// over the pixels by the pixel filter like the image, so their edges line
+/// up with it, apart from IDs, which can not be blended. Single values are
+/// stored in every channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aov {
+    /// Distance from the camera to the first hit. Samples that hit nothing
+    /// are left out, and pixels where no sample hit anything are infinitely
+    /// far away.
+    Depth,
+    /// World space unit normal of the first hit, facing the camera, in x, y
+    /// and z.
+    Normal,
+    /// Colour of the surface first hit, see `Material::albedo`, or of the
+    /// background.
+    Albedo,
+    /// The object first hit, counted from 1 in the order the objects were
+    /// added to the renderer, or 0 for the background. Each pixel has the ID
+    /// most of its own samples hit, whatever the pixel filter.
+    ObjectId,
+    /// Like `ObjectId` for materials, numbered from 1 in the order they first
+    /// appear in the image from the top left. Objects sharing a material,
+    /// such as a named material of a scene file, share its ID.
+    MaterialId,
+    /// Texture coordinates of the first hit, u in red and v in green.
+    Uv,
+    /// How far the first hit moves across the image while the shutter is
+    /// open, in pixels, to the right in red and down in green.
+    Motion,
+}
+
+impl Aov {
+    pub const ALL: [Aov; 7] = [
+        Aov::Depth,
+        Aov::Normal,
+        Aov::Albedo,
+        Aov::ObjectId,
+        Aov::MaterialId,
+        Aov::Uv,
+        Aov::Motion,
+    ];
+
+    /// Returns the name of the pass, e.g. for file names.
+    pub fn name(self) -> &'static str {
+        match self {
+            Aov::Depth => "depth",
+            Aov::Normal => "normal",
+            Aov::Albedo => "albedo",
+            Aov::ObjectId => "object_id",
+            Aov::MaterialId => "material_id",
+            Aov::Uv => "uv",
+            Aov::Motion => "motion",
+        }
+    }
+}
+
+/// An object of a scene tagged with its ID, which it sets on its hits.
+pub(super) struct IdentifiedObject {
+    pub id: usize,
+    pub object: Box<dyn Hittable>,
+}
+
+impl Hittable for IdentifiedObject {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        self.object.hit(ray, t_min, t_max).map(|mut hr| {
+            hr.object_id = self.id;
+            hr
+        })
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<BoundingBox> {
+        self.object.bounding_box(time0, time1)
+    }
+}
+
+/// What the first hits of camera rays are found in.
+pub(super) struct AovScene<'a> {
+    pub bvh: &'a Bvh,
+    pub camera: &'a Camera,
+    pub settings: &'a RenderSettings,
+}
+
+impl AovScene<'_> {
+    /// Returns what a camera ray sees at the first surface it hits.
+    pub fn sample(&self, ray: &Ray) -> AovSample {
+        let Some(hr) = self.bvh.hit(ray, 0.001, f64::MAX) else {
+            return AovSample {
+                albedo: self.settings.background.colour(ray),
+                ..AovSample::default()
+            };
+        };
+
+        let wo = -1.0 * ray.direction.unit_vector();
+        AovSample {
+            depth: Some(hr.t * ray.direction.len()),
+            // The normal of a hit inside a volume is arbitrary
+            normal: match hr.material.is_volume() {
+                true => wo,
+                false => hr.normal,
+            },
+            albedo: hr.material.albedo(&hr),
+            object_id: hr.object_id,
+            material: hr.material.identity(),
+            uv: (hr.u, hr.v),
+            motion: self.motion(ray, &hr),
+        }
+    }
+
+    /// Returns how far a hit moves across the image, in pixels, between the
+    /// shutter opening and closing.
+    fn motion(&self, ray: &Ray, hr: &HitRecord) -> (f64, f64) {
+        let settings = self.settings;
+        let open = hr.point + (settings.time0 - ray.time) * hr.velocity;
+        let close = hr.point + (settings.time1 - ray.time) * hr.velocity;
+        match (
+            self.camera.image_position(ray.origin, open),
+            self.camera.image_position(ray.origin, close),
+        ) {
+            (Some((s0, t0)), Some((s1, t1))) => (
+                (s1 - s0) * (settings.image_width - 1) as f64,
+                // Image rows go top -> bottom, but t goes bottom -> top
+                (t0 - t1) * (settings.image_height - 1) as f64,
+            ),
+            _ => (0.0, 0.0),
+        }
+    }
+}
+
+/// What a sample saw at its first hit, or of the background if it hit
+/// nothing.
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct AovSample {
+    /// `None` if the sample hit nothing.
+    depth: Option<f64>,
+    normal: Vec3d,
+    albedo: RGB,
+    /// 0 for the background.
+    object_id: usize,
+    /// The identity of the material, see `Material::identity`, 0 for the
+    /// background.
+    material: usize,
+    uv: (f64, f64),
+    motion: (f64, f64),
+}
+
+/// Votes for the IDs seen by the samples of a pixel. IDs can not be blended,
+/// so they are not spread over the pixels around by the pixel filter.
+#[derive(Debug, Clone, Default)]
+pub(super) struct AovSamples {
+    object_ids: Votes,
+    materials: Votes,
+}
+
+impl AovSamples {
+    pub fn add(&mut self, sample: &AovSample) {
+        self.object_ids.add(sample.object_id);
+        self.materials.add(sample.material);
+    }
+}
+
+/// The samples of the passes that can be blended, spread over the pixels
+/// around them by the pixel filter the same way as the samples of the image.
+pub(super) struct AovSplats {
+    /// The depth of hits in red and their count in green, so that samples
+    /// that hit nothing are left out.
+    depth: TileSplats,
+    normal: TileSplats,
+    albedo: TileSplats,
+    uv: TileSplats,
+    motion: TileSplats,
+}
+
+impl AovSplats {
+    /// Creates splats for a tile, see `TileSplats::new`.
+    pub fn new(filter: PixelFilter, image_height: usize, tile: TileRect, area: TileRect) -> Self {
+        let splats = || TileSplats::new(filter, image_height, tile, area);
+        Self {
+            depth: splats(),
+            normal: splats(),
+            albedo: splats(),
+            uv: splats(),
+            motion: splats(),
+        }
+    }
+
+    /// Spreads a sample at `(x, y)`, in pixels from the bottom left corner
+    /// of the image, over the pixels around it.
+    pub fn add(&mut self, x: f64, y: f64, sample: &AovSample) {
+        let depth = match sample.depth {
+            Some(depth) => RGB(depth, 1.0, 0.0),
+            None => RGB(0.0, 0.0, 0.0),
+        };
+        let Vec3d {
+            x: nx,
+            y: ny,
+            z: nz,
+        } = sample.normal;
+        self.depth.add(x, y, depth);
+        self.normal.add(x, y, RGB(nx, ny, nz));
+        self.albedo.add(x, y, sample.albedo);
+        self.uv.add(x, y, RGB(sample.uv.0, sample.uv.1, 0.0));
+        self.motion
+            .add(x, y, RGB(sample.motion.0, sample.motion.1, 0.0));
+    }
+
+    /// Adds the splats to the sums of an image `image_width` pixels wide.
+    pub fn add_to(&self, sums: &mut FilteredAovs, image_width: usize) {
+        self.depth.add_to(&mut sums.depth, image_width);
+        self.normal.add_to(&mut sums.normal, image_width);
+        self.albedo.add_to(&mut sums.albedo, image_width);
+        self.uv.add_to(&mut sums.uv, image_width);
+        self.motion.add_to(&mut sums.motion, image_width);
+    }
+}
+
+/// The weighted sums of the samples of the passes that can be blended, for
+/// every pixel of the image.
+pub(super) struct FilteredAovs {
+    depth: Vec<FilteredSamples>,
+    normal: Vec<FilteredSamples>,
+    albedo: Vec<FilteredSamples>,
+    uv: Vec<FilteredSamples>,
+    motion: Vec<FilteredSamples>,
+}
+
+impl FilteredAovs {
+    pub fn new(num_pixels: usize) -> Self {
+        let sums = || vec![FilteredSamples::default(); num_pixels];
+        Self {
+            depth: sums(),
+            normal: sums(),
+            albedo: sums(),
+            uv: sums(),
+            motion: sums(),
+        }
+    }
+
+    /// Returns the value of a pass for the pixel at `index`, where `ids` are
+    /// the votes of the pixel's own samples and `material_id` numbers the
+    /// identities of materials.
+    fn value(
+        &self,
+        aov: Aov,
+        index: usize,
+        ids: &AovSamples,
+        material_id: impl Fn(usize) -> usize,
+    ) -> RGB {
+        let grey = |value: f64| RGB(value, value, value);
+        let average = |sums: &[FilteredSamples]| sums[index].colour().unwrap_or_default();
+        match aov {
+            // The weights of the hits cancel out, and a filter's negative
+            // lobes can leave them adding up to nothing
+            Aov::Depth => match self.depth[index].colour {
+                RGB(depth, hits, _) if hits > 0.0 => grey(depth / hits),
+                _ => grey(f64::INFINITY),
+            },
+            Aov::Normal => average(&self.normal),
+            Aov::Albedo => average(&self.albedo),
+            Aov::ObjectId => grey(ids.object_ids.winner() as f64),
+            Aov::MaterialId => grey(material_id(ids.materials.winner()) as f64),
+            Aov::Uv => average(&self.uv),
+            Aov::Motion => average(&self.motion),
+        }
+    }
+}
+
+/// Counts of the IDs seen by the samples of a pixel.
+#[derive(Debug, Clone, Default)]
+struct Votes(Vec<(usize, usize)>);
+
+impl Votes {
+    fn add(&mut self, id: usize) {
+        match self.0.iter_mut().find(|(seen, _)| *seen == id) {
+            Some((_, count)) => *count += 1,
+            None => self.0.push((id, 1)),
+        }
+    }
+
+    /// Returns the ID seen most, the first seen of those tied, or 0 if there
+    /// are no votes.
+    fn winner(&self) -> usize {
+        self.0
+            .iter()
+            .rev()
+            .max_by_key(|(_, count)| *count)
+            .map_or(0, |(id, _)| *id)
+    }
+}
+
+/// Returns the images of passes from the samples of the pixels at the given
+/// indexes, in the order the pixels are in the image, and the filtered
+/// samples of the passes. Pixels without samples are black.
+pub(super) fn aov_images(
+    width: usize,
+    height: usize,
+    pixels: &[(usize, AovSamples)],
+    filtered: &FilteredAovs,
+    aovs: &[Aov],
+) -> Vec<Image> {
+    // Materials are numbered in the order they first appear, the background
+    // stays 0
+    let mut identities = vec![0];
+    for (_, pixel) in pixels {
+        let identity = pixel.materials.winner();
+        if !identities.contains(&identity) {
+            identities.push(identity);
+        }
+    }
+    let material_id = |identity: usize| {
+        identities
+            .iter()
+            .position(|&seen| seen == identity)
+            .unwrap_or(0)
+    };
+
+    aovs.iter()
+        .map(|&aov| {
+            let mut image = Image::new(width, height);
+            for (index, pixel) in pixels {
+                let value = filtered.value(aov, *index, pixel, material_id);
+                image.set(index % width, index / width, value);
+            }
+            image
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod aov_tests {
+    use super::*;
+
+    #[test]
+    fn ids_should_be_the_ones_seen_by_most_samples() {
+        let mut votes = Votes::default();
+        assert_eq!(votes.winner(), 0);
+
+        for id in [3, 5, 5, 3, 7] {
+            votes.add(id);
+        }
+        assert_eq!(votes.winner(), 3);
+        votes.add(5);
+        assert_eq!(votes.winner(), 5);
+    }
+}
@@ -0,0 +1,586 @@
+use std::{
+    f64::consts::PI,
+    sync::atomic::{AtomicI64, Ordering},
+};
+
+use crate::{
+    camera::Camera,
+    colour::RGB,
+    hittable::{hit_record::HitRecord, hittable::Hittable},
+    materials::Material,
+    ray::Ray,
+    utilities::{random, random_unit_vec},
+    vec3d::{Point3d, Vec3d},
+};
+
+use super::{filter::PixelFilter, integrator::Scene, settings::RenderSettings, tiles::TileRect};
+
+/// Splats are added up in fixed point, with this many steps per unit.
+const SPLAT_SCALE: f64 = (1i64 << 24) as f64;
+
+/// Where light paths that reach the camera land on the image. A light path
+/// can add light to any pixel, so the light is added up here from every
+/// thread rather than to the pixel the sample was taken for.
+pub(super) struct Film<'a> {
+    camera: &'a Camera,
+    width: usize,
+    height: usize,
+    area: TileRect,
+    filter: PixelFilter,
+    /// Density of the camera picking a point on the image per unit of `s`
+    /// and `t`, over all of the pixels rendered.
+    image_pdf: f64,
+    /// Sums of the light splatted onto each pixel. They are kept in fixed
+    /// point so they do not depend on the order threads add to them, which
+    /// keeps seeded renders repeatable.
+    splats: Vec<[AtomicI64; 3]>,
+}
+
+impl<'a> Film<'a> {
+    pub fn new(camera: &'a Camera, settings: &RenderSettings) -> Self {
+        let (width, height) = (settings.image_width, settings.image_height);
+        let area = settings.render_area();
+        // Pixels are sampled from s = i / (width - 1) to (i + 1) / (width - 1)
+        let pixel_pdf = ((width - 1) * (height - 1)) as f64;
+        Self {
+            camera,
+            width,
+            height,
+            area,
+            filter: settings.pixel_filter,
+            image_pdf: pixel_pdf / (area.width * area.height) as f64,
+            splats: (0..width * height).map(|_| Default::default()).collect(),
+        }
+    }
+
+    /// Returns the position, in pixels from the bottom left corner of the
+    /// image, that light from `point` reaching `lens_point` is seen at,
+    /// `None` if it is not seen in the pixels rendered.
+    fn position(&self, lens_point: Point3d, point: Point3d) -> Option<(f64, f64)> {
+        let (s, t) = self.camera.image_position(lens_point, point)?;
+        if s < 0.0 || t < 0.0 {
+            return None;
+        }
+        let (x, y) = (s * (self.width - 1) as f64, t * (self.height - 1) as f64);
+        let (i, j) = (x as usize, y as usize);
+        if i >= self.width || j >= self.height {
+            return None;
+        }
+        // Image rows go top -> bottom, but t goes bottom -> top
+        let row = self.height - 1 - j;
+        let area = self.area;
+        ((area.x..area.x + area.width).contains(&i)
+            && (area.y..area.y + area.height).contains(&row))
+        .then_some((x, y))
+    }
+
+    /// Returns the density, per unit solid angle, of a camera ray leaving
+    /// the lens in `direction`.
+    fn direction_pdf(&self, direction: Vec3d) -> f64 {
+        self.camera.direction_pdf(direction) * self.image_pdf
+    }
+
+    /// Spreads light seen at `(x, y)`, in pixels from the bottom left corner
+    /// of the image, over the pixels around it with the pixel filter. The
+    /// weights are scaled to add up to 1, so the filter moves light between
+    /// pixels without adding or taking away any.
+    fn splat(&self, x: f64, y: f64, colour: RGB) {
+        let mut total_weight = 0.0;
+        self.filter
+            .for_each_pixel(x, y, |_, _, weight| total_weight += weight);
+        if total_weight <= 0.0 {
+            return;
+        }
+        let area = self.area;
+        self.filter.for_each_pixel(x, y, |i, j, weight| {
+            // Image rows go top -> bottom, but j goes bottom -> top
+            let row = self.height as isize - 1 - j;
+            let (Ok(column), Ok(row)) = (usize::try_from(i), usize::try_from(row)) else {
+                return;
+            };
+            if !((area.x..area.x + area.width).contains(&column)
+                && (area.y..area.y + area.height).contains(&row))
+            {
+                return;
+            }
+            let RGB(r, g, b) = (weight / total_weight) * colour;
+            for (sum, value) in self.splats[row * self.width + column].iter().zip([r, g, b]) {
+                if value.is_finite() && value != 0.0 {
+                    let steps = (value * SPLAT_SCALE).round() as i64;
+                    // Saturates rather than wrapping round
+                    let _ = sum.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |sum| {
+                        Some(sum.saturating_add(steps))
+                    });
+                }
+            }
+        });
+    }
+
+    /// Returns the light splatted onto each pixel. The image is the sum
+    /// divided by the number of light paths traced, one per sample.
+    pub fn into_splats(self) -> Vec<RGB> {
+        self.splats
+            .into_iter()
+            .map(|[r, g, b]| {
+                let value = |sum: AtomicI64| sum.into_inner() as f64 / SPLAT_SCALE;
+                RGB(value(r), value(g), value(b))
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy)]
+enum VertexKind<'a> {
+    Camera,
+    /// The start of a light path, on the surface of a sampled light.
+    Light {
+        emitted: RGB,
+    },
+    /// Where a path hits an object.
+    Scatter {
+        material: &'a dyn Material,
+        u: f64,
+        v: f64,
+        front_face: bool,
+    },
+}
+
+/// A point on a camera or light path.
+#[derive(Clone, Copy)]
+struct Vertex<'a> {
+    kind: VertexKind<'a>,
+    point: Point3d,
+    /// Unit normal on the side the path arrived from, unused for the camera.
+    normal: Vec3d,
+    /// Share of the light carried along the path to here, divided by the
+    /// density of sampling the path.
+    throughput: RGB,
+    /// Density, per unit area, of the path picking this vertex.
+    pdf_forward: f64,
+    /// Density, per unit area, of a path in the other direction picking this
+    /// vertex from the next one.
+    pdf_reverse: f64,
+    /// Whether the path scattered in a single direction here, which can not
+    /// be connected to.
+    specular: bool,
+    /// Density, per unit area, of the sampled lights picking this vertex.
+    /// Non-zero for camera path vertices on a sampled light.
+    light_pdf: f64,
+}
+
+impl<'a> Vertex<'a> {
+    fn new(kind: VertexKind<'a>, point: Point3d, normal: Vec3d, throughput: RGB) -> Self {
+        Self {
+            kind,
+            point,
+            normal,
+            throughput,
+            pdf_forward: 0.0,
+            pdf_reverse: 0.0,
+            specular: false,
+            light_pdf: 0.0,
+        }
+    }
+
+    fn camera(lens_point: Point3d) -> Self {
+        Self::new(
+            VertexKind::Camera,
+            lens_point,
+            Vec3d::default(),
+            RGB(1.0, 1.0, 1.0),
+        )
+    }
+
+    fn is_volume(&self) -> bool {
+        matches!(self.kind, VertexKind::Scatter { material, .. } if material.is_volume())
+    }
+
+    /// Returns the cosine between the normal and a unit direction, which
+    /// scales the light crossing the surface. Volumes and the camera take
+    /// light from every direction alike.
+    fn cos(&self, direction: &Vec3d) -> f64 {
+        match self.kind {
+            VertexKind::Camera => 1.0,
+            _ if self.is_volume() => 1.0,
+            _ => self.normal.dot(direction).abs(),
+        }
+    }
+
+    /// Converts a density per unit solid angle of the direction from this
+    /// vertex to `next` into a density per unit area at `next`.
+    fn area_pdf(&self, pdf: f64, next: &Vertex) -> f64 {
+        let direction = next.point - self.point;
+        let distance_squared = direction.len_squared();
+        pdf * next.cos(&direction.unit_vector()) / distance_squared
+    }
+
+    /// Returns the hit record of a scatter vertex seen from the `wo` side.
+    fn hit_record(&self, wo: &Vec3d) -> Option<HitRecord<'a>> {
+        let VertexKind::Scatter {
+            material,
+            u,
+            v,
+            front_face,
+        } = self.kind
+        else {
+            return None;
+        };
+        let (normal, front_face) = match self.normal.dot(wo) < 0.0 && !self.is_volume() {
+            true => (-1.0 * self.normal, !front_face),
+            false => (self.normal, front_face),
+        };
+        Some(HitRecord::new(
+            self.point, normal, material, 0.0, u, v, front_face,
+        ))
+    }
+
+    /// Returns the light scattered towards the unit direction `wo` from
+    /// light arriving from the unit direction `wi`, including the cosine of
+    /// `wi`, as `Material::eval`.
+    fn eval(&self, wo: &Vec3d, wi: &Vec3d) -> RGB {
+        match self.hit_record(wo) {
+            Some(hr) => hr.material.eval(&hr, wo, wi),
+            None => RGB(0.0, 0.0, 0.0),
+        }
+    }
+
+    /// Returns the density, per unit area, of this vertex picking `next`
+    /// after being reached from `previous`.
+    fn pdf(&self, film: &Film, previous: Option<&Vertex>, next: &Vertex) -> f64 {
+        let wi = (next.point - self.point).unit_vector();
+        let pdf = match self.kind {
+            VertexKind::Camera => film.direction_pdf(wi),
+            VertexKind::Light { .. } => return self.emission_pdf(next),
+            VertexKind::Scatter { material, .. } => {
+                let Some(previous) = previous else {
+                    return 0.0;
+                };
+                let wo = (previous.point - self.point).unit_vector();
+                match self.hit_record(&wo) {
+                    Some(hr) => material.pdf(&hr, &wo, &wi),
+                    None => 0.0,
+                }
+            }
+        };
+        self.area_pdf(pdf, next)
+    }
+
+    /// Returns the density, per unit area, of light leaving this point on a
+    /// light towards `next`. Lights emit from both sides with a cosine
+    /// distribution.
+    fn emission_pdf(&self, next: &Vertex) -> f64 {
+        let wi = (next.point - self.point).unit_vector();
+        self.area_pdf(self.normal.dot(&wi).abs() / (2.0 * PI), next)
+    }
+}
+
+/// Returns the colour seen along a camera ray by bidirectional path tracing.
+///
+/// A path is traced from the camera as in `ray_colour`, and another from a
+/// random point on a sampled light. Every vertex of the camera path is then
+/// joined to every vertex of the light path by a shadow ray, each join giving
+/// another way of finding a path from the light to the camera. Paths found
+/// several ways are weighted by how likely each way was to find them with
+/// the power heuristic, so the image converges to the same result as path
+/// tracing. Joins to the camera itself are splatted onto `film` at whichever
+/// pixel they are seen in, which finds caustics path tracing rarely does.
+pub(super) fn bidirectional_colour(ray: Ray, scene: &Scene, film: &Film) -> RGB {
+    let time = ray.time;
+    let mut camera_path = vec![Vertex::camera(ray.origin)];
+    let pdf = film.direction_pdf(ray.direction);
+    let escaped = random_walk(
+        scene,
+        ray,
+        RGB(1.0, 1.0, 1.0),
+        pdf,
+        scene.max_depth + 1,
+        true,
+        &mut camera_path,
+    );
+    // Only the camera path can see the background
+    let mut colour = escaped;
+
+    let mut light_path = Vec::new();
+    if let Some(lights) = scene.lights {
+        light_subpath(scene, lights, time, &mut light_path);
+    }
+
+    for t in 1..=camera_path.len() {
+        for s in 0..=light_path.len() {
+            if s + t < 2 || (s, t) == (1, 1) || s + t - 1 > scene.max_depth {
+                continue;
+            }
+            if t == 1 {
+                splat_light_vertex(scene, film, &light_path[..s], time);
+                continue;
+            }
+            let light = connect(scene, &camera_path[..t], &light_path[..s], time);
+            if light != RGB(0.0, 0.0, 0.0) {
+                let weight = mis_weight(film, &camera_path[..t], &light_path[..s]);
+                colour += weight * light;
+            }
+        }
+    }
+    colour
+}
+
+/// Follows a ray from the last vertex of `path`, adding a vertex where it
+/// hits an object and scattering it, until the path has `max_vertices`
+/// vertices or ends by Russian roulette. `pdf` is the density, per unit solid
+/// angle, of the last vertex picking the ray's direction.
+///
+/// Returns the background seen if the ray of a camera path hits nothing.
+fn random_walk<'a>(
+    scene: &Scene<'a>,
+    mut ray: Ray,
+    mut throughput: RGB,
+    mut pdf: f64,
+    max_vertices: usize,
+    is_camera_path: bool,
+    path: &mut Vec<Vertex<'a>>,
+) -> RGB {
+    while path.len() < max_vertices {
+        let Some(hr) = scene.bvh.hit(&ray, 0.001, f64::MAX) else {
+            return match is_camera_path {
+                true => throughput * scene.background.colour(&ray),
+                false => RGB(0.0, 0.0, 0.0),
+            };
+        };
+
+        let mut vertex = Vertex::new(
+            VertexKind::Scatter {
+                material: hr.material,
+                u: hr.u,
+                v: hr.v,
+                front_face: hr.front_face,
+            },
+            hr.point,
+            hr.normal,
+            throughput,
+        );
+        let previous = path.last().expect("paths start at the camera or a light");
+        vertex.pdf_forward = previous.area_pdf(pdf, &vertex);
+        if is_camera_path
+            && hr.material.is_emissive()
+            && let Some(lights) = scene.lights
+        {
+            vertex.light_pdf = lights.surface_pdf(&ray, hr.t);
+        }
+        path.push(vertex);
+        if path.len() == max_vertices {
+            break;
+        }
+
+        let wo = -1.0 * ray.direction.unit_vector();
+        let Some(sample) = hr.material.sample(&hr, &wo) else {
+            break;
+        };
+        let wi = sample.direction.unit_vector();
+        let index = path.len() - 1;
+        // The chance of sampling the path the other way round, from the next
+        // vertex back to the previous one
+        let reverse_pdf = if sample.flags.is_specular() {
+            path[index].specular = true;
+            pdf = 0.0;
+            0.0
+        } else {
+            pdf = sample.pdf;
+            path[index]
+                .hit_record(&wi)
+                .map_or(0.0, |hr| hr.material.pdf(&hr, &wi, &wo))
+        };
+        let (current, previous) = (path[index], path[index - 1]);
+        path[index - 1].pdf_reverse = current.area_pdf(reverse_pdf, &previous);
+
+        let survival_chance = scene.survival_chance(index, throughput * sample.attenuation);
+        if survival_chance < 1.0 && random() >= survival_chance {
+            break;
+        }
+        throughput = throughput * sample.attenuation / survival_chance;
+        ray = Ray::new(hr.point, sample.direction, ray.time);
+    }
+    RGB(0.0, 0.0, 0.0)
+}
+
+/// Traces a path from a random point on a sampled light into `path`.
+fn light_subpath<'a>(
+    scene: &Scene<'a>,
+    lights: &'a dyn Hittable,
+    time: f64,
+    path: &mut Vec<Vertex<'a>>,
+) {
+    let Some((hr, pdf_area)) = lights.sample_surface() else {
+        return;
+    };
+    let emitted = hr.material.emitted(hr.u, hr.v, hr.point);
+    let mut light_vertex = Vertex::new(
+        VertexKind::Light { emitted },
+        hr.point,
+        hr.normal,
+        (1.0 / pdf_area) * RGB(1.0, 1.0, 1.0),
+    );
+    light_vertex.pdf_forward = pdf_area;
+    path.push(light_vertex);
+
+    // Lights emit from both sides, so pick a side then a direction around
+    // its normal
+    let side = if random() < 0.5 { 1.0 } else { -1.0 };
+    let normal = side * hr.normal;
+    let mut direction = normal + random_unit_vec();
+    if direction.near_zero() {
+        direction = normal;
+    }
+    let direction = direction.unit_vector();
+    let pdf = normal.dot(&direction) / (2.0 * PI);
+    if pdf <= 0.0 {
+        return;
+    }
+    let throughput = (normal.dot(&direction) / (pdf_area * pdf)) * emitted;
+    let ray = Ray::new(hr.point, direction, time);
+    random_walk(scene, ray, throughput, pdf, scene.max_depth, false, path);
+}
+
+/// Returns whether nothing blocks the straight line between two points.
+fn visible(scene: &Scene, from: Point3d, to: Point3d, time: f64) -> bool {
+    let direction = to - from;
+    let distance = direction.len();
+    let ray = Ray::new(from, direction / distance, time);
+    scene.bvh.hit(&ray, 0.001, distance - 0.001).is_none()
+}
+
+/// Returns the light carried by the path made of the first `camera.len()`
+/// vertices of the camera path and the first `light.len()` of the light
+/// path, joined between their last vertices, before it is weighted.
+fn connect(scene: &Scene, camera: &[Vertex], light: &[Vertex], time: f64) -> RGB {
+    let black = RGB(0.0, 0.0, 0.0);
+    let pt = &camera[camera.len() - 1];
+    if light.is_empty() {
+        // The camera path hit a light
+        return match pt.kind {
+            VertexKind::Scatter { material, u, v, .. } if material.is_emissive() => {
+                pt.throughput * material.emitted(u, v, pt.point)
+            }
+            _ => black,
+        };
+    }
+
+    let qs = &light[light.len() - 1];
+    if pt.specular || qs.specular {
+        return black;
+    }
+    let direction = qs.point - pt.point;
+    let distance_squared = direction.len_squared();
+    let wi = direction.unit_vector();
+    let camera_wo = (camera[camera.len() - 2].point - pt.point).unit_vector();
+    let camera_f = pt.eval(&camera_wo, &wi);
+    if camera_f == black {
+        return black;
+    }
+    let light_f = match qs.kind {
+        VertexKind::Light { emitted } => qs.cos(&wi) * emitted,
+        _ => {
+            let light_wo = (light[light.len() - 2].point - qs.point).unit_vector();
+            qs.eval(&light_wo, &(-1.0 * wi))
+        }
+    };
+    if light_f == black || !visible(scene, pt.point, qs.point, time) {
+        return black;
+    }
+    (1.0 / distance_squared) * (pt.throughput * camera_f * light_f * qs.throughput)
+}
+
+/// Joins the last vertex of a light path to a random point on the lens, and
+/// splats the light onto the pixels around where it is seen.
+fn splat_light_vertex(scene: &Scene, film: &Film, light: &[Vertex], time: f64) {
+    let qs = &light[light.len() - 1];
+    if qs.specular {
+        return;
+    }
+    let lens_point = film.camera.lens_point();
+    let Some((x, y)) = film.position(lens_point, qs.point) else {
+        return;
+    };
+    let direction = lens_point - qs.point;
+    let distance_squared = direction.len_squared();
+    let wi = direction.unit_vector();
+    let light_wo = (light[light.len() - 2].point - qs.point).unit_vector();
+    let light_f = qs.eval(&light_wo, &wi);
+    if light_f == RGB(0.0, 0.0, 0.0) || !visible(scene, qs.point, lens_point, time) {
+        return;
+    }
+
+    // The camera ray would have had to leave the lens towards the vertex,
+    // and the pixel's value is its average over the pixel's area of the
+    // image
+    let camera = [Vertex::camera(lens_point)];
+    let importance =
+        film.camera.direction_pdf(-1.0 * direction) * ((film.width - 1) * (film.height - 1)) as f64;
+    let weight = mis_weight(film, &camera, light);
+    film.splat(
+        x,
+        y,
+        (weight * importance / distance_squared) * (light_f * qs.throughput),
+    );
+}
+
+/// Returns the weight of a path found by joining the last vertex of `camera`
+/// to the last vertex of `light`, by the power heuristic over all of the
+/// ways it could have been found.
+///
+/// Each other way moves the join along the path, so the ratio of its density
+/// to this way's density is built up a vertex at a time, as in PBRT.
+fn mis_weight(film: &Film, camera: &[Vertex], light: &[Vertex]) -> f64 {
+    let (s, t) = (light.len(), camera.len());
+    if s + t == 2 {
+        // Joining the light to the camera is not done
+        return 1.0;
+    }
+    let mut camera: Vec<Vertex> = camera.to_vec();
+    let mut light: Vec<Vertex> = light.to_vec();
+
+    // The vertices either side of the join could be reached from the other
+    // side of it
+    if s == 0 {
+        let pt = camera[t - 1];
+        if pt.light_pdf <= 0.0 {
+            // Lights that are not sampled can only be found by hitting them
+            return 1.0;
+        }
+        camera[t - 1].pdf_reverse = pt.light_pdf;
+        camera[t - 2].pdf_reverse = pt.emission_pdf(&camera[t - 2]);
+    } else {
+        let (pt, qs) = (camera[t - 1], light[s - 1]);
+        let pt_previous = t.checked_sub(2).map(|i| camera[i]);
+        let qs_previous = s.checked_sub(2).map(|i| light[i]);
+        camera[t - 1].pdf_reverse = qs.pdf(film, qs_previous.as_ref(), &pt);
+        if let Some(pt_previous) = pt_previous {
+            camera[t - 2].pdf_reverse = pt.pdf(film, Some(&qs), &pt_previous);
+        }
+        light[s - 1].pdf_reverse = pt.pdf(film, pt_previous.as_ref(), &qs);
+        if let Some(qs_previous) = qs_previous {
+            light[s - 2].pdf_reverse = qs.pdf(film, Some(&pt), &qs_previous);
+        }
+        camera[t - 1].specular = false;
+        light[s - 1].specular = false;
+    }
+
+    // Specular vertices have no density, they are skipped over
+    let remap = |pdf: f64| if pdf == 0.0 { 1.0 } else { pdf };
+    let mut sum = 0.0;
+    let mut ratio = 1.0;
+    for i in (1..t).rev() {
+        ratio *= remap(camera[i].pdf_reverse) / remap(camera[i].pdf_forward);
+        if !camera[i].specular && !camera[i - 1].specular {
+            sum += ratio * ratio;
+        }
+    }
+    ratio = 1.0;
+    for i in (0..s).rev() {
+        ratio *= remap(light[i].pdf_reverse) / remap(light[i].pdf_forward);
+        let previous_specular = i > 0 && light[i - 1].specular;
+        if !light[i].specular && !previous_specular {
+            sum += ratio * ratio;
+        }
+    }
+    1.0 / (1.0 + sum)
+}
@@ -0,0 +1,406 @@
+use std::{fs, path::Path};
+
+use crate::{Error, Result, colour::RGB, utilities::fnv1a_hash};
+
+use super::{
+    filter::{Filter, FilteredSamples},
+    image::Image,
+    settings::{Background, Integrator, LightSampling, RenderSettings},
+};
+
+/// Identifies checkpoint files, followed by the version of the format.
+const MAGIC: &[u8; 8] = b"RSRTCKPT";
+const VERSION: u64 = 4;
+
+/// The samples taken so far in a progressive render of one camera, see
+/// `Renderer::render_progressive`. Checkpoints can be saved to a file and
+/// loaded later to carry on with the render, or to add more samples to a
+/// finished one.
+///
+/// A checkpoint can only be resumed by a renderer with the same scene and
+/// the same settings, apart from the number of samples, adaptive sampling
+/// and tiles.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    pub(super) camera_index: usize,
+    pub(super) width: usize,
+    pub(super) height: usize,
+    /// Number of passes started, used to seed each pass differently.
+    pub(super) passes: usize,
+    /// The samples per pixel the last finished pass took pixels up to.
+    pub(super) max_samples: usize,
+    pub(super) scene_fingerprint: u64,
+    pub(super) settings_fingerprint: u64,
+    pub(super) pixels: Vec<PixelSamples>,
+    /// The samples spread over each pixel by the pixel filter.
+    pub(super) filtered: Vec<FilteredSamples>,
+    /// Sums of the light that light paths added to each pixel with
+    /// bidirectional path tracing, for every sample taken.
+    pub(super) splats: Vec<RGB>,
+}
+
+impl Checkpoint {
+    /// Creates a checkpoint without any samples.
+    pub(super) fn new(
+        camera_index: usize,
+        settings: &RenderSettings,
+        scene_fingerprint: u64,
+    ) -> Self {
+        Self {
+            camera_index,
+            width: settings.image_width,
+            height: settings.image_height,
+            passes: 0,
+            max_samples: 0,
+            scene_fingerprint,
+            settings_fingerprint: settings_fingerprint(settings),
+            pixels: vec![PixelSamples::default(); settings.image_width * settings.image_height],
+            filtered: vec![
+                FilteredSamples::default();
+                settings.image_width * settings.image_height
+            ],
+            splats: vec![RGB::default(); settings.image_width * settings.image_height],
+        }
+    }
+
+    pub fn camera_index(&self) -> usize {
+        self.camera_index
+    }
+
+    /// Returns the samples per pixel taken so far. Pixels have fewer with
+    /// adaptive sampling or if the render was stopped part way through a
+    /// pass.
+    pub fn max_samples(&self) -> usize {
+        self.max_samples
+    }
+
+    /// Returns the image of the samples taken so far.
+    pub fn image(&self) -> Image {
+        // Each sample traces one light path, which can add light to any pixel
+        let num_light_paths: usize = self.pixels.iter().map(|pixel| pixel.num_samples).sum();
+        let colours = self
+            .pixels
+            .iter()
+            .zip(&self.filtered)
+            .zip(&self.splats)
+            .map(|((pixel, filtered), &splat)| {
+                // Weights of filters with negative lobes can add up to
+                // nothing for pixels with few samples
+                let colour = filtered.colour().unwrap_or_else(|| pixel.colour());
+                match num_light_paths {
+                    0 => colour,
+                    n => colour + splat / n as f64,
+                }
+            })
+            .collect();
+        let sample_counts = self.pixels.iter().map(|pixel| pixel.num_samples).collect();
+        Image::from_pixels(self.width, self.height, colours).with_sample_counts(sample_counts)
+    }
+
+    /// Returns an error if the checkpoint was not made by a renderer with
+    /// these settings and this scene.
+    pub(super) fn check(&self, settings: &RenderSettings, scene_fingerprint: u64) -> Result<()> {
+        if (self.width, self.height) != (settings.image_width, settings.image_height) {
+            return Err(Error::InvalidCheckpoint(format!(
+                "checkpoint is {}x{} pixels but the render is {}x{}",
+                self.width, self.height, settings.image_width, settings.image_height
+            )));
+        }
+        if self.scene_fingerprint != scene_fingerprint {
+            return Err(Error::InvalidCheckpoint(
+                "checkpoint is of a different scene".to_string(),
+            ));
+        }
+        if self.settings_fingerprint != settings_fingerprint(settings) {
+            return Err(Error::InvalidCheckpoint(
+                "checkpoint was rendered with different settings".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Saves the checkpoint to a file. The file is written alongside and
+    /// then renamed, so a render stopped while saving leaves the previous
+    /// checkpoint.
+    pub fn save(&self, file_path: impl AsRef<Path>) -> Result<()> {
+        let file_path = file_path.as_ref();
+        let mut bytes = Vec::with_capacity(64 + self.pixels.len() * 104);
+        bytes.extend_from_slice(MAGIC);
+        for value in [
+            VERSION,
+            self.camera_index as u64,
+            self.width as u64,
+            self.height as u64,
+            self.passes as u64,
+            self.max_samples as u64,
+            self.scene_fingerprint,
+            self.settings_fingerprint,
+        ] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for pixel in &self.pixels {
+            let RGB(r, g, b) = pixel.colour;
+            for value in [r, g, b, pixel.noise.sum, pixel.noise.sum_squares] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            bytes.extend_from_slice(&(pixel.num_samples as u64).to_le_bytes());
+        }
+        for filtered in &self.filtered {
+            let RGB(r, g, b) = filtered.colour;
+            for value in [r, g, b, filtered.weight] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        for &RGB(r, g, b) in &self.splats {
+            for value in [r, g, b] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        // Added to the whole name, so that checkpoints which only differ in
+        // their extension, or end in .tmp themselves, are not written over
+        let mut temporary_name = file_path.file_name().unwrap_or_default().to_os_string();
+        temporary_name.push(".tmp");
+        let temporary_path = file_path.with_file_name(temporary_name);
+        fs::write(&temporary_path, bytes)
+            .and_then(|()| fs::rename(&temporary_path, file_path))
+            .map_err(|source| Error::Io {
+                path: file_path.to_path_buf(),
+                source,
+            })
+    }
+
+    /// Loads a checkpoint saved by `save`.
+    pub fn load(file_path: impl AsRef<Path>) -> Result<Self> {
+        let file_path = file_path.as_ref();
+        let bytes = fs::read(file_path).map_err(|source| Error::Io {
+            path: file_path.to_path_buf(),
+            source,
+        })?;
+        let invalid =
+            |message: &str| Error::InvalidCheckpoint(format!("{}: {message}", file_path.display()));
+
+        let Some(body) = bytes.strip_prefix(MAGIC) else {
+            return Err(invalid("not a checkpoint file"));
+        };
+        let mut reader = Reader(body);
+        if reader.u64()? != VERSION {
+            return Err(invalid("checkpoint was saved by a different version"));
+        }
+        let camera_index = reader.u64()? as usize;
+        let width = reader.u64()? as usize;
+        let height = reader.u64()? as usize;
+        let passes = reader.u64()? as usize;
+        let max_samples = reader.u64()? as usize;
+        let scene_fingerprint = reader.u64()?;
+        let settings_fingerprint = reader.u64()?;
+        let Some(pixels_size) = width
+            .checked_mul(height)
+            .and_then(|num_pixels| num_pixels.checked_mul(104))
+        else {
+            return Err(invalid("checkpoint image size is too large"));
+        };
+        if reader.0.len() != pixels_size {
+            return Err(invalid("checkpoint file is truncated"));
+        }
+        let pixels = (0..width * height)
+            .map(|_| {
+                let colour = RGB(reader.f64()?, reader.f64()?, reader.f64()?);
+                let noise = PixelNoise {
+                    sum: reader.f64()?,
+                    sum_squares: reader.f64()?,
+                };
+                Ok(PixelSamples {
+                    colour,
+                    noise,
+                    num_samples: reader.u64()? as usize,
+                    converged: false,
+                })
+            })
+            .collect::<Result<_>>()?;
+        let filtered = (0..width * height)
+            .map(|_| {
+                Ok(FilteredSamples {
+                    colour: RGB(reader.f64()?, reader.f64()?, reader.f64()?),
+                    weight: reader.f64()?,
+                })
+            })
+            .collect::<Result<_>>()?;
+        let splats = (0..width * height)
+            .map(|_| Ok(RGB(reader.f64()?, reader.f64()?, reader.f64()?)))
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            camera_index,
+            width,
+            height,
+            passes,
+            max_samples,
+            scene_fingerprint,
+            settings_fingerprint,
+            pixels,
+            filtered,
+            splats,
+        })
+    }
+}
+
+/// Returns a fingerprint of the settings that change what a sample sees, so
+/// that samples taken with different settings are not mixed.
+fn settings_fingerprint(settings: &RenderSettings) -> u64 {
+    // Listing every field makes adding a setting a compile error here until
+    // it is decided whether it belongs in the fingerprint
+    let RenderSettings {
+        image_width,
+        image_height,
+        num_samples: _,
+        max_depth,
+        background,
+        time0,
+        time1,
+        seed,
+        light_sampling,
+        russian_roulette_depth,
+        integrator,
+        caustics,
+        adaptive_sampling: _,
+        pixel_filter,
+        tile_size: _,
+        tile_order: _,
+        crop: _,
+    } = *settings;
+    // The crop window is compared by the pixels it covers
+    let area = settings.render_area();
+
+    let mut values = vec![image_width as u64, image_height as u64, max_depth as u64];
+    match background {
+        Background::Sky => values.push(0),
+        Background::Colour(RGB(r, g, b)) => {
+            values.extend([1, r.to_bits(), g.to_bits(), b.to_bits()])
+        }
+    }
+    values.extend([time0.to_bits(), time1.to_bits()]);
+    values.extend(optional(seed));
+    values.push(match light_sampling {
+        LightSampling::Material => 0,
+        LightSampling::Lights => 1,
+        LightSampling::Balance => 2,
+        LightSampling::Power => 3,
+    });
+    values.extend(optional(russian_roulette_depth.map(|depth| depth as u64)));
+    values.push(match integrator {
+        Integrator::PathTracing => 0,
+        Integrator::Bidirectional => 1,
+    });
+    match caustics {
+        Some(caustics) => {
+            values.extend([1, caustics.num_photons as u64, caustics.radius.to_bits()]);
+            values.extend(optional(caustics.radius_reduction.map(f64::to_bits)));
+        }
+        None => values.push(0),
+    }
+    values.push(match pixel_filter.filter {
+        Filter::Box => 0,
+        Filter::Tent => 1,
+        Filter::Gaussian => 2,
+        Filter::Mitchell => 3,
+        Filter::Lanczos => 4,
+    });
+    values.push(pixel_filter.radius.to_bits());
+    values.extend([area.x, area.y, area.width, area.height].map(|value| value as u64));
+
+    let bytes: Vec<u8> = values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect();
+    fnv1a_hash(&bytes)
+}
+
+/// Returns a value that is not set as `[0]` and one that is as `[1, value]`,
+/// so the two can not be mistaken for each other in a fingerprint.
+fn optional(value: Option<u64>) -> Vec<u64> {
+    match value {
+        Some(value) => vec![1, value],
+        None => vec![0],
+    }
+}
+
+/// Reads little endian numbers from the start of a slice.
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn bytes(&mut self) -> Result<[u8; 8]> {
+        let Some((bytes, rest)) = self.0.split_first_chunk::<8>() else {
+            return Err(Error::InvalidCheckpoint(
+                "checkpoint file is truncated".to_string(),
+            ));
+        };
+        self.0 = rest;
+        Ok(*bytes)
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        self.bytes().map(u64::from_le_bytes)
+    }
+
+    fn f64(&mut self) -> Result<f64> {
+        self.bytes().map(f64::from_le_bytes)
+    }
+}
+
+/// The samples taken for a pixel so far.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(super) struct PixelSamples {
+    /// Sum of the samples.
+    pub colour: RGB,
+    pub noise: PixelNoise,
+    pub num_samples: usize,
+    /// Whether adaptive sampling has found the pixel is no longer noisy.
+    pub converged: bool,
+}
+
+impl PixelSamples {
+    pub fn add(&mut self, sample: RGB) {
+        self.colour += sample;
+        self.noise.add(sample);
+        self.num_samples += 1;
+    }
+
+    /// Returns the average of the samples, black if there are none.
+    pub fn colour(&self) -> RGB {
+        match self.num_samples {
+            0 => RGB(0.0, 0.0, 0.0),
+            num_samples => self.colour / num_samples as f64,
+        }
+    }
+}
+
+/// Running sums of the brightness of a pixel's samples, used to measure how
+/// noisy the pixel is.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(super) struct PixelNoise {
+    sum: f64,
+    sum_squares: f64,
+}
+
+impl PixelNoise {
+    fn add(&mut self, sample: RGB) {
+        let brightness = (sample.0 + sample.1 + sample.2) / 3.0;
+        self.sum += brightness;
+        self.sum_squares += brightness * brightness;
+    }
+
+    /// Returns the standard error of the pixel's brightness after `num_samples`
+    /// samples, scaled to how it appears once gamma corrected. Errors in dark
+    /// pixels are scaled up as gamma correction brightens them.
+    pub fn standard_error(&self, num_samples: usize) -> f64 {
+        let n = num_samples as f64;
+        let mean = self.sum / n;
+        let variance = (self.sum_squares / n - mean * mean).max(0.0) / (n - 1.0);
+        if variance == 0.0 {
+            return 0.0;
+        }
+        // The slope of the gamma correction, sqrt, at the mean
+        variance.sqrt() / (2.0 * mean.sqrt())
+    }
+}
@@ -0,0 +1,359 @@
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{Error, Result, colour::RGB};
+
+use super::image::Image;
+
+/// Weights of the 5 taps of the B3 spline the filter blurs with along each
+/// axis.
+const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+/// Albedo below this is treated as white when dividing it out, e.g. for a
+/// black background.
+const MIN_ALBEDO: f64 = 0.01;
+
+/// Removes the noise from a render with an edge-avoiding à-trous wavelet
+/// filter (Dammertz et al. 2010), guided by the albedo, normal and depth AOVs
+/// of the render so that edges and textures stay sharp.
+///
+/// The filter blurs each pixel with its neighbours a number of times, each
+/// time reaching twice as far, and takes less from neighbours the more their
+/// colour, normal, depth or albedo differ. The lighting is filtered rather
+/// than the colour: pixels are divided by their albedo before and multiplied
+/// by it after, so textures are kept however much the lighting is smoothed.
+///
+/// ```no_run
+/// # use rs_ray_tracer::{render::{Aov, Denoiser}, scenes};
+/// let renderer = scenes::get("cornell_box").unwrap().renderer()?.samples(32).build()?;
+/// let image = renderer.render(0);
+/// let [albedo, normal, depth] = &renderer.render_aovs(0, &[Aov::Albedo, Aov::Normal, Aov::Depth])[..]
+/// else {
+///     unreachable!()
+/// };
+/// let denoised = Denoiser::default().denoise(&image, albedo, normal, depth)?;
+/// # Ok::<(), rs_ray_tracer::Error>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Denoiser {
+    /// Number of times the image is filtered. After `n` passes the filter
+    /// reaches `2^(n + 1) - 2` pixels from each pixel.
+    pub passes: usize,
+    /// How different the lighting of two pixels, once gamma corrected, can
+    /// be before they are kept apart. Halved with each pass, so the first
+    /// passes smooth away the noise and later passes keep shadow edges.
+    pub colour_sigma: f64,
+    /// How different the normals of two pixels can be before they are kept
+    /// apart.
+    pub normal_sigma: f64,
+    /// How different the depths of two pixels can be, as a share of the
+    /// depth, before they are kept apart.
+    pub depth_sigma: f64,
+    /// How different the albedos of two pixels can be before they are kept
+    /// apart.
+    pub albedo_sigma: f64,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self {
+            passes: 5,
+            colour_sigma: 0.5,
+            normal_sigma: 0.3,
+            depth_sigma: 0.1,
+            albedo_sigma: 0.1,
+        }
+    }
+}
+
+impl Denoiser {
+    /// Returns the image with its noise removed, guided by AOVs of the same
+    /// size from `Renderer::render_aovs`. Pixels without samples, outside a
+    /// crop window, are left out. Returns an error if the AOVs are not the
+    /// same size as the image.
+    pub fn denoise(
+        &self,
+        image: &Image,
+        albedo: &Image,
+        normal: &Image,
+        depth: &Image,
+    ) -> Result<Image> {
+        let (width, height) = (image.width(), image.height());
+        for guide in [albedo, normal, depth] {
+            if (guide.width(), guide.height()) != (width, height) {
+                return Err(Error::InvalidSettings(format!(
+                    "AOVs must be the same size as the image, {width}x{height}, found {}x{}",
+                    guide.width(),
+                    guide.height()
+                )));
+            }
+        }
+        let has_samples: Vec<bool> = match image.sample_counts() {
+            Some(sample_counts) => sample_counts.iter().map(|&count| count > 0).collect(),
+            None => vec![true; width * height],
+        };
+
+        let albedos: Vec<RGB> = albedo
+            .pixels()
+            .iter()
+            .map(|&RGB(r, g, b)| RGB(divisor(r), divisor(g), divisor(b)))
+            .collect();
+        let lighting: Vec<RGB> = image
+            .pixels()
+            .iter()
+            .zip(&albedos)
+            .map(|(colour, albedo)| {
+                RGB(
+                    colour.0 / albedo.0,
+                    colour.1 / albedo.1,
+                    colour.2 / albedo.2,
+                )
+            })
+            .collect();
+
+        let guides = Guides {
+            width,
+            height,
+            has_samples: &has_samples,
+            albedo: albedo.pixels(),
+            normal: normal.pixels(),
+            depth: depth.pixels(),
+        };
+        let mut lighting = clamp_fireflies(&guides, &lighting);
+        for pass in 0..self.passes {
+            let step = 1 << pass;
+            let colour_sigma = self.colour_sigma / step as f64;
+            lighting = (0..width * height)
+                .into_par_iter()
+                .map(|index| self.filter_pixel(&guides, &lighting, index, step, colour_sigma))
+                .collect();
+        }
+
+        let pixels = lighting
+            .iter()
+            .zip(&albedos)
+            .map(|(&lighting, &albedo)| lighting * albedo)
+            .collect();
+        let denoised = Image::from_pixels(width, height, pixels);
+        Ok(match image.sample_counts() {
+            Some(sample_counts) => denoised.with_sample_counts(sample_counts.to_vec()),
+            None => denoised,
+        })
+    }
+
+    /// Returns the weighted average of a pixel and its neighbours `step`
+    /// pixels apart for one pass of the filter.
+    fn filter_pixel(
+        &self,
+        guides: &Guides,
+        lighting: &[RGB],
+        index: usize,
+        step: usize,
+        colour_sigma: f64,
+    ) -> RGB {
+        if !guides.has_samples[index] {
+            return lighting[index];
+        }
+        let (x, y) = (index % guides.width, index / guides.width);
+        let centre = gamma(lighting[index]);
+
+        let mut sum = RGB(0.0, 0.0, 0.0);
+        let mut total_weight = 0.0;
+        for (dy, ky) in KERNEL.iter().enumerate() {
+            let Some(ny) = offset(y, dy, step, guides.height) else {
+                continue;
+            };
+            for (dx, kx) in KERNEL.iter().enumerate() {
+                let Some(nx) = offset(x, dx, step, guides.width) else {
+                    continue;
+                };
+                let neighbour = ny * guides.width + nx;
+                if !guides.has_samples[neighbour] {
+                    continue;
+                }
+
+                let colour_distance = distance_squared(centre, gamma(lighting[neighbour]));
+                let weight = kx
+                    * ky
+                    * (-colour_distance / (colour_sigma * colour_sigma)).exp()
+                    * self.guide_weight(guides, index, neighbour);
+                sum += weight * lighting[neighbour];
+                total_weight += weight;
+            }
+        }
+        // The pixel itself always has some weight
+        sum / total_weight
+    }
+
+    /// Returns how alike the normals, depths and albedos of two pixels are,
+    /// from 0 to 1.
+    fn guide_weight(&self, guides: &Guides, a: usize, b: usize) -> f64 {
+        let normal_distance = distance_squared(guides.normal[a], guides.normal[b]);
+        let albedo_distance = distance_squared(guides.albedo[a], guides.albedo[b]);
+        let (depth_a, depth_b) = (guides.depth[a].0, guides.depth[b].0);
+        let depth_weight = match (depth_a.is_finite(), depth_b.is_finite()) {
+            (true, true) => {
+                let scale = self.depth_sigma * depth_a.max(f64::EPSILON);
+                (-(depth_a - depth_b).abs() / scale).exp()
+            }
+            // The background only blends with the background
+            (finite_a, finite_b) => f64::from(finite_a == finite_b),
+        };
+        (-normal_distance / (self.normal_sigma * self.normal_sigma)).exp()
+            * (-albedo_distance / (self.albedo_sigma * self.albedo_sigma)).exp()
+            * depth_weight
+    }
+}
+
+/// The AOVs guiding the filter, and which pixels have samples.
+struct Guides<'a> {
+    width: usize,
+    height: usize,
+    has_samples: &'a [bool],
+    albedo: &'a [RGB],
+    normal: &'a [RGB],
+    depth: &'a [RGB],
+}
+
+/// Returns the lighting with each pixel no brighter than the second
+/// brightest of its 8 neighbours. Fireflies, single pixels or pairs far
+/// brighter than those around them, would otherwise be kept apart from their
+/// neighbours by the colour weight and survive the filter as blotches.
+fn clamp_fireflies(guides: &Guides, lighting: &[RGB]) -> Vec<RGB> {
+    (0..guides.width * guides.height)
+        .into_par_iter()
+        .map(|index| {
+            let colour = lighting[index];
+            if !guides.has_samples[index] {
+                return colour;
+            }
+            let (x, y) = (index % guides.width, index / guides.width);
+            let mut brightest = [f64::NEG_INFINITY; 2];
+            for ny in y.saturating_sub(1)..(y + 2).min(guides.height) {
+                for nx in x.saturating_sub(1)..(x + 2).min(guides.width) {
+                    let neighbour = ny * guides.width + nx;
+                    if neighbour == index || !guides.has_samples[neighbour] {
+                        continue;
+                    }
+                    let brightness = brightness(lighting[neighbour]);
+                    if brightness > brightest[0] {
+                        brightest = [brightness, brightest[0]];
+                    } else if brightness > brightest[1] {
+                        brightest[1] = brightness;
+                    }
+                }
+            }
+            // Pixels with fewer than 2 neighbours are left alone
+            let limit = brightest[1].max(0.0);
+            match brightness(colour) {
+                brightness if brightest[1].is_finite() && brightness > limit => {
+                    (limit / brightness) * colour
+                }
+                _ => colour,
+            }
+        })
+        .collect()
+}
+
+/// Returns the brightest channel of a colour.
+fn brightness(colour: RGB) -> f64 {
+    colour.0.max(colour.1).max(colour.2)
+}
+
+/// Returns the position of the `tap`th tap of the kernel around `position`,
+/// `None` if it is outside the image.
+fn offset(position: usize, tap: usize, step: usize, size: usize) -> Option<usize> {
+    let position = (position + tap * step).checked_sub(2 * step)?;
+    (position < size).then_some(position)
+}
+
+fn divisor(albedo: f64) -> f64 {
+    if albedo < MIN_ALBEDO { 1.0 } else { albedo }
+}
+
+/// Gamma corrects a colour the way images are saved, so that differences in
+/// dark and bright areas count the same as they look.
+fn gamma(colour: RGB) -> RGB {
+    RGB(
+        colour.0.max(0.0).sqrt(),
+        colour.1.max(0.0).sqrt(),
+        colour.2.max(0.0).sqrt(),
+    )
+}
+
+fn distance_squared(a: RGB, b: RGB) -> f64 {
+    let difference = a - b;
+    difference.0 * difference.0 + difference.1 * difference.1 + difference.2 * difference.2
+}
+
+#[cfg(test)]
+mod denoise_tests {
+    use crate::utilities::{random_rng, seed_random};
+
+    use super::*;
+
+    #[test]
+    fn denoise_should_smooth_noise_but_keep_edges() {
+        // A corner between two walls of different colours, one lit more
+        // brightly than the other
+        let (width, height) = (32, 16);
+        let is_left = |x: usize| x < width / 2;
+        let wall = |x: usize| match is_left(x) {
+            true => RGB(0.8, 0.2, 0.2),
+            false => 0.3 * RGB(0.2, 0.2, 0.8),
+        };
+        let guide = |colour: &dyn Fn(usize) -> RGB| {
+            let pixels = (0..width * height).map(|index| colour(index % width));
+            Image::from_pixels(width, height, pixels.collect())
+        };
+        let albedo = guide(&|x| match is_left(x) {
+            true => RGB(0.8, 0.2, 0.2),
+            false => RGB(0.2, 0.2, 0.8),
+        });
+        let normal = guide(&|x| match is_left(x) {
+            true => RGB(1.0, 0.0, 0.0),
+            false => RGB(0.0, 0.0, -1.0),
+        });
+        let depth = guide(&|_| RGB(5.0, 5.0, 5.0));
+
+        seed_random(3);
+        let noisy = guide(&|x| random_rng(0.5, 1.5) * wall(x));
+        let denoised = Denoiser::default()
+            .denoise(&noisy, &albedo, &normal, &depth)
+            .unwrap();
+
+        let error = |image: &Image| {
+            let total: f64 = (0..width * height)
+                .map(|index| distance_squared(image.pixels()[index], wall(index % width)))
+                .sum();
+            (total / (width * height) as f64).sqrt()
+        };
+        assert!(
+            error(&denoised) < 0.3 * error(&noisy),
+            "{} vs {}",
+            error(&denoised),
+            error(&noisy)
+        );
+        // Neither wall's light bleeds into the other
+        for x in [width / 2 - 1, width / 2] {
+            let column: RGB =
+                (0..height).fold(RGB(0.0, 0.0, 0.0), |sum, y| sum + denoised.get(x, y));
+            let mean = column / height as f64;
+            assert!(
+                distance_squared(mean, wall(x)).sqrt() < 0.05,
+                "{mean:?} at {x}"
+            );
+        }
+    }
+
+    #[test]
+    fn denoise_should_reject_aovs_of_a_different_size() {
+        let image = Image::new(4, 4);
+        let guide = Image::new(4, 4);
+        let small = Image::new(2, 4);
+
+        assert!(matches!(
+            Denoiser::default().denoise(&image, &guide, &small, &guide),
+            Err(Error::InvalidSettings(_))
+        ));
+    }
+}
@@ -0,0 +1,331 @@
+use std::f64::consts::PI;
+
+use crate::colour::RGB;
+
+use super::tiles::TileRect;
+
+/// The shape of a pixel filter, see `PixelFilter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Filter {
+    /// Every sample within the radius counts the same. With a radius of half
+    /// a pixel each pixel is the average of its own samples.
+    #[default]
+    Box,
+    /// Samples count less the further they are from the pixel's centre,
+    /// falling to nothing at the radius.
+    Tent,
+    /// A Gaussian with a standard deviation of a third of the radius, soft
+    /// but without jagged edges.
+    Gaussian,
+    /// The Mitchell-Netravali cubic with B = C = 1/3, a balance of sharpness
+    /// and ringing. Its negative lobes sharpen edges.
+    Mitchell,
+    /// A windowed sinc, as many lobes wide as the radius, the sharpest of
+    /// the filters but it rings around bright edges.
+    Lanczos,
+}
+
+impl Filter {
+    /// Returns the radius the filter is usually used with, in pixels.
+    pub fn default_radius(self) -> f64 {
+        match self {
+            Filter::Box => 0.5,
+            Filter::Tent => 1.0,
+            Filter::Gaussian => 1.5,
+            Filter::Mitchell => 2.0,
+            Filter::Lanczos => 3.0,
+        }
+    }
+}
+
+/// How the samples of the image are made into pixels (reconstruction
+/// filtering). Each sample is spread over the pixels whose centres are
+/// within the radius of it, weighted by the filter, and each pixel is the
+/// weighted average of the samples it receives. Wider filters anti-alias
+/// edges such as those of checker textures and triangles better, at the cost
+/// of a softer image.
+///
+/// Samples are only spread over the pixels being rendered, so the pixels at
+/// the edges of a crop window do not have the samples from outside it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PixelFilter {
+    pub filter: Filter,
+    /// How far a sample reaches along each axis, in pixels, at least half a
+    /// pixel so that every sample reaches the pixel it was taken for.
+    pub radius: f64,
+}
+
+impl Default for PixelFilter {
+    fn default() -> Self {
+        Self::new(Filter::Box)
+    }
+}
+
+impl PixelFilter {
+    /// Returns the filter with the radius it is usually used with.
+    pub fn new(filter: Filter) -> Self {
+        Self {
+            filter,
+            radius: filter.default_radius(),
+        }
+    }
+
+    pub fn radius(mut self, radius: f64) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    /// Returns the weight of a sample `offset` pixels from the centre of a
+    /// pixel along one axis.
+    fn weight(&self, offset: f64) -> f64 {
+        let radius = self.radius;
+        let x = offset.abs();
+        match self.filter {
+            // Half open, like the pixels, so that a sample on the edge
+            // between two pixels only reaches one of them
+            Filter::Box => f64::from((-radius..radius).contains(&offset)),
+            _ if x >= radius => 0.0,
+            Filter::Tent => 1.0 - x / radius,
+            Filter::Gaussian => {
+                // Shifted down to reach 0 at the radius
+                let sigma = radius / 3.0;
+                let gaussian = |x: f64| (-x * x / (2.0 * sigma * sigma)).exp();
+                gaussian(x) - gaussian(radius)
+            }
+            Filter::Mitchell => mitchell(2.0 * x / radius),
+            Filter::Lanczos => sinc(x) * sinc(x / radius),
+        }
+    }
+
+    /// Calls `f` with the column and row, counted from the bottom left
+    /// corner and possibly outside the image, and weight of every pixel a
+    /// sample at `(x, y)` reaches. The sample's position is in pixels from
+    /// the bottom left corner, so pixel `(i, j)` covers `i <= x < i + 1` and
+    /// `j <= y < j + 1`.
+    pub(super) fn for_each_pixel(&self, x: f64, y: f64, mut f: impl FnMut(isize, isize, f64)) {
+        let (mut columns, mut rows) = (Vec::new(), Vec::new());
+        self.axis_weights(x, &mut columns);
+        self.axis_weights(y, &mut rows);
+        for &(j, row_weight) in &rows {
+            for &(i, column_weight) in &columns {
+                f(i, j, column_weight * row_weight);
+            }
+        }
+    }
+
+    /// Replaces `weights` with the column, or row, and weight of every pixel
+    /// along one axis that a sample at `position` on that axis reaches.
+    fn axis_weights(&self, position: f64, weights: &mut Vec<(isize, f64)>) {
+        // Pixels whose centres, at i + 0.5, are within the radius
+        let first = (position - 0.5 - self.radius).ceil() as isize;
+        let last = (position - 0.5 + self.radius).floor() as isize;
+        weights.clear();
+        weights.extend(
+            (first..=last)
+                .map(|i| (i, self.weight(position - (i as f64 + 0.5))))
+                .filter(|&(_, weight)| weight != 0.0),
+        );
+    }
+}
+
+/// The Mitchell-Netravali cubic with B = C = 1/3 at `x` from 0 to 2.
+fn mitchell(x: f64) -> f64 {
+    const B: f64 = 1.0 / 3.0;
+    const C: f64 = 1.0 / 3.0;
+    let value = match x {
+        x if x < 1.0 => {
+            (12.0 - 9.0 * B - 6.0 * C) * x * x * x
+                + (-18.0 + 12.0 * B + 6.0 * C) * x * x
+                + (6.0 - 2.0 * B)
+        }
+        x if x < 2.0 => {
+            (-B - 6.0 * C) * x * x * x
+                + (6.0 * B + 30.0 * C) * x * x
+                + (-12.0 * B - 48.0 * C) * x
+                + (8.0 * B + 24.0 * C)
+        }
+        _ => 0.0,
+    };
+    value / 6.0
+}
+
+fn sinc(x: f64) -> f64 {
+    match x.abs() < 1e-9 {
+        true => 1.0,
+        false => (PI * x).sin() / (PI * x),
+    }
+}
+
+/// The weighted sum of the samples a pixel has received from the pixel
+/// filter.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(super) struct FilteredSamples {
+    pub colour: RGB,
+    pub weight: f64,
+}
+
+impl FilteredSamples {
+    pub fn add(&mut self, other: &FilteredSamples) {
+        self.colour += other.colour;
+        self.weight += other.weight;
+    }
+
+    /// Returns the weighted average of the samples, `None` if the weights
+    /// do not add up to more than nothing.
+    pub fn colour(&self) -> Option<RGB> {
+        (self.weight > 0.0).then(|| self.colour / self.weight)
+    }
+}
+
+/// The samples a tile has spread over the pixels around it, which reach past
+/// the tile by the radius of the filter.
+pub(super) struct TileSplats {
+    filter: PixelFilter,
+    image_height: usize,
+    /// The pixels reached, counted from the top left corner.
+    rect: TileRect,
+    pixels: Vec<FilteredSamples>,
+    /// Reused for the weights of each sample along each axis.
+    columns: Vec<(isize, f64)>,
+    rows: Vec<(isize, f64)>,
+}
+
+impl TileSplats {
+    /// Creates splats for a tile, reaching no further than `area`.
+    pub fn new(filter: PixelFilter, image_height: usize, tile: TileRect, area: TileRect) -> Self {
+        // The furthest a sample reaches past the pixel it was taken for
+        let reach = ((filter.radius + 0.5).ceil() as usize).saturating_sub(1);
+        let x0 = tile.x.saturating_sub(reach).max(area.x);
+        let y0 = tile.y.saturating_sub(reach).max(area.y);
+        let x1 = (tile.x + tile.width + reach).min(area.x + area.width);
+        let y1 = (tile.y + tile.height + reach).min(area.y + area.height);
+        let rect = TileRect {
+            x: x0,
+            y: y0,
+            width: x1 - x0,
+            height: y1 - y0,
+        };
+        Self {
+            filter,
+            image_height,
+            rect,
+            pixels: vec![FilteredSamples::default(); rect.width * rect.height],
+            columns: Vec::new(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Spreads a sample at `(x, y)`, in pixels from the bottom left corner
+    /// of the image, over the pixels around it.
+    pub fn add(&mut self, x: f64, y: f64, colour: RGB) {
+        let rect = self.rect;
+        let image_height = self.image_height as isize;
+        self.filter.axis_weights(x, &mut self.columns);
+        self.filter.axis_weights(y, &mut self.rows);
+        // Image rows go top -> bottom, but j goes bottom -> top
+        let in_rect = |position: isize, start: usize, size: usize| {
+            usize::try_from(position)
+                .ok()
+                .filter(|&position| (start..start + size).contains(&position))
+                .map(|position| position - start)
+        };
+        for &(j, row_weight) in &self.rows {
+            let Some(row) = in_rect(image_height - 1 - j, rect.y, rect.height) else {
+                continue;
+            };
+            for &(i, column_weight) in &self.columns {
+                let Some(column) = in_rect(i, rect.x, rect.width) else {
+                    continue;
+                };
+                let weight = column_weight * row_weight;
+                let pixel = &mut self.pixels[row * rect.width + column];
+                pixel.colour += weight * colour;
+                pixel.weight += weight;
+            }
+        }
+    }
+
+    /// Returns the splats onto the pixel at `index` of an image `image_width`
+    /// pixels wide, nothing if the tile does not reach it.
+    pub fn get(&self, index: usize, image_width: usize) -> FilteredSamples {
+        let (column, row) = (index % image_width, index / image_width);
+        let rect = self.rect;
+        match (rect.x..rect.x + rect.width).contains(&column)
+            && (rect.y..rect.y + rect.height).contains(&row)
+        {
+            true => self.pixels[(row - rect.y) * rect.width + column - rect.x],
+            false => FilteredSamples::default(),
+        }
+    }
+
+    /// Adds the splats to the sums of an image `image_width` pixels wide.
+    pub fn add_to(&self, sums: &mut [FilteredSamples], image_width: usize) {
+        for (index, pixel) in self.rect.indexes(image_width).zip(&self.pixels) {
+            sums[index].add(pixel);
+        }
+    }
+}
+
+#[cfg(test)]
+mod filter_tests {
+    use super::*;
+
+    #[test]
+    fn filters_should_fall_to_nothing_at_their_radius() {
+        for filter in [
+            Filter::Box,
+            Filter::Tent,
+            Filter::Gaussian,
+            Filter::Mitchell,
+            Filter::Lanczos,
+        ] {
+            let pixel_filter = PixelFilter::new(filter);
+            let radius = pixel_filter.radius;
+            assert!(pixel_filter.weight(0.0) > 0.0, "{filter:?}");
+            assert!(pixel_filter.weight(radius).abs() < 1e-9, "{filter:?}");
+            assert_eq!(pixel_filter.weight(radius + 0.1), 0.0, "{filter:?}");
+            assert_eq!(
+                pixel_filter.weight(0.3),
+                pixel_filter.weight(-0.3),
+                "{filter:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn box_filter_should_only_reach_the_sample_pixel() {
+        let filter = PixelFilter::default();
+        for (x, y) in [(3.0, 5.0), (3.999, 5.5), (3.5, 5.999)] {
+            let mut reached = Vec::new();
+            filter.for_each_pixel(x, y, |i, j, weight| reached.push((i, j, weight)));
+            assert_eq!(reached, vec![(3, 5, 1.0)], "({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn tile_splats_should_stay_in_the_render_area() {
+        let filter = PixelFilter::new(Filter::Tent).radius(1.5);
+        let area = TileRect {
+            x: 2,
+            y: 2,
+            width: 6,
+            height: 6,
+        };
+        let tile = TileRect {
+            x: 2,
+            y: 2,
+            width: 2,
+            height: 2,
+        };
+        let mut splats = TileSplats::new(filter, 10, tile, area);
+        // Pixel (2, 2) from the top left, (2, 7) from the bottom left
+        splats.add(2.5, 7.5, RGB(1.0, 1.0, 1.0));
+
+        let mut sums = vec![FilteredSamples::default(); 100];
+        splats.add_to(&mut sums, 10);
+        let reached: Vec<usize> = (0..100).filter(|&i| sums[i].weight > 0.0).collect();
+        assert_eq!(reached, vec![22, 23, 32, 33]);
+        assert_eq!(sums[22].colour(), Some(RGB(1.0, 1.0, 1.0)));
+        assert_eq!(sums[22].weight, 1.0);
+    }
+}
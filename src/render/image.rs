@@ -0,0 +1,245 @@
+use crate::{
+    Result,
+    colour::RGB,
+    utilities::{read_image_file, save_as_exr, save_as_png, save_as_ppm},
+};
+
+use super::settings::CropWindow;
+
+/// A rendered image. Pixels are stored a row at a time from the top left
+/// corner, each pixel is the average of the samples taken for it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<RGB>,
+    /// Number of samples taken for each pixel, if known.
+    sample_counts: Option<Vec<usize>>,
+}
+
+impl Image {
+    /// Creates a black image.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![RGB(0.0, 0.0, 0.0); width * height],
+            sample_counts: None,
+        }
+    }
+
+    /// Creates an image from pixels stored a row at a time from the top left
+    /// corner.
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<RGB>) -> Self {
+        assert_eq!(
+            pixels.len(),
+            width * height,
+            "Number of pixels does not match image size; width: {width}, height: {height}"
+        );
+        Self {
+            width,
+            height,
+            pixels,
+            sample_counts: None,
+        }
+    }
+
+    /// Adds the number of samples taken for each pixel, stored in the same
+    /// order as the pixels.
+    pub fn with_sample_counts(mut self, sample_counts: Vec<usize>) -> Self {
+        assert_eq!(
+            sample_counts.len(),
+            self.pixels.len(),
+            "Number of sample counts does not match image size; width: {}, height: {}",
+            self.width,
+            self.height
+        );
+        self.sample_counts = Some(sample_counts);
+        self
+    }
+
+    /// Loads an image saved by `save_png` or `save_ppm`, e.g. to merge a
+    /// region rendered again into it with `fill_region`.
+    pub fn load(file_name: &str) -> Result<Self> {
+        let (width, height, pixels) = read_image_file(file_name)?;
+        // Undo the gamma correction of saved images
+        let pixels = pixels.iter().map(|&colour| colour * colour).collect();
+        Ok(Self::from_pixels(width, height, pixels))
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[RGB] {
+        &self.pixels
+    }
+
+    /// Returns the number of samples taken for each pixel, for images from a
+    /// `Renderer`.
+    pub fn sample_counts(&self) -> Option<&[usize]> {
+        self.sample_counts.as_deref()
+    }
+
+    /// Returns a heatmap of the number of samples taken for each pixel, from
+    /// black for the fewest through blue, red and yellow to white for the
+    /// most. Returns `None` if the sample counts are not known.
+    pub fn sample_heatmap(&self) -> Option<Image> {
+        let sample_counts = self.sample_counts.as_ref()?;
+        let min = sample_counts.iter().copied().min().unwrap_or(0);
+        let max = sample_counts.iter().copied().max().unwrap_or(0);
+        let pixels = sample_counts
+            .iter()
+            .map(|&count| {
+                let t = match max - min {
+                    0 => 1.0,
+                    range => (count - min) as f64 / range as f64,
+                };
+                heatmap_colour(t)
+            })
+            .collect();
+        Some(Image::from_pixels(self.width, self.height, pixels))
+    }
+
+    /// Returns the pixel at column `x` and row `y`, counted from the top left.
+    pub fn get(&self, x: usize, y: usize) -> RGB {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, colour: RGB) {
+        self.pixels[y * self.width + x] = colour;
+    }
+
+    /// Returns the part of the image inside a crop window.
+    pub fn crop(&self, window: CropWindow) -> Image {
+        let rect = window.rect(self.width, self.height);
+        let pixels = rect.indexes(self.width).map(|i| self.pixels[i]).collect();
+        let image = Image::from_pixels(rect.width, rect.height, pixels);
+        match &self.sample_counts {
+            Some(sample_counts) => image
+                .with_sample_counts(rect.indexes(self.width).map(|i| sample_counts[i]).collect()),
+            None => image,
+        }
+    }
+
+    /// Replaces the part of the image inside a crop window with the same
+    /// part of another image of the same size, e.g. to merge a region
+    /// rendered again with more samples into a full render.
+    pub fn fill_region(&mut self, other: &Image, window: CropWindow) {
+        assert_eq!(
+            (other.width, other.height),
+            (self.width, self.height),
+            "Images must be the same size to fill a region"
+        );
+        for i in window.rect(self.width, self.height).indexes(self.width) {
+            self.pixels[i] = other.pixels[i];
+        }
+        match (&mut self.sample_counts, &other.sample_counts) {
+            (Some(sample_counts), Some(other_counts)) => {
+                for i in window.rect(self.width, self.height).indexes(self.width) {
+                    sample_counts[i] = other_counts[i];
+                }
+            }
+            (sample_counts, _) => *sample_counts = None,
+        }
+    }
+
+    pub fn save_png(&self, file_name: &str) -> Result<()> {
+        save_as_png(file_name, self.width, self.height, &self.pixels, 1)
+    }
+
+    pub fn save_ppm(&self, file_name: &str) -> Result<()> {
+        save_as_ppm(file_name, self.width, self.height, &self.pixels, 1)
+    }
+
+    /// Saves the pixels as they are, without gamma correction, in an OpenEXR
+    /// file, e.g. for AOVs, which hold values outside 0 to 1.
+    pub fn save_exr(&self, file_name: &str) -> Result<()> {
+        save_as_exr(file_name, self.width, self.height, &self.pixels)
+    }
+}
+
+/// Returns the colour `t` of the way along the heatmap, from 0 to 1. Colours
+/// are squared to undo the gamma correction when saved.
+fn heatmap_colour(t: f64) -> RGB {
+    const STOPS: [RGB; 5] = [
+        RGB(0.0, 0.0, 0.0),
+        RGB(0.0, 0.0, 1.0),
+        RGB(1.0, 0.0, 0.0),
+        RGB(1.0, 1.0, 0.0),
+        RGB(1.0, 1.0, 1.0),
+    ];
+    let position = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f64;
+    let i = (position as usize).min(STOPS.len() - 2);
+    let f = position - i as f64;
+    let colour = (1.0 - f) * STOPS[i] + f * STOPS[i + 1];
+    colour * colour
+}
+
+#[cfg(test)]
+mod image_tests {
+    use super::*;
+
+    #[test]
+    fn sample_heatmap_should_go_from_black_to_white() {
+        let image = Image::new(3, 1).with_sample_counts(vec![16, 40, 64]);
+
+        let heatmap = image.sample_heatmap().unwrap();
+
+        assert_eq!(heatmap.get(0, 0), RGB(0.0, 0.0, 0.0));
+        assert_eq!(heatmap.get(1, 0), RGB(1.0, 0.0, 0.0));
+        assert_eq!(heatmap.get(2, 0), RGB(1.0, 1.0, 1.0));
+        assert!(Image::new(3, 1).sample_heatmap().is_none());
+    }
+
+    /// A 4x3 image where each pixel's red is its index.
+    fn numbered_image() -> Image {
+        let pixels = (0..12).map(|i| RGB(i as f64, 0.0, 0.0)).collect();
+        Image::from_pixels(4, 3, pixels).with_sample_counts((0..12).collect())
+    }
+
+    #[test]
+    fn crop_should_return_the_pixels_in_the_window() {
+        let window = CropWindow::Pixels {
+            x: 1,
+            y: 1,
+            width: 2,
+            height: 2,
+        };
+
+        let cropped = numbered_image().crop(window);
+
+        let red: Vec<f64> = cropped.pixels().iter().map(|colour| colour.0).collect();
+        assert_eq!(red, [5.0, 6.0, 9.0, 10.0]);
+        assert_eq!(cropped.sample_counts(), Some(&[5, 6, 9, 10][..]));
+        let normalised = CropWindow::Normalised {
+            x0: 0.3,
+            y0: 0.5,
+            x1: 0.6,
+            y1: 1.0,
+        };
+        assert_eq!(numbered_image().crop(normalised), cropped);
+    }
+
+    #[test]
+    fn fill_region_should_only_replace_the_pixels_in_the_window() {
+        let window = CropWindow::Pixels {
+            x: 3,
+            y: 0,
+            width: 1,
+            height: 3,
+        };
+        let mut image = Image::new(4, 3);
+
+        image.fill_region(&numbered_image(), window);
+
+        for (i, colour) in image.pixels().iter().enumerate() {
+            let expected = if i % 4 == 3 { i as f64 } else { 0.0 };
+            assert_eq!(colour.0, expected, "pixel {i}");
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use crate::{
+    bvh::bvh::Bvh,
+    colour::RGB,
+    hittable::{hit_record::HitRecord, hittable::Hittable, hittable_list::HittableList},
+    ray::Ray,
+    utilities::random,
+    vec3d::{Point3d, Vec3d},
+};
+
+use super::{
+    photon_map::CausticMap,
+    settings::{Background, LightSampling},
+};
+
+/// What rays are traced against.
+pub(super) struct Scene<'a> {
+    pub bvh: &'a Bvh,
+    /// Lights sampled directly at each bounce, `None` if there are no
+    /// lights to sample.
+    pub lights: Option<&'a HittableList>,
+    pub background: &'a Background,
+    pub light_sampling: LightSampling,
+    pub max_depth: usize,
+    pub russian_roulette_depth: Option<usize>,
+    /// Photons gathered for the caustics at each bounce that is not
+    /// specular, `None` to leave caustics to path tracing.
+    pub caustics: Option<&'a CausticMap>,
+}
+
+impl Scene<'_> {
+    /// Returns whether a ray's hit at distance `t` is on one of the sampled
+    /// lights. The lights are also in the BVH, so a hit on a light is found at
+    /// the same distance in both.
+    fn is_sampled_light(&self, ray: &Ray, t: f64) -> bool {
+        self.lights
+            .and_then(|lights| lights.hit(ray, 0.001, f64::MAX))
+            .is_some_and(|light_hit| (light_hit.t - t).abs() <= 1e-9 * t.max(1.0))
+    }
+
+    /// Returns the chance of a path carrying on after `depth` bounces, where
+    /// `throughput` is the share of light the path carries back to the
+    /// camera. Dim paths are more likely to end, which saves following them
+    /// when they add little to the image.
+    pub fn survival_chance(&self, depth: usize, throughput: RGB) -> f64 {
+        match self.russian_roulette_depth {
+            Some(russian_roulette_depth) if depth >= russian_roulette_depth => {
+                // Capped so that paths bouncing between bright surfaces end
+                throughput.0.max(throughput.1).max(throughput.2).min(0.95)
+            }
+            _ => 1.0,
+        }
+    }
+
+    /// Returns the weight of light found by one strategy, where `pdf` is the
+    /// density of that strategy picking the direction and `other_pdf` the
+    /// density of the other strategy picking it.
+    fn weight(&self, pdf: f64, other_pdf: f64, is_light_sample: bool) -> f64 {
+        match self.light_sampling {
+            LightSampling::Material => f64::from(!is_light_sample),
+            LightSampling::Lights => f64::from(is_light_sample),
+            LightSampling::Balance => pdf / (pdf + other_pdf),
+            LightSampling::Power => (pdf * pdf) / (pdf * pdf + other_pdf * other_pdf),
+        }
+    }
+}
+
+/// A bounce where the lights were sampled, kept so that the light of
+/// a sampled light hit by the scattered ray can be weighted against the
+/// shadow ray sent from the same bounce.
+#[derive(Debug, Clone, Copy)]
+struct Bounce {
+    point: Point3d,
+    /// Density of the material scattering the ray that left the bounce.
+    scattering_pdf: f64,
+}
+
+/// Returns the colour seen along a camera ray, following it as it bounces
+/// around the scene. Paths end at `max_depth` bounces or earlier by Russian
+/// roulette.
+///
+/// At each bounce that is not specular the light arriving straight from the
+/// sampled lights is also found by tracing a shadow ray to a random point on
+/// them (next-event estimation). Light from a sampled light is then found
+/// twice, by the shadow ray and by the scattered ray, so each is weighted by
+/// how likely its strategy was to find that light (multiple importance
+/// sampling).
+///
+/// With a caustic photon map the caustics are also gathered at each bounce
+/// that is not specular. Light from a sampled light the path then finds after
+/// only specular bounces is a caustic already counted, so it is left out.
+/// Other lights send no photons, so their caustics are only found this way.
+pub(super) fn ray_colour(mut ray: Ray, scene: &Scene) -> RGB {
+    let mut colour = RGB(0.0, 0.0, 0.0);
+    // Share of the light found along the ray that reaches the camera
+    let mut throughput = RGB(1.0, 1.0, 1.0);
+    // The bounce the ray left from, `None` for the camera ray and rays
+    // leaving a bounce where the lights were not sampled
+    let mut previous: Option<Bounce> = None;
+    // Whether the caustics were gathered at the last bounce that was not
+    // specular, and whether the path has bounced off a specular surface
+    // since
+    let mut caustics_gathered = false;
+    let mut in_caustic = false;
+
+    for depth in 0..scene.max_depth {
+        let Some(hr) = scene.bvh.hit(&ray, 0.001, f64::MAX) else {
+            return colour + throughput * scene.background.colour(&ray);
+        };
+
+        if hr.material.is_emissive() {
+            let is_sampled_light = scene.is_sampled_light(&ray, hr.t);
+            if !(in_caustic && is_sampled_light) {
+                let weight = match (previous, scene.lights) {
+                    (Some(bounce), Some(lights)) if is_sampled_light => {
+                        let light_pdf = lights.pdf_value(bounce.point, ray.direction);
+                        scene.weight(bounce.scattering_pdf, light_pdf, false)
+                    }
+                    _ => 1.0,
+                };
+                colour += weight * (throughput * hr.material.emitted(hr.u, hr.v, hr.point));
+            }
+        }
+
+        let wo = -1.0 * ray.direction.unit_vector();
+        let Some(sample) = hr.material.sample(&hr, &wo) else {
+            return colour;
+        };
+
+        // Light sampling can not find the single direction of specular
+        // scattering. Light reached in one more bounce is only counted if
+        // there is a bounce left, the same as for the scattered ray
+        previous = match scene.lights {
+            Some(lights)
+                if depth + 1 < scene.max_depth
+                    && !sample.flags.is_specular()
+                    && sample.pdf > 0.0 =>
+            {
+                colour += throughput * direct_light(&ray, &hr, &wo, lights, scene);
+                Some(Bounce {
+                    point: hr.point,
+                    scattering_pdf: sample.pdf,
+                })
+            }
+            _ => None,
+        };
+
+        if sample.flags.is_specular() {
+            in_caustic = caustics_gathered;
+        } else {
+            caustics_gathered = false;
+            in_caustic = false;
+            if let Some(caustics) = scene.caustics
+                && !hr.material.is_volume()
+            {
+                colour += throughput * caustics.radiance(&hr, &wo);
+                caustics_gathered = true;
+            }
+        }
+
+        // Paths that survive carry their share of the light of the paths that
+        // ended, so the throughput includes the survival chance
+        let survival_chance = scene.survival_chance(depth + 1, throughput * sample.attenuation);
+        if survival_chance < 1.0 && random() >= survival_chance {
+            return colour;
+        }
+        throughput = throughput * sample.attenuation / survival_chance;
+
+        ray = Ray::new(hr.point, sample.direction, ray.time);
+    }
+
+    colour
+}
+
+/// Estimates the light arriving at a hit straight from the sampled lights and
+/// scattered by its material towards `wo`.
+fn direct_light(
+    ray_in: &Ray,
+    hr: &HitRecord,
+    wo: &Vec3d,
+    lights: &HittableList,
+    scene: &Scene,
+) -> RGB {
+    let direction = lights.random_direction(hr.point);
+    let light_pdf = lights.pdf_value(hr.point, direction);
+    if light_pdf <= 0.0 {
+        return RGB(0.0, 0.0, 0.0);
+    }
+
+    let wi = direction.unit_vector();
+    let scattering_pdf = hr.material.pdf(hr, wo, &wi);
+    if scattering_pdf <= 0.0 {
+        return RGB(0.0, 0.0, 0.0);
+    }
+
+    // The shadow ray only reaches the light if nothing else is hit first
+    let shadow_ray = Ray::new(hr.point, direction, ray_in.time);
+    match scene.bvh.hit(&shadow_ray, 0.001, f64::MAX) {
+        Some(light_hit)
+            if light_hit.material.is_emissive()
+                && scene.is_sampled_light(&shadow_ray, light_hit.t) =>
+        {
+            let weight = scene.weight(light_pdf, scattering_pdf, true);
+            let emitted = light_hit
+                .material
+                .emitted(light_hit.u, light_hit.v, light_hit.point);
+            (weight / light_pdf) * hr.material.eval(hr, wo, &wi) * emitted
+        }
+        _ => RGB(0.0, 0.0, 0.0),
+    }
+}
@@ -0,0 +1,24 @@
+mod aov;
+mod bdpt;
+mod checkpoint;
+mod denoise;
+mod filter;
+mod image;
+mod integrator;
+mod photon_map;
+mod renderer;
+mod settings;
+mod tiles;
+
+pub use aov::Aov;
+pub use checkpoint::Checkpoint;
+pub use denoise::Denoiser;
+pub use filter::{Filter, PixelFilter};
+pub use image::Image;
+pub use renderer::{
+    Pass, Progress, ProgressObserver, Renderer, RendererBuilder, Tile, TileObserver,
+};
+pub use settings::{
+    AdaptiveSampling, Background, Caustics, CropWindow, Integrator, LightSampling, RenderSettings,
+};
+pub use tiles::TileOrder;
@@ -0,0 +1,290 @@
+use std::f64::consts::PI;
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{
+    colour::RGB,
+    hittable::{hit_record::HitRecord, hittable::Hittable},
+    ray::Ray,
+    utilities::{mix_seed, random, random_unit_vec, with_seeded_random},
+    vec3d::{Point3d, Vec3d},
+};
+
+use super::{integrator::Scene, settings::Caustics};
+
+/// Photons are traced in batches of this many, each batch from its own
+/// seed.
+const PHOTONS_PER_BATCH: usize = 4096;
+
+/// Mixed into the seeds of photon batches, to keep them apart from the seeds
+/// of pixels.
+const PHOTON_SEEDS: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Light that reached a surface that is not specular after bouncing off
+/// specular surfaces, e.g. after passing through glass.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct Photon {
+    pub position: Point3d,
+    /// Unit direction back towards where the photon came from.
+    pub direction: Vec3d,
+    /// Unit normal of the surface, on the side the photon arrived at.
+    pub normal: Vec3d,
+    /// Share of the power of the lights the photon carries.
+    pub power: RGB,
+}
+
+/// Photons stored in a kd-tree, to find the photons near a point.
+///
+/// The tree is kept in the order of the photons: the photon in the middle of
+/// a range splits it, with the photons before it on one side of it along its
+/// axis and the photons after it on the other.
+pub(super) struct PhotonMap {
+    photons: Vec<Photon>,
+    /// The axis each photon splits its range along.
+    axes: Vec<usize>,
+}
+
+impl PhotonMap {
+    pub fn build(mut photons: Vec<Photon>) -> Self {
+        let mut axes = vec![0; photons.len()];
+        build_tree(&mut photons, &mut axes);
+        Self { photons, axes }
+    }
+
+    /// Calls `f` with every photon less than `radius` from `point`.
+    pub fn for_each_within(&self, point: Point3d, radius: f64, mut f: impl FnMut(&Photon)) {
+        self.search(0, self.photons.len(), point, radius * radius, &mut f);
+    }
+
+    fn search(
+        &self,
+        start: usize,
+        end: usize,
+        point: Point3d,
+        radius_squared: f64,
+        f: &mut impl FnMut(&Photon),
+    ) {
+        if start >= end {
+            return;
+        }
+        let middle = start + (end - start) / 2;
+        let photon = &self.photons[middle];
+        if (photon.position - point).len_squared() < radius_squared {
+            f(photon);
+        }
+
+        // Only look on the far side of the split if the sphere crosses it
+        let axis = self.axes[middle];
+        let offset = point.get_axis(axis) - photon.position.get_axis(axis);
+        let (near, far) = match offset < 0.0 {
+            true => ((start, middle), (middle + 1, end)),
+            false => ((middle + 1, end), (start, middle)),
+        };
+        self.search(near.0, near.1, point, radius_squared, f);
+        if offset * offset < radius_squared {
+            self.search(far.0, far.1, point, radius_squared, f);
+        }
+    }
+}
+
+/// Sorts `photons` into a kd-tree, splitting each range along the axis it is
+/// widest in.
+fn build_tree(photons: &mut [Photon], axes: &mut [usize]) {
+    if photons.len() <= 1 {
+        return;
+    }
+    let (min, max) = photons.iter().fold(
+        (
+            Point3d::new(f64::MAX, f64::MAX, f64::MAX),
+            Point3d::new(f64::MIN, f64::MIN, f64::MIN),
+        ),
+        |(min, max), photon| {
+            let p = photon.position;
+            (
+                Point3d::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)),
+                Point3d::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)),
+            )
+        },
+    );
+    let extent = max - min;
+    let axis = (0..3)
+        .max_by(|&a, &b| extent.get_axis(a).total_cmp(&extent.get_axis(b)))
+        .unwrap();
+
+    let middle = photons.len() / 2;
+    photons.select_nth_unstable_by(middle, |a, b| {
+        a.position
+            .get_axis(axis)
+            .total_cmp(&b.position.get_axis(axis))
+    });
+    axes[middle] = axis;
+
+    let (left, rest) = photons.split_at_mut(middle);
+    let (left_axes, rest_axes) = axes.split_at_mut(middle);
+    build_tree(left, left_axes);
+    build_tree(&mut rest[1..], &mut rest_axes[1..]);
+}
+
+/// A photon map of the caustics of a scene and the radius to gather photons
+/// from.
+pub(super) struct CausticMap {
+    map: PhotonMap,
+    radius: f64,
+}
+
+impl CausticMap {
+    /// Traces photons from the sampled lights for one pass of a render. With
+    /// a progressive radius each pass gathers from a smaller radius than the
+    /// pass before it.
+    pub fn trace(
+        scene: &Scene,
+        lights: &dyn Hittable,
+        caustics: &Caustics,
+        time: (f64, f64),
+        seed: Option<u64>,
+        pass_index: usize,
+    ) -> Self {
+        let num_batches = caustics.num_photons.div_ceil(PHOTONS_PER_BATCH);
+        let photons = (0..num_batches)
+            .into_par_iter()
+            .flat_map_iter(|batch| {
+                let trace = || {
+                    let count =
+                        PHOTONS_PER_BATCH.min(caustics.num_photons - batch * PHOTONS_PER_BATCH);
+                    let mut photons = Vec::new();
+                    for _ in 0..count {
+                        trace_photon(scene, lights, caustics.num_photons, time, &mut photons);
+                    }
+                    photons
+                };
+                match seed {
+                    Some(seed) => {
+                        let batch_index = (pass_index * num_batches + batch) as u64;
+                        with_seeded_random(mix_seed(seed ^ PHOTON_SEEDS, batch_index), trace)
+                    }
+                    None => trace(),
+                }
+            })
+            .collect();
+
+        Self {
+            map: PhotonMap::build(photons),
+            radius: caustics.radius(pass_index),
+        }
+    }
+
+    /// Returns the light of the caustics scattered towards `wo` at a hit, by
+    /// the density of the photons around it.
+    pub fn radiance(&self, hr: &HitRecord, wo: &Vec3d) -> RGB {
+        let mut sum = RGB(0.0, 0.0, 0.0);
+        self.map.for_each_within(hr.point, self.radius, |photon| {
+            // Photons on surfaces round a corner, or the back of a thin wall,
+            // do not light this surface
+            let cos = hr.normal.dot(&photon.direction);
+            if photon.normal.dot(&hr.normal) < 0.9 || cos <= 0.0 {
+                return;
+            }
+            // `eval` includes the cosine, which the photon's power already has
+            sum += (1.0 / cos) * (hr.material.eval(hr, wo, &photon.direction) * photon.power);
+        });
+        sum / (PI * self.radius * self.radius)
+    }
+}
+
+/// Traces a photon from a random point on the lights, adding it to
+/// `photons` if it reaches a surface that is not specular after one or more
+/// specular bounces. `num_photons` is the number of photons traced in all.
+fn trace_photon(
+    scene: &Scene,
+    lights: &dyn Hittable,
+    num_photons: usize,
+    time: (f64, f64),
+    photons: &mut Vec<Photon>,
+) {
+    let Some((light, pdf_area)) = lights.sample_surface() else {
+        return;
+    };
+    // Lights emit from both sides, so pick a side then a direction around its
+    // normal with a cosine distribution
+    let side = if random() < 0.5 { 1.0 } else { -1.0 };
+    let normal = side * light.normal;
+    let mut direction = normal + random_unit_vec();
+    if direction.near_zero() {
+        direction = normal;
+    }
+    let emitted = light.material.emitted(light.u, light.v, light.point);
+    // The cosine over the density of the direction, cos / 2π, leaves 2π
+    let mut power = (2.0 * PI / (pdf_area * num_photons as f64)) * emitted;
+    let time = time.0 + random() * (time.1 - time.0);
+    let mut ray = Ray::new(light.point, direction, time);
+
+    for depth in 0..scene.max_depth {
+        let Some(hr) = scene.bvh.hit(&ray, 0.001, f64::MAX) else {
+            return;
+        };
+        if hr.material.is_volume() {
+            return;
+        }
+        let wo = -1.0 * ray.direction.unit_vector();
+        let Some(sample) = hr.material.sample(&hr, &wo) else {
+            return;
+        };
+        if !sample.flags.is_specular() {
+            // Light reaching the surface straight from the light is found
+            // by sampling the lights
+            if depth > 0 {
+                photons.push(Photon {
+                    position: hr.point,
+                    direction: wo,
+                    normal: hr.normal,
+                    power,
+                });
+            }
+            return;
+        }
+        power = power * sample.attenuation;
+        ray = Ray::new(hr.point, sample.direction, ray.time);
+    }
+}
+
+#[cfg(test)]
+mod photon_map_tests {
+    use crate::utilities::{random_rng, seed_random};
+
+    use super::*;
+
+    #[test]
+    fn for_each_within_should_find_the_photons_in_the_radius() {
+        seed_random(11);
+        let photons: Vec<Photon> = (0..2000)
+            .map(|_| Photon {
+                position: Point3d::new(
+                    random_rng(-1.0, 1.0),
+                    random_rng(-1.0, 1.0),
+                    random_rng(0.0, 0.2),
+                ),
+                direction: Vec3d::new(0.0, 0.0, 1.0),
+                normal: Vec3d::new(0.0, 0.0, 1.0),
+                power: RGB(1.0, 1.0, 1.0),
+            })
+            .collect();
+        let map = PhotonMap::build(photons.clone());
+
+        for _ in 0..50 {
+            let point = Point3d::new(random_rng(-1.0, 1.0), random_rng(-1.0, 1.0), 0.1);
+            let radius = random_rng(0.01, 0.3);
+            let mut found = Vec::new();
+            map.for_each_within(point, radius, |photon| found.push(photon.position));
+
+            let mut expected: Vec<Point3d> = photons
+                .iter()
+                .map(|photon| photon.position)
+                .filter(|position| (*position - point).len() < radius)
+                .collect();
+            let by_x = |a: &Point3d, b: &Point3d| a.x.total_cmp(&b.x);
+            found.sort_by(by_x);
+            expected.sort_by(by_x);
+            assert_eq!(found, expected);
+        }
+    }
+}
@@ -0,0 +1,1682 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use rayon::iter::{IntoParallelIterator, ParallelBridge, ParallelIterator};
+
+use crate::{
+    Error, Result,
+    bvh::bvh::{Bvh, BvhMetrics},
+    camera::Camera,
+    colour::RGB,
+    hittable::{hittable::Hittable, hittable_list::HittableList},
+    ray::Ray,
+    resolution::Resolution,
+    scene::CameraDescription,
+    utilities::{mix_seed, random, with_seeded_random},
+};
+
+use super::{
+    aov::{
+        AOV_SEEDS, Aov, AovSamples, AovScene, AovSplats, FilteredAovs, IdentifiedObject, aov_images,
+    },
+    bdpt::{Film, bidirectional_colour},
+    checkpoint::{Checkpoint, PixelSamples},
+    filter::{PixelFilter, TileSplats},
+    image::Image,
+    integrator::{Scene, ray_colour},
+    photon_map::CausticMap,
+    settings::{
+        AdaptiveSampling, Background, Caustics, CropWindow, Integrator, LightSampling,
+        RenderSettings,
+    },
+    tiles::{TileOrder, TileRect, tiles},
+};
+
+/// How far through a render, or a pass of a progressive render, is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    pub camera_index: usize,
+    pub completed_tiles: usize,
+    pub total_tiles: usize,
+}
+
+/// A rendered tile of an image, see `RendererBuilder::tile_observer`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tile {
+    pub camera_index: usize,
+    /// Column of the tile's top left pixel.
+    pub x: usize,
+    /// Row of the tile's top left pixel, counted from the top.
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    /// The tile's pixels so far, stored a row at a time from the top left.
+    /// With a pixel filter wider than a pixel, pixels at the edges of the
+    /// tile do not yet have the samples of neighbouring tiles rendered at
+    /// the same time.
+    pub pixels: Vec<RGB>,
+}
+
+/// A finished pass of a progressive render, see
+/// `Renderer::render_progressive`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pass {
+    pub camera_index: usize,
+    /// Counted from 1.
+    pub pass_number: usize,
+    pub num_passes: usize,
+    /// The most samples per pixel taken so far. Pixels have fewer with
+    /// adaptive sampling or when the render is stopped.
+    pub max_samples: usize,
+    /// Whether the render was stopped during this pass, it is the last pass.
+    pub stopped: bool,
+}
+
+/// Called each time a tile of the image has been rendered. Tiles are rendered
+/// in parallel so may be reported from any thread.
+pub type ProgressObserver = Box<dyn Fn(Progress) + Send + Sync>;
+
+/// Called with each tile of the image once it has been rendered, from any
+/// thread.
+pub type TileObserver = Box<dyn Fn(&Tile) + Send + Sync>;
+
+/// Builds a `Renderer` from the objects and cameras in a scene and the
+/// settings to render them with.
+///
+/// ```no_run
+/// # use rs_ray_tracer::{render::Renderer, scenes};
+/// let scene = scenes::get("cornell_box").unwrap();
+/// let renderer = scene.renderer()?.samples(100).seed(42).build()?;
+/// let image = renderer.render(0);
+/// image.save_png("cornell_box.png")?;
+/// # Ok::<(), rs_ray_tracer::Error>(())
+/// ```
+pub struct RendererBuilder {
+    settings: RenderSettings,
+    objects: Vec<Box<dyn Hittable>>,
+    lights: Vec<Box<dyn Hittable>>,
+    cameras: Vec<CameraDescription>,
+    progress: Option<ProgressObserver>,
+    tile_observer: Option<TileObserver>,
+    scene_fingerprint: u64,
+}
+
+impl RendererBuilder {
+    /// Replaces all of the settings.
+    pub fn settings(mut self, settings: RenderSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn object(mut self, object: impl Hittable + 'static) -> Self {
+        self.objects.push(Box::new(object));
+        self
+    }
+
+    pub fn objects(mut self, objects: impl IntoIterator<Item = Box<dyn Hittable>>) -> Self {
+        self.objects.extend(objects);
+        self
+    }
+
+    /// Adds a light to sample directly, it must also be added as an object.
+    /// Sampling the lights cuts noise a lot in scenes lit by small lights.
+    /// Lights must implement `Hittable::pdf_value` and
+    /// `Hittable::random_direction`, and `Hittable::sample_surface` and
+    /// `Hittable::surface_pdf` for bidirectional path tracing, e.g. spheres
+    /// and rectangles.
+    pub fn light(mut self, light: impl Hittable + 'static) -> Self {
+        self.lights.push(Box::new(light));
+        self
+    }
+
+    pub fn lights(mut self, lights: impl IntoIterator<Item = Box<dyn Hittable>>) -> Self {
+        self.lights.extend(lights);
+        self
+    }
+
+    pub fn camera(mut self, camera: CameraDescription) -> Self {
+        self.cameras.push(camera);
+        self
+    }
+
+    pub fn cameras(mut self, cameras: impl IntoIterator<Item = CameraDescription>) -> Self {
+        self.cameras.extend(cameras);
+        self
+    }
+
+    /// Uses the size and sampling settings of a resolution preset.
+    pub fn resolution(mut self, resolution: Resolution) -> Self {
+        self.settings = self
+            .settings
+            .size(resolution.image_width, resolution.image_height)
+            .samples(resolution.num_samples)
+            .max_depth(resolution.max_depth);
+        self
+    }
+
+    pub fn size(mut self, image_width: usize, image_height: usize) -> Self {
+        self.settings = self.settings.size(image_width, image_height);
+        self
+    }
+
+    pub fn samples(mut self, num_samples: usize) -> Self {
+        self.settings = self.settings.samples(num_samples);
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.settings = self.settings.max_depth(max_depth);
+        self
+    }
+
+    pub fn background(mut self, background: Background) -> Self {
+        self.settings = self.settings.background(background);
+        self
+    }
+
+    pub fn shutter(mut self, time0: f64, time1: f64) -> Self {
+        self.settings = self.settings.shutter(time0, time1);
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.settings = self.settings.seed(seed);
+        self
+    }
+
+    pub fn light_sampling(mut self, light_sampling: LightSampling) -> Self {
+        self.settings = self.settings.light_sampling(light_sampling);
+        self
+    }
+
+    pub fn russian_roulette_depth(mut self, russian_roulette_depth: Option<usize>) -> Self {
+        self.settings = self.settings.russian_roulette_depth(russian_roulette_depth);
+        self
+    }
+
+    pub fn integrator(mut self, integrator: Integrator) -> Self {
+        self.settings = self.settings.integrator(integrator);
+        self
+    }
+
+    pub fn caustics(mut self, caustics: Caustics) -> Self {
+        self.settings = self.settings.caustics(caustics);
+        self
+    }
+
+    pub fn adaptive_sampling(mut self, adaptive_sampling: AdaptiveSampling) -> Self {
+        self.settings = self.settings.adaptive_sampling(adaptive_sampling);
+        self
+    }
+
+    /// Spreads samples over the pixels around them with a filter, see
+    /// `PixelFilter`.
+    pub fn pixel_filter(mut self, pixel_filter: PixelFilter) -> Self {
+        self.settings = self.settings.pixel_filter(pixel_filter);
+        self
+    }
+
+    pub fn tiles(mut self, tile_size: usize, tile_order: TileOrder) -> Self {
+        self.settings = self.settings.tiles(tile_size, tile_order);
+        self
+    }
+
+    pub fn crop(mut self, crop: CropWindow) -> Self {
+        self.settings = self.settings.crop(crop);
+        self
+    }
+
+    pub fn progress(mut self, observer: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Box::new(observer));
+        self
+    }
+
+    /// Calls `observer` with each tile as soon as it is rendered, e.g. to
+    /// show the image as it is rendered.
+    pub fn tile_observer(mut self, observer: impl Fn(&Tile) + Send + Sync + 'static) -> Self {
+        self.tile_observer = Some(Box::new(observer));
+        self
+    }
+
+    /// Sets a hash of the scene, used to check checkpoints are resumed with
+    /// the scene they were rendered from. `SceneDescription::renderer` sets
+    /// it.
+    pub fn scene_fingerprint(mut self, scene_fingerprint: u64) -> Self {
+        self.scene_fingerprint = scene_fingerprint;
+        self
+    }
+
+    /// Checks the settings, then builds the BVH for the shutter interval and
+    /// the cameras for the image size.
+    pub fn build(self) -> Result<Renderer> {
+        let settings = self.settings;
+        if settings.image_width < 2 || settings.image_height < 2 {
+            return Err(Error::InvalidSettings(format!(
+                "image must be at least 2x2 pixels, found {}x{}",
+                settings.image_width, settings.image_height
+            )));
+        }
+        if settings.num_samples == 0 {
+            return Err(Error::InvalidSettings(
+                "must take at least one sample per pixel".to_string(),
+            ));
+        }
+        if let Some(adaptive_sampling) = settings.adaptive_sampling {
+            if adaptive_sampling.min_samples < 2 {
+                return Err(Error::InvalidSettings(
+                    "adaptive sampling must take at least two samples per pixel to measure noise"
+                        .to_string(),
+                ));
+            }
+            // A threshold of 0 is never reached and an infinite one is
+            // reached by every pixel
+            let noise_threshold = adaptive_sampling.noise_threshold;
+            if !(noise_threshold.is_finite() && noise_threshold > 0.0) {
+                return Err(Error::InvalidSettings(format!(
+                    "noise threshold must be positive and finite, found {noise_threshold}"
+                )));
+            }
+        }
+        if let Some(caustics) = settings.caustics {
+            if caustics.num_photons == 0 {
+                return Err(Error::InvalidSettings(
+                    "caustics must trace at least one photon".to_string(),
+                ));
+            }
+            if !(caustics.radius.is_finite() && caustics.radius > 0.0) {
+                return Err(Error::InvalidSettings(format!(
+                    "caustic radius must be positive, found {}",
+                    caustics.radius
+                )));
+            }
+            if let Some(alpha) = caustics.radius_reduction
+                && !(alpha > 0.0 && alpha < 1.0)
+            {
+                return Err(Error::InvalidSettings(format!(
+                    "caustic radius reduction must be between 0 and 1, found {alpha}"
+                )));
+            }
+        }
+        let radius = settings.pixel_filter.radius;
+        if !(radius.is_finite() && radius >= 0.5) {
+            return Err(Error::InvalidSettings(format!(
+                "pixel filter radius must be at least half a pixel, found {radius}"
+            )));
+        }
+        if settings.tile_size == 0 {
+            return Err(Error::InvalidSettings(
+                "tiles must be at least 1 pixel".to_string(),
+            ));
+        }
+        if let Some(crop) = settings.crop
+            && !crop.fits(settings.image_width, settings.image_height)
+        {
+            return Err(Error::InvalidSettings(format!(
+                "crop window {crop:?} is empty or outside the {}x{} image",
+                settings.image_width, settings.image_height
+            )));
+        }
+        if settings.time0 > settings.time1 {
+            return Err(Error::InvalidSettings(
+                "shutter opens after it closes".to_string(),
+            ));
+        }
+        if self.cameras.is_empty() {
+            return Err(Error::InvalidSettings("no cameras to render".to_string()));
+        }
+
+        // Objects set their ID on their hits, for the object ID pass
+        let objects = self
+            .objects
+            .into_iter()
+            .enumerate()
+            .map(|(index, object)| {
+                Box::new(IdentifiedObject {
+                    id: index + 1,
+                    object,
+                }) as Box<dyn Hittable>
+            })
+            .collect();
+        let (bvh, bvh_metrics) = Bvh::build(settings.time0, settings.time1, objects)?;
+        let lights = HittableList::build(
+            settings.time0,
+            settings.time1,
+            self.lights.into_boxed_slice(),
+        );
+        let cameras = self
+            .cameras
+            .iter()
+            .map(|camera| camera.build(settings.aspect_ratio(), settings.time0, settings.time1))
+            .collect();
+
+        Ok(Renderer {
+            settings,
+            bvh,
+            bvh_metrics,
+            lights,
+            cameras,
+            progress: self.progress,
+            tile_observer: self.tile_observer,
+            scene_fingerprint: self.scene_fingerprint,
+        })
+    }
+}
+
+/// Renders images of a scene, see `RendererBuilder`.
+pub struct Renderer {
+    settings: RenderSettings,
+    bvh: Bvh,
+    bvh_metrics: BvhMetrics,
+    lights: HittableList,
+    cameras: Vec<Camera>,
+    progress: Option<ProgressObserver>,
+    tile_observer: Option<TileObserver>,
+    scene_fingerprint: u64,
+}
+
+impl Renderer {
+    pub fn builder() -> RendererBuilder {
+        RendererBuilder {
+            settings: RenderSettings::default(),
+            objects: Vec::new(),
+            lights: Vec::new(),
+            cameras: Vec::new(),
+            progress: None,
+            tile_observer: None,
+            scene_fingerprint: 0,
+        }
+    }
+
+    pub fn settings(&self) -> &RenderSettings {
+        &self.settings
+    }
+
+    pub fn bvh_metrics(&self) -> BvhMetrics {
+        self.bvh_metrics
+    }
+
+    pub fn num_cameras(&self) -> usize {
+        self.cameras.len()
+    }
+
+    /// Renders the image seen by a camera, panics if the camera does not
+    /// exist.
+    pub fn render(&self, camera_index: usize) -> Image {
+        let mut checkpoint = self.checkpoint(camera_index);
+        self.render_pass(
+            &mut checkpoint,
+            self.settings.num_samples,
+            &AtomicBool::new(false),
+        );
+        checkpoint.image()
+    }
+
+    /// Returns a checkpoint without any samples, to start a progressive
+    /// render of a camera with. Panics if the camera does not exist.
+    pub fn checkpoint(&self, camera_index: usize) -> Checkpoint {
+        assert!(
+            camera_index < self.cameras.len(),
+            "camera {camera_index} does not exist"
+        );
+        Checkpoint::new(camera_index, &self.settings, self.scene_fingerprint)
+    }
+
+    /// Carries on a progressive render from a checkpoint, taking samples in
+    /// passes of `samples_per_pass` samples per pixel over the whole image
+    /// until pixels have the number of samples in the settings. `on_pass` is
+    /// called with the checkpoint after each pass, e.g. to save it or to show
+    /// the image so far.
+    ///
+    /// Start a render with an empty checkpoint from `checkpoint`, or resume
+    /// one loaded with `Checkpoint::load`. Resuming a finished render with
+    /// more samples adds to the samples it has. Returns an error if the
+    /// checkpoint is of a different scene or was rendered with different
+    /// settings.
+    ///
+    /// Setting `stop` ends the render early, even part way through a pass.
+    /// Pixels the pass did not reach keep the samples of the earlier passes,
+    /// so the image returned is still usable and the checkpoint can be
+    /// resumed later.
+    pub fn render_progressive(
+        &self,
+        checkpoint: &mut Checkpoint,
+        samples_per_pass: usize,
+        stop: &AtomicBool,
+        mut on_pass: impl FnMut(&Checkpoint, Pass),
+    ) -> Result<Image> {
+        checkpoint.check(&self.settings, self.scene_fingerprint)?;
+        let camera_index = checkpoint.camera_index;
+        if camera_index >= self.cameras.len() {
+            return Err(Error::InvalidCheckpoint(format!(
+                "checkpoint is of camera {camera_index} but there are {} cameras",
+                self.cameras.len()
+            )));
+        }
+        // Adaptive sampling may have changed since the checkpoint was saved
+        for pixel in &mut checkpoint.pixels {
+            pixel.converged = self.is_converged(pixel);
+        }
+
+        let samples_per_pass = samples_per_pass.max(1);
+        let first_samples = checkpoint.max_samples;
+        let num_passes = self
+            .settings
+            .num_samples
+            .saturating_sub(first_samples)
+            .div_ceil(samples_per_pass);
+        for pass_index in 0..num_passes {
+            let max_samples = (first_samples + (pass_index + 1) * samples_per_pass)
+                .min(self.settings.num_samples);
+            self.render_pass(checkpoint, max_samples, stop);
+            // A stopped pass is started again when the render is resumed,
+            // with new samples
+            checkpoint.passes += 1;
+            let stopped = stop.load(Ordering::Relaxed);
+            if !stopped {
+                checkpoint.max_samples = max_samples;
+            }
+
+            on_pass(
+                checkpoint,
+                Pass {
+                    camera_index,
+                    pass_number: pass_index + 1,
+                    num_passes,
+                    max_samples,
+                    stopped,
+                },
+            );
+            let converged = self
+                .settings
+                .render_area()
+                .indexes(self.settings.image_width)
+                .all(|index| checkpoint.pixels[index].converged);
+            if stopped || converged {
+                break;
+            }
+        }
+        Ok(checkpoint.image())
+    }
+
+    /// Takes samples for every pixel in the crop window until it has
+    /// `max_samples`, or until it is no longer noisy with adaptive sampling.
+    /// The image is rendered a tile at a time, and pixels are skipped once
+    /// `stop` is set.
+    ///
+    /// Tiles handed to the tile observer only have the light found from the
+    /// camera. Light that bidirectional path tracing finds from the lights
+    /// lands on any pixel, so it is only added to the checkpoint at the end
+    /// of the pass, as are the samples the pixel filter spreads over the
+    /// pixels of neighbouring tiles.
+    fn render_pass(&self, checkpoint: &mut Checkpoint, max_samples: usize, stop: &AtomicBool) {
+        let settings = &self.settings;
+        let width = settings.image_width;
+        let camera_index = checkpoint.camera_index;
+        let pass_index = checkpoint.passes;
+        let pixels = &mut checkpoint.pixels;
+        let filtered = &checkpoint.filtered;
+        let lights_sampled = match settings.integrator {
+            Integrator::PathTracing => settings.light_sampling != LightSampling::Material,
+            Integrator::Bidirectional => true,
+        };
+        let film = (settings.integrator == Integrator::Bidirectional)
+            .then(|| Film::new(&self.cameras[camera_index], settings));
+        let scene = Scene {
+            bvh: &self.bvh,
+            lights: (!self.lights.is_empty() && lights_sampled).then_some(&self.lights),
+            background: &settings.background,
+            light_sampling: settings.light_sampling,
+            max_depth: settings.max_depth,
+            russian_roulette_depth: settings.russian_roulette_depth,
+            caustics: None,
+        };
+        // Photons are traced for each pass, bidirectional path tracing finds
+        // caustics itself
+        let caustic_map = match (settings.caustics, settings.integrator) {
+            (Some(caustics), Integrator::PathTracing) if !self.lights.is_empty() => {
+                Some(CausticMap::trace(
+                    &scene,
+                    &self.lights,
+                    &caustics,
+                    (settings.time0, settings.time1),
+                    settings.seed,
+                    pass_index,
+                ))
+            }
+            _ => None,
+        };
+        let scene = Scene {
+            caustics: caustic_map.as_ref(),
+            ..scene
+        };
+        let area = settings.render_area();
+        let tiles = tiles(area, settings.tile_size, settings.tile_order);
+        let total_tiles = tiles.len();
+        let completed_tiles = AtomicUsize::new(0);
+
+        // Tiles are handed out in order as threads become free
+        let mut rendered: Vec<(TileRect, Vec<PixelSamples>, TileSplats)> = tiles
+            .into_iter()
+            .par_bridge()
+            .map(|tile| {
+                let mut tile_pixels: Vec<PixelSamples> =
+                    tile.indexes(width).map(|index| pixels[index]).collect();
+                let mut splats =
+                    TileSplats::new(settings.pixel_filter, settings.image_height, tile, area);
+                for (index, pixel) in tile.indexes(width).zip(&mut tile_pixels) {
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    self.sample_pixel(
+                        camera_index,
+                        &scene,
+                        film.as_ref(),
+                        index,
+                        pixel,
+                        &mut splats,
+                        pass_index,
+                        max_samples,
+                    );
+                }
+
+                if let Some(tile_observer) = &self.tile_observer {
+                    let tile_colours = tile
+                        .indexes(width)
+                        .zip(&tile_pixels)
+                        .map(|(index, pixel)| {
+                            let mut sums = filtered[index];
+                            sums.add(&splats.get(index, width));
+                            sums.colour().unwrap_or_else(|| pixel.colour())
+                        })
+                        .collect();
+                    tile_observer(&Tile {
+                        camera_index,
+                        x: tile.x,
+                        y: tile.y,
+                        width: tile.width,
+                        height: tile.height,
+                        pixels: tile_colours,
+                    });
+                }
+                let completed = completed_tiles.fetch_add(1, Ordering::Relaxed) + 1;
+                if let Some(progress) = &self.progress {
+                    progress(Progress {
+                        camera_index,
+                        completed_tiles: completed,
+                        total_tiles,
+                    });
+                }
+
+                (tile, tile_pixels, splats)
+            })
+            .collect();
+
+        // Splats from neighbouring tiles overlap, so they are added in the
+        // same order whichever threads rendered the tiles, which keeps
+        // seeded renders repeatable
+        rendered.sort_by_key(|(tile, ..)| (tile.y, tile.x));
+        for (tile, tile_pixels, splats) in rendered {
+            for (index, pixel) in tile.indexes(width).zip(tile_pixels) {
+                checkpoint.pixels[index] = pixel;
+            }
+            splats.add_to(&mut checkpoint.filtered, width);
+        }
+        if let Some(film) = film {
+            for (sum, splat) in checkpoint.splats.iter_mut().zip(film.into_splats()) {
+                *sum += splat;
+            }
+        }
+    }
+
+    /// Takes samples for the pixel at `index` until it has `max_samples`, or
+    /// until it is no longer noisy with adaptive sampling. The samples are
+    /// also spread over `splats` by the pixel filter. `film` collects the
+    /// light of light paths with bidirectional path tracing.
+    #[allow(clippy::too_many_arguments)]
+    fn sample_pixel(
+        &self,
+        camera_index: usize,
+        scene: &Scene,
+        film: Option<&Film>,
+        index: usize,
+        pixel: &mut PixelSamples,
+        splats: &mut TileSplats,
+        pass_index: usize,
+        max_samples: usize,
+    ) {
+        let settings = &self.settings;
+        let camera = &self.cameras[camera_index];
+        let width = settings.image_width;
+        let height = settings.image_height;
+        let mut sample = || {
+            while !pixel.converged && pixel.num_samples < max_samples {
+                let (x, y) = self.sample_position(index);
+                let ray = self.camera_ray(camera, x, y);
+
+                let colour = match film {
+                    Some(film) => bidirectional_colour(ray, scene, film),
+                    None => ray_colour(ray, scene),
+                };
+                pixel.add(colour);
+                splats.add(x, y, colour);
+                pixel.converged = self.is_converged(pixel);
+            }
+        };
+        match settings.seed {
+            // Seed each pixel so the image does not depend on which thread
+            // renders which pixel, and each pass so passes take different
+            // samples
+            Some(seed) => {
+                let sample_index = pass_index * width * height + index;
+                with_seeded_random(mix_seed(seed, sample_index as u64), sample);
+            }
+            None => sample(),
+        }
+    }
+
+    /// Returns a random point of the pixel at `index`, in pixels from the
+    /// bottom left corner of the image.
+    fn sample_position(&self, index: usize) -> (f64, f64) {
+        let width = self.settings.image_width;
+        let height = self.settings.image_height;
+        // Image rows go top -> bottom, but y goes bottom -> top
+        let i = index % width;
+        let j = height - 1 - index / width;
+        ((i as f64) + random(), (j as f64) + random())
+    }
+
+    /// Returns a ray through a point of the image, in pixels from the bottom
+    /// left corner.
+    fn camera_ray(&self, camera: &Camera, x: f64, y: f64) -> Ray {
+        let width = self.settings.image_width;
+        let height = self.settings.image_height;
+        camera.get_ray(x / ((width - 1) as f64), y / ((height - 1) as f64))
+    }
+
+    /// Renders passes of what a camera sees at the first surface each sample
+    /// hits, see `Aov`, in the order asked for. Each pixel in the crop window
+    /// takes the number of samples in the settings, spread over the pixel
+    /// and the pixels around it the same way as the samples of the image.
+    /// Panics if the camera does not exist.
+    pub fn render_aovs(&self, camera_index: usize, aovs: &[Aov]) -> Vec<Image> {
+        let settings = &self.settings;
+        let width = settings.image_width;
+        let camera = &self.cameras[camera_index];
+        let scene = AovScene {
+            bvh: &self.bvh,
+            camera,
+            settings,
+        };
+        let area = settings.render_area();
+        let mut rendered: Vec<_> = tiles(area, settings.tile_size, settings.tile_order)
+            .into_par_iter()
+            .map(|tile| {
+                let mut splats =
+                    AovSplats::new(settings.pixel_filter, settings.image_height, tile, area);
+                let pixels: Vec<(usize, AovSamples)> = tile
+                    .indexes(width)
+                    .map(|index| {
+                        let mut sample = || {
+                            let mut pixel = AovSamples::default();
+                            for _ in 0..settings.num_samples {
+                                let (x, y) = self.sample_position(index);
+                                let sample = scene.sample(&self.camera_ray(camera, x, y));
+                                pixel.add(&sample);
+                                splats.add(x, y, &sample);
+                            }
+                            (index, pixel)
+                        };
+                        match settings.seed {
+                            Some(seed) => {
+                                with_seeded_random(mix_seed(seed ^ AOV_SEEDS, index as u64), sample)
+                            }
+                            None => sample(),
+                        }
+                    })
+                    .collect();
+                (tile, pixels, splats)
+            })
+            .collect();
+
+        // In the same order whichever threads rendered the tiles, like the
+        // splats of the image
+        rendered.sort_by_key(|(tile, ..)| (tile.y, tile.x));
+        let mut filtered = FilteredAovs::new(width * settings.image_height);
+        let mut pixels = Vec::with_capacity(area.width * area.height);
+        for (_, tile_pixels, splats) in rendered {
+            splats.add_to(&mut filtered, width);
+            pixels.extend(tile_pixels);
+        }
+        // Materials are numbered in the order they appear from the top left
+        pixels.sort_by_key(|(index, _)| *index);
+        aov_images(width, settings.image_height, &pixels, &filtered, aovs)
+    }
+
+    /// Returns whether adaptive sampling has taken enough samples of a pixel
+    /// for it to no longer be noisy.
+    fn is_converged(&self, pixel: &PixelSamples) -> bool {
+        self.settings
+            .adaptive_sampling
+            .is_some_and(|adaptive_sampling| {
+                pixel.num_samples >= adaptive_sampling.min_samples
+                    && pixel.noise.standard_error(pixel.num_samples)
+                        <= adaptive_sampling.noise_threshold
+            })
+    }
+
+    /// Renders the image seen by each camera.
+    pub fn render_all(&self) -> Vec<Image> {
+        (0..self.cameras.len()).map(|i| self.render(i)).collect()
+    }
+}
+
+#[cfg(test)]
+mod renderer_tests {
+    use std::sync::Arc;
+
+    use crate::{
+        materials::{Dielectric, DiffuseLight, Lambertian, Metal},
+        objects::{MovingSphere, RectangleXZ, Sphere},
+        render::Filter,
+        textures::SolidColour,
+        vec3d::{Point3d, Vec3d},
+    };
+
+    use super::*;
+
+    fn light_renderer() -> RendererBuilder {
+        Renderer::builder()
+            .object(Sphere::new(
+                Point3d::new(0.0, 0.0, 0.0),
+                2.0,
+                DiffuseLight::build_from_colour(RGB(1.0, 0.5, 0.25)),
+            ))
+            .camera(CameraDescription::new(
+                Point3d::new(0.0, 0.0, -10.0),
+                Point3d::new(0.0, 0.0, 0.0),
+                20.0,
+            ))
+            .size(8, 6)
+            .samples(4)
+    }
+
+    #[test]
+    fn resolution_should_only_change_the_size_and_sampling() {
+        let builder = light_renderer()
+            .background(Background::Sky)
+            .seed(3)
+            .pixel_filter(PixelFilter::new(Filter::Tent))
+            .tiles(4, TileOrder::Hilbert);
+        let expected = RenderSettings {
+            image_width: 32,
+            image_height: 24,
+            num_samples: 10,
+            max_depth: 5,
+            ..builder.settings
+        };
+
+        let builder = builder.resolution(Resolution {
+            image_width: 32,
+            image_height: 24,
+            num_samples: 10,
+            max_depth: 5,
+        });
+
+        assert_eq!(builder.settings, expected);
+    }
+
+    #[test]
+    fn render_should_return_image_of_the_settings_size() {
+        let image = light_renderer().build().unwrap().render(0);
+
+        assert_eq!((image.width(), image.height()), (8, 6));
+        // The light fills the centre of the image and the background is black
+        assert_eq!(image.get(4, 3), RGB(1.0, 0.5, 0.25));
+        assert_eq!(image.get(0, 0), RGB(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn render_should_be_repeatable_with_a_seed() {
+        let renderer = light_renderer()
+            .size(16, 12)
+            .background(Background::Sky)
+            .seed(7)
+            .build()
+            .unwrap();
+
+        assert_eq!(renderer.render(0), renderer.render(0));
+    }
+
+    #[test]
+    fn render_should_report_progress_for_every_tile() {
+        let tiles = std::sync::Arc::new(AtomicUsize::new(0));
+        let observed_tiles = tiles.clone();
+        let renderer = light_renderer()
+            .tiles(4, TileOrder::Scanline)
+            .progress(move |progress| {
+                assert_eq!(progress.total_tiles, 4);
+                observed_tiles.fetch_add(1, Ordering::Relaxed);
+            })
+            .build()
+            .unwrap();
+
+        renderer.render(0);
+
+        assert_eq!(tiles.load(Ordering::Relaxed), 4);
+    }
+
+    #[test]
+    fn tiles_should_hold_the_rendered_pixels() {
+        let tiles = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let observed_tiles = tiles.clone();
+        let renderer = light_renderer()
+            .background(Background::Sky)
+            .seed(5)
+            .tiles(3, TileOrder::Hilbert)
+            .tile_observer(move |tile| observed_tiles.lock().unwrap().push(tile.clone()))
+            .build()
+            .unwrap();
+
+        let image = renderer.render(0);
+
+        let tiles = tiles.lock().unwrap();
+        assert_eq!(tiles.len(), 6);
+        for tile in tiles.iter() {
+            for y in 0..tile.height {
+                for x in 0..tile.width {
+                    let colour = tile.pixels[y * tile.width + x];
+                    assert_eq!(colour, image.get(tile.x + x, tile.y + y));
+                }
+            }
+        }
+        let scanline_image = light_renderer()
+            .background(Background::Sky)
+            .seed(5)
+            .build()
+            .unwrap()
+            .render(0);
+        assert_eq!(image, scanline_image);
+    }
+
+    #[test]
+    fn render_should_only_sample_the_crop_window() {
+        let window = CropWindow::Pixels {
+            x: 2,
+            y: 1,
+            width: 5,
+            height: 3,
+        };
+        let renderer = |crop| {
+            let builder = light_renderer().background(Background::Sky).seed(9);
+            match crop {
+                Some(crop) => builder.crop(crop),
+                None => builder,
+            }
+            .build()
+        };
+
+        let image = renderer(Some(window)).unwrap().render(0);
+
+        let full_image = renderer(None).unwrap().render(0);
+        assert_eq!(image.crop(window), full_image.crop(window));
+        let mut merged = Image::new(8, 6);
+        merged.fill_region(&image, window);
+        assert_eq!(merged.pixels(), image.pixels());
+        let sampled = image
+            .sample_counts()
+            .unwrap()
+            .iter()
+            .filter(|&&count| count > 0)
+            .count();
+        assert_eq!(sampled, 15);
+
+        let outside = CropWindow::Pixels {
+            x: 6,
+            y: 0,
+            width: 4,
+            height: 2,
+        };
+        assert!(matches!(
+            renderer(Some(outside)),
+            Err(Error::InvalidSettings(_))
+        ));
+    }
+
+    #[test]
+    fn wide_pixel_filters_should_blend_pixels_across_edges() {
+        let render = |filter: Filter| {
+            light_renderer()
+                .size(32, 24)
+                .seed(3)
+                .pixel_filter(PixelFilter::new(filter))
+                .build()
+                .unwrap()
+                .render(0)
+        };
+        let sharp = render(Filter::Box);
+        let soft = render(Filter::Gaussian);
+
+        // Background pixels next to the light pick up some of its light
+        let mut edge_pixels = 0;
+        for y in 1..23 {
+            for x in 1..31 {
+                let lit_neighbour = [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+                    .iter()
+                    .any(|&(nx, ny)| sharp.get(nx, ny) != RGB(0.0, 0.0, 0.0));
+                if sharp.get(x, y) == RGB(0.0, 0.0, 0.0) && lit_neighbour {
+                    edge_pixels += 1;
+                    assert!(soft.get(x, y).0 > 0.0, "({x}, {y})");
+                }
+            }
+        }
+        assert!(edge_pixels > 0);
+        // Inside the light every sample is the same colour
+        let RGB(r, g, b) = soft.get(16, 12) - RGB(1.0, 0.5, 0.25);
+        assert!(r.abs().max(g.abs()).max(b.abs()) < 1e-9);
+    }
+
+    #[test]
+    fn aovs_should_be_filtered_like_the_image_apart_from_ids() {
+        let render = |filter: Filter| {
+            light_renderer()
+                .size(32, 24)
+                .seed(3)
+                .pixel_filter(PixelFilter::new(filter))
+                .build()
+                .unwrap()
+                .render_aovs(0, &[Aov::Albedo, Aov::ObjectId])
+        };
+        let sharp = render(Filter::Box);
+        let soft = render(Filter::Gaussian);
+
+        let mut edge_pixels = 0;
+        for y in 1..23 {
+            for x in 1..31 {
+                let on_light = |x, y| sharp[0].get(x, y) != RGB(0.0, 0.0, 0.0);
+                let lit_neighbour = [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+                    .iter()
+                    .any(|&(nx, ny)| on_light(nx, ny));
+                if !on_light(x, y) && lit_neighbour {
+                    edge_pixels += 1;
+                    assert!(soft[0].get(x, y).0 > 0.0, "({x}, {y})");
+                    assert_eq!(soft[1].get(x, y), RGB(0.0, 0.0, 0.0), "({x}, {y})");
+                }
+            }
+        }
+        assert!(edge_pixels > 0);
+    }
+
+    #[test]
+    fn pixel_filters_should_keep_seeded_renders_repeatable() {
+        let render = |tile_order| {
+            light_renderer()
+                .size(24, 16)
+                .background(Background::Sky)
+                .seed(5)
+                .pixel_filter(PixelFilter::new(Filter::Lanczos))
+                .tiles(4, tile_order)
+                .build()
+                .unwrap()
+                .render(0)
+        };
+
+        // Tiles overlap by the filter's radius, and are finished in a
+        // different order
+        assert_eq!(render(TileOrder::Scanline), render(TileOrder::Spiral));
+    }
+
+    #[test]
+    fn build_should_reject_pixel_filters_narrower_than_half_a_pixel() {
+        let result = light_renderer()
+            .pixel_filter(PixelFilter::new(Filter::Tent).radius(0.25))
+            .build();
+
+        assert!(matches!(result, Err(Error::InvalidSettings(_))));
+    }
+
+    #[test]
+    fn build_should_reject_renderer_without_cameras() {
+        let result = Renderer::builder()
+            .object(Sphere::new(
+                Point3d::new(0.0, 0.0, 0.0),
+                1.0,
+                DiffuseLight::build_from_colour(RGB(1.0, 1.0, 1.0)),
+            ))
+            .build();
+
+        assert!(matches!(result, Err(Error::InvalidSettings(_))));
+    }
+
+    /// A floor lit by a light, rendered twice with different seeds. Returns
+    /// the mean brightness and the mean squared difference between the two
+    /// renders.
+    fn render_lit_floor(
+        light: impl Hittable + Clone + 'static,
+        light_sampling: LightSampling,
+    ) -> (f64, f64) {
+        render_lit_floor_with(light, light_sampling, Integrator::PathTracing)
+    }
+
+    fn render_lit_floor_with(
+        light: impl Hittable + Clone + 'static,
+        light_sampling: LightSampling,
+        integrator: Integrator,
+    ) -> (f64, f64) {
+        // Low enough to see the floor under the large light
+        let render = |seed| {
+            Renderer::builder()
+                .object(Sphere::new(
+                    Point3d::new(0.0, -1000.0, 0.0),
+                    1000.0,
+                    Lambertian::build_from_colour(RGB(0.5, 0.5, 0.5)),
+                ))
+                .object(light.clone())
+                .light(light.clone())
+                .camera(CameraDescription::new(
+                    Point3d::new(0.0, 0.5, -6.0),
+                    Point3d::new(0.0, 0.0, 0.0),
+                    40.0,
+                ))
+                .size(12, 8)
+                .samples(256)
+                .max_depth(4)
+                .seed(seed)
+                .light_sampling(light_sampling)
+                .integrator(integrator)
+                .russian_roulette_depth(None)
+                .build()
+                .unwrap()
+                .render(0)
+        };
+        let (a, b) = (render(1), render(2));
+
+        let brightness = |colour: RGB| (colour.0 + colour.1 + colour.2) / 3.0;
+        let count = a.pixels().len() as f64;
+        let mean = a
+            .pixels()
+            .iter()
+            .chain(b.pixels())
+            .map(|&colour| brightness(colour))
+            .sum::<f64>()
+            / (2.0 * count);
+        let difference = a
+            .pixels()
+            .iter()
+            .zip(b.pixels())
+            .map(|(&a, &b)| (brightness(a) - brightness(b)).powi(2))
+            .sum::<f64>()
+            / count;
+        (mean, difference)
+    }
+
+    fn small_light() -> Sphere<DiffuseLight<SolidColour>> {
+        Sphere::new(
+            Point3d::new(0.0, 4.0, 0.0),
+            0.5,
+            DiffuseLight::build_from_colour(RGB(50.0, 50.0, 50.0)),
+        )
+    }
+
+    /// A light almost touching the floor, so shadow rays to its edges are
+    /// much less likely than rays scattered by the floor.
+    fn large_light() -> RectangleXZ<DiffuseLight<SolidColour>> {
+        RectangleXZ::new(
+            -20.0,
+            20.0,
+            -20.0,
+            20.0,
+            1.0,
+            DiffuseLight::build_from_colour(RGB(1.0, 1.0, 1.0)),
+        )
+    }
+
+    #[test]
+    fn light_sampling_should_cut_noise_without_changing_brightness() {
+        let (mean, difference) = render_lit_floor(small_light(), LightSampling::Material);
+
+        for light_sampling in [
+            LightSampling::Lights,
+            LightSampling::Balance,
+            LightSampling::Power,
+        ] {
+            let (sampled_mean, sampled_difference) =
+                render_lit_floor(small_light(), light_sampling);
+            assert!(
+                (sampled_mean - mean).abs() < 0.1 * mean,
+                "{light_sampling:?} mean: {sampled_mean}, without light sampling: {mean}"
+            );
+            assert!(
+                sampled_difference < 0.1 * difference,
+                "{light_sampling:?} difference: {sampled_difference}, without light sampling: {difference}"
+            );
+        }
+    }
+
+    #[test]
+    fn multiple_importance_sampling_should_beat_either_strategy_for_large_lights() {
+        let (mean, material_difference) = render_lit_floor(large_light(), LightSampling::Material);
+        let (_, lights_difference) = render_lit_floor(large_light(), LightSampling::Lights);
+        let (mis_mean, mis_difference) = render_lit_floor(large_light(), LightSampling::Power);
+
+        assert!(
+            (mis_mean - mean).abs() < 0.05 * mean,
+            "mean: {mis_mean}, without light sampling: {mean}"
+        );
+        assert!(
+            mis_difference < material_difference && mis_difference < lights_difference,
+            "difference: {mis_difference}, without light sampling: {material_difference}, with light sampling only: {lights_difference}"
+        );
+    }
+
+    #[test]
+    fn bidirectional_path_tracing_should_match_path_tracing() {
+        let lights: [Arc<dyn Hittable>; 2] = [Arc::new(small_light()), Arc::new(large_light())];
+        for light in lights {
+            let (mean, _) = render_lit_floor(light.clone(), LightSampling::Power);
+            let (bidirectional_mean, _) =
+                render_lit_floor_with(light, LightSampling::Power, Integrator::Bidirectional);
+
+            assert!(
+                (bidirectional_mean - mean).abs() < 0.05 * mean,
+                "mean: {bidirectional_mean}, with path tracing: {mean}"
+            );
+        }
+    }
+
+    /// A red ball on a grey floor, seen from the front against a black
+    /// background, moving `offset` to the side while the shutter is open.
+    fn render_ball_aovs(offset: Vec3d) -> Vec<Image> {
+        let renderer = Renderer::builder()
+            .object(Sphere::new(
+                Point3d::new(0.0, -1000.0, 0.0),
+                1000.0,
+                Lambertian::build_from_colour(RGB(0.5, 0.5, 0.5)),
+            ))
+            .object(MovingSphere::new(
+                Point3d::new(0.0, 1.0, 0.0),
+                Point3d::new(0.0, 1.0, 0.0) + offset,
+                0.0,
+                1.0,
+                1.0,
+                Lambertian::build_from_colour(RGB(0.8, 0.1, 0.1)),
+            ))
+            .camera(CameraDescription::new(
+                Point3d::new(0.0, 1.0, -5.0),
+                Point3d::new(0.0, 1.0, 0.0),
+                20.0,
+            ))
+            .background(Background::BLACK)
+            .shutter(0.0, 1.0)
+            .size(9, 9)
+            .samples(16)
+            .seed(5)
+            .build()
+            .unwrap();
+        renderer.render_aovs(0, &Aov::ALL)
+    }
+
+    #[test]
+    fn aovs_should_describe_the_first_hit() {
+        let [depth, normal, albedo, object_id, material_id, uv, motion] =
+            &render_ball_aovs(Vec3d::new(0.0, 0.0, 0.0))[..]
+        else {
+            panic!("expected a pass for each AOV");
+        };
+
+        // The front of the ball, in the middle of the image
+        assert!(
+            (depth.get(4, 4).0 - 4.0).abs() < 0.05,
+            "{:?}",
+            depth.get(4, 4)
+        );
+        assert!(normal.get(4, 4).2 < -0.98, "{:?}", normal.get(4, 4));
+        let RGB(r, g, b) = albedo.get(4, 4);
+        assert!((r - 0.8).abs() + (g - 0.1).abs() + (b - 0.1).abs() < 1e-9);
+        assert_eq!(object_id.get(4, 4), RGB(2.0, 2.0, 2.0));
+        let RGB(u, v, _) = uv.get(4, 4);
+        assert!(
+            (u - 0.75).abs() < 0.05 && (v - 0.5).abs() < 0.05,
+            "{u}, {v}"
+        );
+        assert_eq!(motion.get(4, 4), RGB(0.0, 0.0, 0.0));
+
+        // The background in the top left, then the floor in the bottom left
+        assert_eq!(depth.get(0, 0).0, f64::INFINITY);
+        assert_eq!(object_id.get(0, 0), RGB(0.0, 0.0, 0.0));
+        assert_eq!(object_id.get(0, 8), RGB(1.0, 1.0, 1.0));
+        // Materials are numbered in the order they appear from the top left
+        assert_eq!(material_id.get(0, 0), RGB(0.0, 0.0, 0.0));
+        assert_eq!(material_id.get(4, 0), RGB(1.0, 1.0, 1.0));
+        assert_eq!(material_id.get(0, 8), RGB(2.0, 2.0, 2.0));
+    }
+
+    #[test]
+    fn motion_aov_should_follow_moving_objects() {
+        // The camera looks along z, so -x is to the right of the image
+        let motion = &render_ball_aovs(Vec3d::new(-0.5, 0.0, 0.0))[6];
+
+        // The front of the ball is 4 from the camera, where the 20 degree
+        // view is 8 tan(10) wide over 8 pixels
+        let expected = 0.5 / (8.0 * 10f64.to_radians().tan()) * 8.0;
+        let RGB(x, y, _) = motion.get(4, 4);
+        assert!((x - expected).abs() < 0.05 * expected, "{x} != {expected}");
+        assert!(y.abs() < 1e-9, "{y}");
+        assert_eq!(motion.get(0, 8), RGB(0.0, 0.0, 0.0));
+    }
+
+    /// A floor with the caustic cast by a glass sphere under a light,
+    /// rendered in passes of 16 samples.
+    fn render_caustic(caustics: Option<Caustics>, samples: usize) -> Image {
+        render_caustic_with(caustics, samples, true)
+    }
+
+    /// Like `render_caustic`, but without sampling the light over the
+    /// sphere unless `sample_light`. A dim light off to the side is sampled
+    /// instead so that photons are still traced.
+    fn render_caustic_with(
+        caustics: Option<Caustics>,
+        samples: usize,
+        sample_light: bool,
+    ) -> Image {
+        let light = RectangleXZ::new(
+            -1.0,
+            1.0,
+            -1.0,
+            1.0,
+            5.0,
+            DiffuseLight::build_from_colour(RGB(10.0, 10.0, 10.0)),
+        );
+        let mut builder = Renderer::builder()
+            .object(Sphere::new(
+                Point3d::new(0.0, -1000.0, 0.0),
+                1000.0,
+                Lambertian::build_from_colour(RGB(0.5, 0.5, 0.5)),
+            ))
+            .object(Sphere::new(
+                Point3d::new(0.0, 1.5, 0.0),
+                1.0,
+                Dielectric::new(1.5),
+            ))
+            .object(light)
+            // Looking at the caustic under the sphere, not through it
+            .camera(CameraDescription::new(
+                Point3d::new(0.0, 0.2, -3.0),
+                Point3d::new(0.0, 0.0, 0.0),
+                10.0,
+            ))
+            .size(8, 8)
+            .samples(samples)
+            .seed(3);
+        if let Some(caustics) = caustics {
+            builder = builder.caustics(caustics);
+        }
+        builder = match sample_light {
+            true => builder.light(light),
+            false => {
+                let side_light = Sphere::new(
+                    Point3d::new(20.0, 20.0, 20.0),
+                    0.1,
+                    DiffuseLight::build_from_colour(RGB(1.0, 1.0, 1.0)),
+                );
+                builder.object(side_light).light(side_light)
+            }
+        };
+        let renderer = builder.build().unwrap();
+        let mut checkpoint = renderer.checkpoint(0);
+        renderer
+            .render_progressive(&mut checkpoint, 16, &AtomicBool::new(false), |_, _| {})
+            .unwrap()
+    }
+
+    fn mean_brightness(image: &Image) -> f64 {
+        let brightness = |colour: &RGB| (colour.0 + colour.1 + colour.2) / 3.0;
+        image.pixels().iter().map(brightness).sum::<f64>() / image.pixels().len() as f64
+    }
+
+    fn rms_difference(a: &Image, b: &Image) -> f64 {
+        let brightness = |colour: &RGB| (colour.0 + colour.1 + colour.2) / 3.0;
+        let sum: f64 = a
+            .pixels()
+            .iter()
+            .zip(b.pixels())
+            .map(|(a, b)| (brightness(a) - brightness(b)).powi(2))
+            .sum();
+        (sum / a.pixels().len() as f64).sqrt()
+    }
+
+    #[test]
+    fn caustics_should_match_path_tracing() {
+        let path_traced = mean_brightness(&render_caustic(None, 8192));
+        let photons = mean_brightness(&render_caustic(Some(Caustics::new(200_000, 0.05)), 64));
+        assert!(
+            (photons - path_traced).abs() < 0.03 * path_traced,
+            "{photons} != {path_traced}"
+        );
+    }
+
+    #[test]
+    fn caustics_should_keep_the_caustics_of_lights_that_are_not_sampled() {
+        let path_traced = mean_brightness(&render_caustic_with(None, 1024, false));
+        let photons = mean_brightness(&render_caustic_with(
+            Some(Caustics::new(20_000, 0.05)),
+            1024,
+            false,
+        ));
+        assert!(
+            (photons - path_traced).abs() < 0.05 * path_traced,
+            "{photons} != {path_traced}"
+        );
+    }
+
+    #[test]
+    fn progressive_caustics_should_get_sharper() {
+        let path_traced = render_caustic(None, 8192);
+        let fixed = render_caustic(Some(Caustics::new(20_000, 0.3)), 256);
+        let progressive = render_caustic(Some(Caustics::new(20_000, 0.3).progressive(0.5)), 256);
+        let fixed_error = rms_difference(&fixed, &path_traced);
+        let progressive_error = rms_difference(&progressive, &path_traced);
+        assert!(
+            progressive_error < 0.8 * fixed_error,
+            "{progressive_error} >= {fixed_error}"
+        );
+    }
+
+    /// The inside of a bright sphere lit by a small light, where light
+    /// bounces many times before it is absorbed.
+    fn render_bright_interior(russian_roulette_depth: Option<usize>) -> f64 {
+        let light = Sphere::new(
+            Point3d::new(0.0, 2.0, 0.0),
+            0.5,
+            DiffuseLight::build_from_colour(RGB(4.0, 4.0, 4.0)),
+        );
+        let image = Renderer::builder()
+            .object(Sphere::new(
+                Point3d::new(0.0, 0.0, 0.0),
+                4.0,
+                Lambertian::build_from_colour(RGB(0.8, 0.8, 0.8)),
+            ))
+            .object(light)
+            .light(light)
+            .camera(CameraDescription::new(
+                Point3d::new(0.0, 0.0, -3.0),
+                Point3d::new(0.0, 0.0, 0.0),
+                60.0,
+            ))
+            .size(8, 8)
+            .samples(256)
+            .max_depth(50)
+            .seed(1)
+            .russian_roulette_depth(russian_roulette_depth)
+            .build()
+            .unwrap()
+            .render(0);
+
+        let pixels = image.pixels();
+        pixels
+            .iter()
+            .map(|colour| (colour.0 + colour.1 + colour.2) / 3.0)
+            .sum::<f64>()
+            / pixels.len() as f64
+    }
+
+    #[test]
+    fn russian_roulette_should_not_change_brightness() {
+        let mean = render_bright_interior(None);
+        let roulette_mean = render_bright_interior(Some(3));
+
+        assert!(
+            (roulette_mean - mean).abs() < 0.05 * mean,
+            "mean: {roulette_mean}, without Russian roulette: {mean}"
+        );
+    }
+
+    #[test]
+    fn render_should_follow_paths_with_many_bounces() {
+        // Every ray bounces back and forth through the centre of a mirrored
+        // sphere until max_depth
+        let image = Renderer::builder()
+            .object(Sphere::new(
+                Point3d::new(0.0, 0.0, 0.0),
+                4.0,
+                Metal::new(RGB(1.0, 1.0, 1.0), 0.0),
+            ))
+            .camera(CameraDescription::new(
+                Point3d::new(0.0, 0.0, 0.0),
+                Point3d::new(0.0, 0.0, 1.0),
+                60.0,
+            ))
+            .size(2, 2)
+            .samples(1)
+            .max_depth(100_000)
+            .russian_roulette_depth(None)
+            .build()
+            .unwrap()
+            .render(0);
+
+        assert!(
+            image
+                .pixels()
+                .iter()
+                .all(|&colour| colour == RGB(0.0, 0.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn adaptive_sampling_should_stop_sampling_pixels_that_are_not_noisy() {
+        // The floor is noisy and the sky above it is black
+        let renderer = |adaptive_sampling| {
+            Renderer::builder()
+                .object(Sphere::new(
+                    Point3d::new(0.0, -1000.0, 0.0),
+                    1000.0,
+                    Lambertian::build_from_colour(RGB(0.5, 0.5, 0.5)),
+                ))
+                .object(small_light())
+                .light(small_light())
+                .camera(CameraDescription::new(
+                    Point3d::new(0.0, 1.0, -6.0),
+                    Point3d::new(0.0, 1.0, 0.0),
+                    30.0,
+                ))
+                .size(12, 8)
+                .samples(256)
+                .seed(1)
+                .adaptive_sampling(adaptive_sampling)
+                .build()
+        };
+
+        let image = renderer(AdaptiveSampling::new(16, 0.01)).unwrap().render(0);
+
+        let sample_counts = image.sample_counts().unwrap();
+        let (sky, floor) = sample_counts.split_at(sample_counts.len() / 2);
+        assert!(sky.iter().all(|&count| count == 16), "{sky:?}");
+        assert!(floor.iter().any(|&count| count > 64), "{floor:?}");
+        assert!(floor.iter().all(|&count| count < 256), "{floor:?}");
+
+        let result = renderer(AdaptiveSampling::new(1, 0.01));
+        assert!(matches!(result, Err(Error::InvalidSettings(_))));
+        for noise_threshold in [0.0, -0.01, f64::INFINITY, f64::NAN] {
+            let result = renderer(AdaptiveSampling::new(16, noise_threshold));
+            assert!(
+                matches!(result, Err(Error::InvalidSettings(_))),
+                "{noise_threshold}"
+            );
+        }
+    }
+
+    #[test]
+    fn render_progressive_should_report_each_pass() {
+        let renderer = light_renderer()
+            .background(Background::Sky)
+            .samples(10)
+            .seed(3)
+            .build()
+            .unwrap();
+
+        let mut passes = Vec::new();
+        let mut checkpoint = renderer.checkpoint(0);
+        let image = renderer
+            .render_progressive(
+                &mut checkpoint,
+                4,
+                &AtomicBool::new(false),
+                |checkpoint, pass| {
+                    assert!(
+                        checkpoint
+                            .image()
+                            .sample_counts()
+                            .unwrap()
+                            .iter()
+                            .all(|&count| count == pass.max_samples)
+                    );
+                    passes.push((pass.pass_number, pass.num_passes, pass.max_samples));
+                },
+            )
+            .unwrap();
+
+        assert_eq!(passes, [(1, 3, 4), (2, 3, 8), (3, 3, 10)]);
+        assert!(
+            image
+                .sample_counts()
+                .unwrap()
+                .iter()
+                .all(|&count| count == 10)
+        );
+        let again = renderer
+            .render_progressive(
+                &mut renderer.checkpoint(0),
+                4,
+                &AtomicBool::new(false),
+                |_, _| {},
+            )
+            .unwrap();
+        assert_eq!(image, again);
+    }
+
+    #[test]
+    fn render_progressive_should_return_the_image_so_far_when_stopped() {
+        let renderer = light_renderer()
+            .background(Background::Sky)
+            .samples(100)
+            .build()
+            .unwrap();
+        let stop = AtomicBool::new(false);
+
+        let mut passes = Vec::new();
+        let mut checkpoint = renderer.checkpoint(0);
+        let image = renderer
+            .render_progressive(&mut checkpoint, 4, &stop, |_, pass| {
+                passes.push(pass.stopped);
+                stop.store(true, Ordering::Relaxed);
+            })
+            .unwrap();
+
+        assert_eq!(passes, [false, true]);
+        assert_eq!(checkpoint.max_samples(), 4);
+        assert!(
+            image
+                .sample_counts()
+                .unwrap()
+                .iter()
+                .all(|&count| count == 4)
+        );
+        assert!(
+            image
+                .pixels()
+                .iter()
+                .all(|&colour| colour != RGB(0.0, 0.0, 0.0))
+        );
+    }
+
+    fn checkpoint_file(name: &str) -> std::path::PathBuf {
+        let folder = std::env::temp_dir().join("rs_ray_tracer_renderer_tests");
+        std::fs::create_dir_all(&folder).unwrap();
+        folder.join(name)
+    }
+
+    #[test]
+    fn render_progressive_should_add_samples_to_a_saved_checkpoint() {
+        let renderer = |num_samples| {
+            light_renderer()
+                .background(Background::Sky)
+                .samples(num_samples)
+                .seed(3)
+                .build()
+                .unwrap()
+        };
+        let stop = AtomicBool::new(false);
+        let file_path = checkpoint_file("add_samples.checkpoint");
+
+        let mut checkpoint = renderer(4).checkpoint(0);
+        renderer(4)
+            .render_progressive(&mut checkpoint, 4, &stop, |_, _| {})
+            .unwrap();
+        checkpoint.save(&file_path).unwrap();
+        let mut loaded = Checkpoint::load(&file_path).unwrap();
+        assert_eq!(loaded, checkpoint);
+
+        let mut passes = Vec::new();
+        let image = renderer(8)
+            .render_progressive(&mut loaded, 4, &stop, |_, pass| {
+                passes.push((pass.pass_number, pass.num_passes, pass.max_samples));
+            })
+            .unwrap();
+
+        assert_eq!(passes, [(1, 1, 8)]);
+        let uninterrupted = renderer(8)
+            .render_progressive(&mut renderer(8).checkpoint(0), 4, &stop, |_, _| {})
+            .unwrap();
+        assert_eq!(image, uninterrupted);
+    }
+
+    #[test]
+    fn render_progressive_should_reject_checkpoints_of_other_renders() {
+        let renderer = light_renderer().scene_fingerprint(1).build().unwrap();
+        let stop = AtomicBool::new(false);
+        let mut checkpoint = renderer.checkpoint(0);
+
+        for other in [
+            light_renderer().scene_fingerprint(2),
+            light_renderer().scene_fingerprint(1).max_depth(3),
+            light_renderer().scene_fingerprint(1).size(16, 12),
+            light_renderer().scene_fingerprint(1).seed(4),
+            light_renderer()
+                .scene_fingerprint(1)
+                .pixel_filter(PixelFilter::new(Filter::Tent)),
+        ] {
+            let result =
+                other
+                    .build()
+                    .unwrap()
+                    .render_progressive(&mut checkpoint, 4, &stop, |_, _| {});
+            assert!(matches!(result, Err(Error::InvalidCheckpoint(_))));
+        }
+
+        let more_samples = light_renderer().scene_fingerprint(1).samples(8);
+        let result =
+            more_samples
+                .build()
+                .unwrap()
+                .render_progressive(&mut checkpoint, 4, &stop, |_, _| {});
+        assert!(result.is_ok());
+
+        let file_path = checkpoint_file("not_a_checkpoint.checkpoint");
+        std::fs::write(&file_path, "P3\n2 2\n255\n").unwrap();
+        let result = Checkpoint::load(&file_path);
+        assert!(matches!(result, Err(Error::InvalidCheckpoint(_))));
+    }
+
+    #[test]
+    fn load_should_reject_checkpoints_too_large_to_address() {
+        let file_path = checkpoint_file("too_large.checkpoint");
+        let mut bytes = b"RSRTCKPT".to_vec();
+        // Version, camera, width, height, passes, samples and fingerprints
+        for value in [4, 0, u64::MAX / 2, 4, 1, 1, 0, 0] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        std::fs::write(&file_path, bytes).unwrap();
+
+        let result = Checkpoint::load(&file_path);
+
+        assert!(matches!(result, Err(Error::InvalidCheckpoint(_))));
+    }
+}
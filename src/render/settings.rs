@@ -0,0 +1,378 @@
+use crate::{colour::RGB, ray::Ray, resolution::Resolution};
+
+use super::{
+    filter::PixelFilter,
+    tiles::{TileOrder, TileRect},
+};
+
+/// What rays that do not hit anything see.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Background {
+    /// A gradient from white at the horizon to blue overhead.
+    Sky,
+    Colour(RGB),
+}
+
+impl Background {
+    pub const BLACK: Background = Background::Colour(RGB(0.0, 0.0, 0.0));
+
+    /// Returns `Sky` if `use_sky_background` is true, otherwise black.
+    pub fn from_sky(use_sky_background: bool) -> Self {
+        match use_sky_background {
+            true => Background::Sky,
+            false => Background::BLACK,
+        }
+    }
+
+    pub fn colour(&self, ray: &Ray) -> RGB {
+        match self {
+            Background::Sky => {
+                // Return sky colour based on direction of ray
+                let unit_direction = ray.direction.unit_vector();
+                let t = 0.5 * (unit_direction.y + 1.0);
+                (1.0 - t) * RGB(1.0, 1.0, 1.0) + t * RGB(0.5, 0.7, 1.0)
+            }
+            Background::Colour(colour) => *colour,
+        }
+    }
+}
+
+/// How light reaching diffuse and glossy surfaces is found. Light can be found by
+/// following the ray the material scatters until it happens to hit a light,
+/// or by tracing a shadow ray to a random point on a light. The first is noisy
+/// for small lights, the second for large lights close to a surface, so by
+/// default both are used and weighted by how likely each was to find the
+/// light (multiple importance sampling).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LightSampling {
+    /// Only follow scattered rays, as in the book.
+    Material,
+    /// Only trace shadow rays to the lights (next-event estimation).
+    Lights,
+    /// Both, weighted with the balance heuristic.
+    Balance,
+    /// Both, weighted with the power heuristic. This usually has the least
+    /// noise.
+    #[default]
+    Power,
+}
+
+/// How the light reaching the camera is found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Integrator {
+    /// Follow paths from the camera, sampling the lights at each bounce.
+    #[default]
+    PathTracing,
+    /// Follow paths from both the camera and the lights and join them up.
+    /// Slower per sample, but finds light that reaches the camera through
+    /// narrow gaps or after hitting glass far more often. `light_sampling`
+    /// is not used, the lights are always sampled.
+    Bidirectional,
+}
+
+/// Finds caustics, the light focused onto surfaces by glass and mirrors, with
+/// a photon map. Path tracing only finds a caustic when a path happens to
+/// bounce off the glass into a light, which is rare for small lights, so
+/// caustics come out as scattered bright pixels. Instead photons are traced
+/// from the lights before each pass and the caustic at a hit is estimated
+/// from the density of the photons around it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Caustics {
+    /// Number of photons traced from the lights for each pass. Only photons
+    /// that bounce off a specular surface are kept.
+    pub num_photons: usize,
+    /// Radius photons are gathered from around a hit, in scene units. Larger
+    /// radii blur the caustics, smaller ones leave them noisy.
+    pub radius: f64,
+    /// With `Some(alpha)`, from 0 to 1, every pass of a progressive render
+    /// gathers from a smaller radius than the pass before it, so the blur
+    /// fades and the image converges to the same result as path tracing
+    /// (progressive photon mapping). Lower values shrink the radius faster.
+    /// `None` keeps the same radius.
+    pub radius_reduction: Option<f64>,
+}
+
+impl Caustics {
+    pub fn new(num_photons: usize, radius: f64) -> Self {
+        Self {
+            num_photons,
+            radius,
+            radius_reduction: None,
+        }
+    }
+
+    /// Shrinks the radius with each pass, see `radius_reduction`.
+    pub fn progressive(mut self, alpha: f64) -> Self {
+        self.radius_reduction = Some(alpha);
+        self
+    }
+
+    /// Returns the radius to gather photons from in a pass of a render.
+    pub(super) fn radius(&self, pass_index: usize) -> f64 {
+        match self.radius_reduction {
+            Some(alpha) => {
+                // The area of the radius shrinks by (i + alpha) / (i + 1)
+                // each pass, slowly enough that the noise still averages out
+                let area_scale: f64 = (1..=pass_index)
+                    .map(|i| (i as f64 + alpha) / (i as f64 + 1.0))
+                    .product();
+                self.radius * area_scale.sqrt()
+            }
+            None => self.radius,
+        }
+    }
+}
+
+/// Stops sampling pixels once they are no longer noisy, so that flat areas
+/// such as the sky take few samples and noisy areas such as caustics take up
+/// to the `num_samples` of the render.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveSampling {
+    /// Number of samples taken for every pixel before its noise is measured,
+    /// at least 2. Rare bright paths can be missed by the first samples, so
+    /// this should be high enough to find them.
+    pub min_samples: usize,
+    /// The noise a pixel is sampled down to, as the standard error of its
+    /// brightness once gamma corrected, from 0 to 1. e.g. 0.004 is about one
+    /// level of an 8-bit image.
+    pub noise_threshold: f64,
+}
+
+impl AdaptiveSampling {
+    pub fn new(min_samples: usize, noise_threshold: f64) -> Self {
+        Self {
+            min_samples,
+            noise_threshold,
+        }
+    }
+}
+
+/// A region of the image to render, e.g. to look at a problem in one corner
+/// without rendering the whole image. The rest of the image is left black,
+/// `Image::crop` cuts out the region and `Image::fill_region` merges it into
+/// an earlier render.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CropWindow {
+    /// A rectangle of pixels from column `x` and row `y`, counted from the
+    /// top left corner.
+    Pixels {
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    },
+    /// Fractions of the image width and height from 0 to 1, counted from the
+    /// top left corner. Pixels partly inside the window are rendered.
+    Normalised { x0: f64, y0: f64, x1: f64, y1: f64 },
+}
+
+impl CropWindow {
+    /// Returns whether the window covers at least one pixel and is inside an
+    /// image of the given size.
+    pub fn fits(&self, image_width: usize, image_height: usize) -> bool {
+        match *self {
+            CropWindow::Pixels {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                width > 0
+                    && height > 0
+                    && x.checked_add(width).is_some_and(|x1| x1 <= image_width)
+                    && y.checked_add(height).is_some_and(|y1| y1 <= image_height)
+            }
+            CropWindow::Normalised { x0, y0, x1, y1 } => {
+                (0.0 <= x0 && x0 < x1 && x1 <= 1.0) && (0.0 <= y0 && y0 < y1 && y1 <= 1.0)
+            }
+        }
+    }
+
+    /// Returns the pixels inside the window in an image of the given size.
+    pub(super) fn rect(&self, image_width: usize, image_height: usize) -> TileRect {
+        let (x0, y0, x1, y1) = match *self {
+            CropWindow::Pixels {
+                x,
+                y,
+                width,
+                height,
+            } => (x, y, x.saturating_add(width), y.saturating_add(height)),
+            CropWindow::Normalised { x0, y0, x1, y1 } => (
+                (x0 * image_width as f64).floor() as usize,
+                (y0 * image_height as f64).floor() as usize,
+                (x1 * image_width as f64).ceil() as usize,
+                (y1 * image_height as f64).ceil() as usize,
+            ),
+        };
+        let (x0, y0) = (x0.min(image_width), y0.min(image_height));
+        TileRect {
+            x: x0,
+            y: y0,
+            width: x1.clamp(x0, image_width) - x0,
+            height: y1.clamp(y0, image_height) - y0,
+        }
+    }
+}
+
+/// Settings for a render; the size of the image, how it is sampled, and what
+/// the camera sees. Settings are built up from the defaults, e.g.
+/// `RenderSettings::default().size(400, 400).samples(100)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderSettings {
+    pub image_width: usize,
+    pub image_height: usize,
+    /// Number of ray samples per pixel, or the most samples per pixel with
+    /// adaptive sampling.
+    pub num_samples: usize,
+    /// Max number of ray bounces. With Russian roulette most paths end long
+    /// before this, so it is only a safety cap.
+    pub max_depth: usize,
+    pub background: Background,
+    /// Shutter open time.
+    pub time0: f64,
+    /// Shutter close time.
+    pub time1: f64,
+    /// Seed for the random numbers used while rendering, makes renders
+    /// repeatable regardless of the number of threads.
+    pub seed: Option<u64>,
+    /// How light is found at diffuse and glossy surfaces. Only lights given to
+    /// `RendererBuilder::light` can be sampled directly.
+    pub light_sampling: LightSampling,
+    /// Number of bounces after which paths are ended at random, more likely
+    /// the less light they carry, with the light of surviving paths scaled up
+    /// to make up for it (Russian roulette). `None` follows every path to
+    /// `max_depth`.
+    pub russian_roulette_depth: Option<usize>,
+    pub integrator: Integrator,
+    /// Find caustics with a photon map when path tracing, `None` leaves
+    /// them to path tracing.
+    pub caustics: Option<Caustics>,
+    /// Stop sampling pixels once they are no longer noisy, `None` takes
+    /// `num_samples` for every pixel.
+    pub adaptive_sampling: Option<AdaptiveSampling>,
+    /// How samples are spread over the pixels around them. The default box
+    /// filter averages the samples of each pixel.
+    pub pixel_filter: PixelFilter,
+    /// Width and height of the square tiles the image is rendered in.
+    pub tile_size: usize,
+    pub tile_order: TileOrder,
+    /// Only render this region of the image, `None` renders all of it.
+    pub crop: Option<CropWindow>,
+}
+
+impl Default for RenderSettings {
+    /// The `low` resolution preset with a black background.
+    fn default() -> Self {
+        Self::from_resolution(Resolution::low())
+    }
+}
+
+impl RenderSettings {
+    /// Uses the size and sampling settings of a resolution preset.
+    pub fn from_resolution(resolution: Resolution) -> Self {
+        Self {
+            image_width: resolution.image_width,
+            image_height: resolution.image_height,
+            num_samples: resolution.num_samples,
+            max_depth: resolution.max_depth,
+            background: Background::BLACK,
+            time0: 0.0,
+            time1: 1.0,
+            seed: None,
+            light_sampling: LightSampling::default(),
+            russian_roulette_depth: Some(3),
+            integrator: Integrator::default(),
+            caustics: None,
+            adaptive_sampling: None,
+            pixel_filter: PixelFilter::default(),
+            tile_size: 16,
+            tile_order: TileOrder::default(),
+            crop: None,
+        }
+    }
+
+    pub fn size(mut self, image_width: usize, image_height: usize) -> Self {
+        self.image_width = image_width;
+        self.image_height = image_height;
+        self
+    }
+
+    pub fn samples(mut self, num_samples: usize) -> Self {
+        self.num_samples = num_samples;
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn background(mut self, background: Background) -> Self {
+        self.background = background;
+        self
+    }
+
+    pub fn shutter(mut self, time0: f64, time1: f64) -> Self {
+        self.time0 = time0;
+        self.time1 = time1;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn light_sampling(mut self, light_sampling: LightSampling) -> Self {
+        self.light_sampling = light_sampling;
+        self
+    }
+
+    pub fn russian_roulette_depth(mut self, russian_roulette_depth: Option<usize>) -> Self {
+        self.russian_roulette_depth = russian_roulette_depth;
+        self
+    }
+
+    pub fn integrator(mut self, integrator: Integrator) -> Self {
+        self.integrator = integrator;
+        self
+    }
+
+    pub fn caustics(mut self, caustics: Caustics) -> Self {
+        self.caustics = Some(caustics);
+        self
+    }
+
+    pub fn adaptive_sampling(mut self, adaptive_sampling: AdaptiveSampling) -> Self {
+        self.adaptive_sampling = Some(adaptive_sampling);
+        self
+    }
+
+    pub fn pixel_filter(mut self, pixel_filter: PixelFilter) -> Self {
+        self.pixel_filter = pixel_filter;
+        self
+    }
+
+    pub fn tiles(mut self, tile_size: usize, tile_order: TileOrder) -> Self {
+        self.tile_size = tile_size;
+        self.tile_order = tile_order;
+        self
+    }
+
+    pub fn crop(mut self, crop: CropWindow) -> Self {
+        self.crop = Some(crop);
+        self
+    }
+
+    /// Returns the pixels to render, the crop window or the whole image.
+    pub(super) fn render_area(&self) -> TileRect {
+        match self.crop {
+            Some(crop) => crop.rect(self.image_width, self.image_height),
+            None => TileRect::image(self.image_width, self.image_height),
+        }
+    }
+
+    pub fn aspect_ratio(&self) -> f64 {
+        (self.image_width as f64) / (self.image_height as f64)
+    }
+}
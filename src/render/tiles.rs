@@ -0,0 +1,177 @@
+use std::f64::consts::PI;
+
+/// The order tiles of the image are rendered in. Tiles are rendered in
+/// parallel, so the order is the order tiles are started in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TileOrder {
+    /// A row at a time from the top left corner.
+    #[default]
+    Scanline,
+    /// Outwards from the centre of the image, where the subject usually is.
+    Spiral,
+    /// Along a Hilbert curve, which keeps each tile next to the one before
+    /// it so the objects hit by nearby tiles are more likely to be cached.
+    Hilbert,
+}
+
+/// A rectangle of pixels, counted from the top left corner of the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct TileRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl TileRect {
+    /// Returns the rectangle covering a whole image.
+    pub fn image(image_width: usize, image_height: usize) -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            width: image_width,
+            height: image_height,
+        }
+    }
+
+    /// Returns the indexes of the tile's pixels in an image `image_width`
+    /// pixels wide, a row at a time.
+    pub fn indexes(&self, image_width: usize) -> impl Iterator<Item = usize> + use<> {
+        let TileRect {
+            x,
+            y,
+            width,
+            height,
+        } = *self;
+        (y..y + height)
+            .flat_map(move |row| (x..x + width).map(move |column| row * image_width + column))
+    }
+}
+
+/// Splits an area of an image into square tiles of `tile_size` pixels,
+/// smaller at the right and bottom edges, and returns them in the order to
+/// render them.
+pub(super) fn tiles(area: TileRect, tile_size: usize, order: TileOrder) -> Vec<TileRect> {
+    let columns = area.width.div_ceil(tile_size);
+    let rows = area.height.div_ceil(tile_size);
+    let mut grid: Vec<(usize, usize)> = (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (column, row)))
+        .collect();
+
+    match order {
+        TileOrder::Scanline => {}
+        TileOrder::Spiral => {
+            // Ring by ring out from the centre, each ring in order of angle
+            let centre_x = (columns as f64 - 1.0) / 2.0;
+            let centre_y = (rows as f64 - 1.0) / 2.0;
+            let key = |&(column, row): &(usize, usize)| {
+                let dx = column as f64 - centre_x;
+                let dy = row as f64 - centre_y;
+                let ring = dx.abs().max(dy.abs()).round();
+                let angle = dy.atan2(dx).rem_euclid(2.0 * PI);
+                (ring, angle)
+            };
+            grid.sort_by(|a, b| key(a).partial_cmp(&key(b)).unwrap());
+        }
+        TileOrder::Hilbert => {
+            let size = columns.max(rows).next_power_of_two();
+            grid.sort_by_key(|&(column, row)| hilbert_index(size, column, row));
+        }
+    }
+
+    grid.into_iter()
+        .map(|(column, row)| {
+            let x = column * tile_size;
+            let y = row * tile_size;
+            TileRect {
+                x: area.x + x,
+                y: area.y + y,
+                width: tile_size.min(area.width - x),
+                height: tile_size.min(area.height - y),
+            }
+        })
+        .collect()
+}
+
+/// Returns how far along a Hilbert curve filling a `size` by `size` grid the
+/// cell at `x`, `y` is. `size` must be a power of two.
+fn hilbert_index(size: usize, mut x: usize, mut y: usize) -> usize {
+    let mut index = 0;
+    let mut s = size / 2;
+    while s > 0 {
+        let rx = usize::from(x & s > 0);
+        let ry = usize::from(y & s > 0);
+        index += s * s * ((3 * rx) ^ ry);
+        // Rotate the quadrant so the curve inside it joins up
+        if ry == 0 {
+            if rx == 1 {
+                x = size - 1 - x;
+                y = size - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tiles_tests {
+    use super::*;
+
+    #[test]
+    fn tiles_should_cover_every_pixel_once() {
+        for order in [TileOrder::Scanline, TileOrder::Spiral, TileOrder::Hilbert] {
+            let mut covered = vec![0; 50 * 30];
+            for tile in tiles(TileRect::image(50, 30), 8, order) {
+                for index in tile.indexes(50) {
+                    covered[index] += 1;
+                }
+            }
+
+            assert!(covered.iter().all(|&count| count == 1), "{order:?}");
+        }
+    }
+
+    #[test]
+    fn spiral_should_start_from_the_centre() {
+        let tiles = tiles(TileRect::image(40, 40), 8, TileOrder::Spiral);
+
+        assert_eq!((tiles[0].x, tiles[0].y), (16, 16));
+        assert_eq!(tiles.len(), 25);
+    }
+
+    #[test]
+    fn hilbert_curve_should_step_to_neighbouring_tiles() {
+        let tiles = tiles(TileRect::image(64, 64), 8, TileOrder::Hilbert);
+
+        assert_eq!((tiles[0].x, tiles[0].y), (0, 0));
+        for pair in tiles.windows(2) {
+            let distance = pair[0].x.abs_diff(pair[1].x) + pair[0].y.abs_diff(pair[1].y);
+            assert_eq!(distance, 8, "{pair:?}");
+        }
+    }
+
+    #[test]
+    fn tiles_should_cover_only_the_area() {
+        let area = TileRect {
+            x: 10,
+            y: 5,
+            width: 20,
+            height: 12,
+        };
+
+        let mut covered = vec![0; 50 * 30];
+        for tile in tiles(area, 8, TileOrder::Spiral) {
+            for index in tile.indexes(50) {
+                covered[index] += 1;
+            }
+        }
+
+        for (index, &count) in covered.iter().enumerate() {
+            let (x, y) = (index % 50, index / 50);
+            let inside = (10..30).contains(&x) && (5..17).contains(&y);
+            assert_eq!(count, usize::from(inside), "({x}, {y})");
+        }
+    }
+}
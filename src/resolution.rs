@@ -1,3 +1,4 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Resolution {
     pub image_width: usize,
     pub image_height: usize,
@@ -10,7 +11,7 @@ pub struct Resolution {
 impl Resolution {
     /// * `num_samples`: Number of ray samples per pixel.
     /// * `max_depth`: Max number of ray bounces.
-    pub fn new(
+    pub const fn new(
         image_width: usize,
         image_height: usize,
         num_samples: usize,
@@ -24,6 +25,26 @@ impl Resolution {
         }
     }
 
+    /// 600x400 with 500 samples per pixel.
+    pub const fn low() -> Resolution {
+        Resolution::new(600, 400, 500, 50)
+    }
+
+    /// 400x400 with 500 samples per pixel, suited to the Cornell box scenes.
+    pub const fn cornell_square() -> Resolution {
+        Resolution::new(400, 400, 500, 50)
+    }
+
+    /// 1200x800 with 500 samples per pixel.
+    pub const fn medium() -> Resolution {
+        Resolution::new(1200, 800, 500, 50)
+    }
+
+    /// 1920x1080 with 10000 samples per pixel.
+    pub const fn high() -> Resolution {
+        Resolution::new(1920, 1080, 10000, 50)
+    }
+
     pub fn get_aspect_ratio(&self) -> f64 {
         (self.image_width as f64) / (self.image_height as f64)
     }
@@ -0,0 +1,903 @@
+use std::{collections::BTreeMap, fs, sync::Arc, time::UNIX_EPOCH};
+
+use super::{
+    graph::SceneNode,
+    validate::{self, Diagnostic},
+    writer::write_scene,
+};
+use crate::{
+    Error, Result,
+    bvh::bvh::{Bvh, BvhMetrics},
+    camera::Camera,
+    colour::RGB,
+    hittable::hittable::Hittable,
+    instances::{RotateX, RotateY, RotateZ, Scale, Translate},
+    materials::{
+        Dielectric, Diffuse, DiffuseLight, Isotropic, Lambertian, Material, MaterialLibrary, Metal,
+    },
+    objects::{
+        BoxObj, Model, MovingSphere, Pyramid, RectangleXY, RectangleXZ, RectangleYZ, Sphere,
+        Triangle,
+    },
+    render::{Background, Renderer, RendererBuilder},
+    resolution::Resolution,
+    textures::{
+        CheckerTexture, ImageTexture, NoiseTexture, Perlin, SolidColour, Texture, TurbulenceTexture,
+    },
+    utilities::fnv1a_hash,
+    vec3d::{Point3d, Vec3d},
+    volumes::constant_medium::ConstantMedium,
+};
+
+/// Resolution used when a scene does not suggest one.
+pub const DEFAULT_RESOLUTION: Resolution = Resolution::low();
+
+/// A description of a scene; the objects in it, the cameras looking at it and
+/// the settings it should be rendered with. A description is plain data, it
+/// can be turned into a renderer for the scene with `renderer`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneDescription {
+    /// Suggested resolution to render the scene at.
+    pub resolution: Option<Resolution>,
+    pub background: Background,
+    /// Shutter open time.
+    pub time0: f64,
+    /// Shutter close time.
+    pub time1: f64,
+    pub cameras: Vec<CameraDescription>,
+    pub objects: Vec<ObjectDescription>,
+    /// Materials that objects can refer to by name with
+    /// `MaterialDescription::Named`.
+    pub materials: BTreeMap<String, MaterialDescription>,
+    /// Textures that materials can refer to by name with
+    /// `TextureDescription::Named`.
+    pub textures: BTreeMap<String, TextureDescription>,
+    /// Replaces every material in the scene except lights, for example to do
+    /// a clay render.
+    pub material_override: Option<MaterialDescription>,
+}
+
+impl Default for SceneDescription {
+    fn default() -> Self {
+        Self {
+            resolution: None,
+            background: Background::BLACK,
+            time0: 0.0,
+            time1: 1.0,
+            cameras: Vec::new(),
+            objects: Vec::new(),
+            materials: BTreeMap::new(),
+            textures: BTreeMap::new(),
+            material_override: None,
+        }
+    }
+}
+
+impl SceneDescription {
+    /// Builds the objects in the scene and returns a renderer set up with
+    /// them, the cameras, and the scene's settings. The resolution is the
+    /// scene's suggested resolution or `DEFAULT_RESOLUTION`.
+    pub fn renderer(&self) -> Result<RendererBuilder> {
+        let library = self.library()?;
+        Ok(Renderer::builder()
+            .resolution(self.resolution.unwrap_or(DEFAULT_RESOLUTION))
+            .background(self.background)
+            .shutter(self.time0, self.time1)
+            .objects(self.build_objects_with(&library)?)
+            .lights(self.build_lights_with(&library)?)
+            .cameras(self.cameras.iter().copied())
+            .scene_fingerprint(self.fingerprint()))
+    }
+
+    /// Returns a hash of the scene, used to check a checkpoint is resumed
+    /// with the scene it was rendered from. The model and image files the
+    /// scene uses are hashed by their size and modification time, so that
+    /// changing one changes the fingerprint without reading every file.
+    pub fn fingerprint(&self) -> u64 {
+        let mut bytes = write_scene(self).into_bytes();
+        for file in self.asset_files() {
+            // Missing files fail when the scene is built
+            let Ok(metadata) = fs::metadata(file) else {
+                continue;
+            };
+            bytes.extend_from_slice(&metadata.len().to_le_bytes());
+            if let Ok(modified) = metadata.modified()
+                && let Ok(since_epoch) = modified.duration_since(UNIX_EPOCH)
+            {
+                bytes.extend_from_slice(&since_epoch.as_nanos().to_le_bytes());
+            }
+        }
+        fnv1a_hash(&bytes)
+    }
+
+    /// Returns the model and image files the scene uses, in the order they
+    /// appear in the scene.
+    pub fn asset_files(&self) -> Vec<&str> {
+        let mut files: Vec<&str> = self
+            .textures
+            .values()
+            .flat_map(TextureDescription::asset_files)
+            .collect();
+        files.extend(
+            self.materials
+                .values()
+                .chain(&self.material_override)
+                .flat_map(MaterialDescription::asset_files),
+        );
+        files.extend(self.objects.iter().flat_map(ObjectDescription::asset_files));
+        files
+    }
+
+    /// Checks the scene for problems that would otherwise only show up as a
+    /// panic while building it or as black pixels once it has rendered.
+    /// Diagnostics are returned in the order the objects appear in the scene.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        validate::validate(self)
+    }
+
+    /// Builds the scene's named materials and textures, with the material
+    /// override if the scene has one. Definitions can refer to each other in
+    /// any order.
+    pub fn library(&self) -> Result<MaterialLibrary> {
+        let mut library = MaterialLibrary::new();
+        for name in self.textures.keys() {
+            self.add_texture(name, &mut library, &mut Vec::new())?;
+        }
+        for name in self.materials.keys() {
+            self.add_material(name, &mut library, &mut Vec::new())?;
+        }
+        if let Some(material) = &self.material_override {
+            let material = material.build(&library)?;
+            library.set_override(material);
+        }
+        Ok(library)
+    }
+
+    /// Adds a named texture to the library after the textures it refers to.
+    /// `building` holds the textures being added so cycles can be reported.
+    fn add_texture<'a>(
+        &'a self,
+        name: &'a str,
+        library: &mut MaterialLibrary,
+        building: &mut Vec<&'a str>,
+    ) -> Result<()> {
+        if library.texture(name).is_some() {
+            return Ok(());
+        }
+        let texture = self
+            .textures
+            .get(name)
+            .ok_or_else(|| Error::UnknownTexture(name.to_string()))?;
+        if building.contains(&name) {
+            return Err(Error::CyclicDefinition(name.to_string()));
+        }
+        building.push(name);
+        for dependency in texture.named_textures() {
+            self.add_texture(dependency, library, building)?;
+        }
+        building.pop();
+        library.add_texture(name, texture.build(library)?);
+        Ok(())
+    }
+
+    /// Adds a named material to the library after the materials it refers to,
+    /// named textures must already be in the library.
+    fn add_material<'a>(
+        &'a self,
+        name: &'a str,
+        library: &mut MaterialLibrary,
+        building: &mut Vec<&'a str>,
+    ) -> Result<()> {
+        if library.material(name).is_some() {
+            return Ok(());
+        }
+        let material = self
+            .materials
+            .get(name)
+            .ok_or_else(|| Error::UnknownMaterial(name.to_string()))?;
+        if building.contains(&name) {
+            return Err(Error::CyclicDefinition(name.to_string()));
+        }
+        building.push(name);
+        if let MaterialDescription::Named(dependency) = material {
+            self.add_material(dependency, library, building)?;
+        }
+        building.pop();
+        library.add_material(name, material.build(library)?);
+        Ok(())
+    }
+
+    pub fn build_objects(&self) -> Result<Vec<Box<dyn Hittable>>> {
+        self.build_objects_with(&self.library()?)
+    }
+
+    /// Builds the objects with materials from `library` rather than the
+    /// scene's own library, for example to swap materials for one render.
+    pub fn build_objects_with(&self, library: &MaterialLibrary) -> Result<Vec<Box<dyn Hittable>>> {
+        build_objects(&self.objects, library, self.time0, self.time1)
+    }
+
+    /// Builds the lights that the renderer can sample directly; spheres and
+    /// rectangles with a light material, moved by any number of translations.
+    /// Other lights are still found by rays bouncing into them.
+    pub fn build_lights_with(&self, library: &MaterialLibrary) -> Result<Vec<Box<dyn Hittable>>> {
+        let mut lights = Vec::new();
+        for object in &self.objects {
+            object.build_lights(library, self.time0, self.time1, &mut lights)?;
+        }
+        Ok(lights
+            .into_iter()
+            .map(|light| Box::new(light) as Box<dyn Hittable>)
+            .collect())
+    }
+
+    pub fn build_bvh(&self) -> Result<(Bvh, BvhMetrics)> {
+        Bvh::build(self.time0, self.time1, self.build_objects()?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraDescription {
+    pub look_from: Point3d,
+    pub look_at: Point3d,
+    /// The "up" direction for the camera, used to control the roll/sideways
+    /// tilt of the camera.
+    pub view_up: Vec3d,
+    /// Vertical field of view in degrees.
+    pub vertical_fov: f64,
+    /// Diameter of the aperture, controls the depth of the focus plane.
+    pub aperture: f64,
+    /// Distance to plane of focus.
+    pub focus_distance: f64,
+}
+
+impl CameraDescription {
+    pub fn new(look_from: Point3d, look_at: Point3d, vertical_fov: f64) -> Self {
+        Self {
+            look_from,
+            look_at,
+            view_up: Vec3d::new(0.0, 1.0, 0.0),
+            vertical_fov,
+            aperture: 0.0,
+            focus_distance: 10.0,
+        }
+    }
+
+    pub fn build(&self, aspect_ratio: f64, time0: f64, time1: f64) -> Camera {
+        Camera::new(
+            self.look_from,
+            self.look_at,
+            self.view_up,
+            self.vertical_fov,
+            aspect_ratio,
+            self.aperture,
+            self.focus_distance,
+            time0,
+            time1,
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TextureDescription {
+    Solid(RGB),
+    Checker {
+        odd: Box<TextureDescription>,
+        even: Box<TextureDescription>,
+    },
+    Image {
+        file: String,
+    },
+    /// Perlin noise. Noise with a seed looks the same every time the scene is
+    /// built, without one it is different each time.
+    Noise {
+        scale: f64,
+        seed: Option<u64>,
+    },
+    /// Perlin noise with turbulence, see `Noise` for the seed.
+    Turbulence {
+        scale: f64,
+        seed: Option<u64>,
+    },
+    /// A texture defined in the scene's texture library.
+    Named(String),
+}
+
+impl TextureDescription {
+    /// Builds the texture, named textures are looked up in `library`.
+    pub fn build(&self, library: &MaterialLibrary) -> Result<Arc<dyn Texture>> {
+        Ok(match self {
+            TextureDescription::Solid(colour) => Arc::new(SolidColour::new(*colour)),
+            TextureDescription::Checker { odd, even } => Arc::new(CheckerTexture::new(
+                odd.build(library)?,
+                even.build(library)?,
+            )),
+            TextureDescription::Image { file } => Arc::new(ImageTexture::build(file)?),
+            TextureDescription::Noise { scale, seed } => {
+                Arc::new(NoiseTexture::new(build_perlin(*seed), *scale))
+            }
+            TextureDescription::Turbulence { scale, seed } => {
+                Arc::new(TurbulenceTexture::new(build_perlin(*seed), *scale))
+            }
+            TextureDescription::Named(name) => library
+                .texture(name)
+                .ok_or_else(|| Error::UnknownTexture(name.clone()))?,
+        })
+    }
+
+    /// Returns the names of the library textures this texture refers to.
+    /// Returns the image files the texture uses, not counting named
+    /// textures.
+    pub fn asset_files(&self) -> Vec<&str> {
+        match self {
+            TextureDescription::Checker { odd, even } => {
+                let mut files = odd.asset_files();
+                files.extend(even.asset_files());
+                files
+            }
+            TextureDescription::Image { file } => vec![file.as_str()],
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn named_textures(&self) -> Vec<&str> {
+        match self {
+            TextureDescription::Checker { odd, even } => {
+                let mut names = odd.named_textures();
+                names.extend(even.named_textures());
+                names
+            }
+            TextureDescription::Named(name) => vec![name.as_str()],
+            _ => Vec::new(),
+        }
+    }
+}
+
+fn build_perlin(seed: Option<u64>) -> Perlin {
+    match seed {
+        Some(seed) => Perlin::build_from_seed(seed),
+        None => Perlin::build_random(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MaterialDescription {
+    Diffuse(RGB),
+    Lambertian(TextureDescription),
+    Metal {
+        albedo: RGB,
+        fuzz: f64,
+    },
+    Dielectric {
+        refraction_index: f64,
+    },
+    DiffuseLight(TextureDescription),
+    Isotropic(TextureDescription),
+    /// A material defined in the scene's material library.
+    Named(String),
+}
+
+impl MaterialDescription {
+    /// Returns the image files the material's texture uses, not counting
+    /// named materials and textures.
+    pub fn asset_files(&self) -> Vec<&str> {
+        match self {
+            MaterialDescription::Lambertian(texture)
+            | MaterialDescription::DiffuseLight(texture)
+            | MaterialDescription::Isotropic(texture) => texture.asset_files(),
+            MaterialDescription::Diffuse(_)
+            | MaterialDescription::Metal { .. }
+            | MaterialDescription::Dielectric { .. }
+            | MaterialDescription::Named(_) => Vec::new(),
+        }
+    }
+
+    /// Builds the material, named materials and textures are looked up in
+    /// `library`. When the library has a material override it is returned in
+    /// place of any material that is not a light.
+    pub fn build(&self, library: &MaterialLibrary) -> Result<Arc<dyn Material>> {
+        if let Some(material) = library.override_material()
+            && !matches!(
+                self,
+                MaterialDescription::DiffuseLight(_) | MaterialDescription::Named(_)
+            )
+        {
+            return Ok(material.clone());
+        }
+        Ok(match self {
+            MaterialDescription::Diffuse(albedo) => Arc::new(Diffuse::new(*albedo)),
+            MaterialDescription::Lambertian(texture) => {
+                Arc::new(Lambertian::new(texture.build(library)?))
+            }
+            MaterialDescription::Metal { albedo, fuzz } => Arc::new(Metal::new(*albedo, *fuzz)),
+            MaterialDescription::Dielectric { refraction_index } => {
+                Arc::new(Dielectric::new(*refraction_index))
+            }
+            MaterialDescription::DiffuseLight(texture) => {
+                Arc::new(DiffuseLight::new(texture.build(library)?))
+            }
+            MaterialDescription::Isotropic(texture) => {
+                Arc::new(Isotropic::new(texture.build(library)?))
+            }
+            MaterialDescription::Named(name) => library
+                .material(name)
+                .ok_or_else(|| Error::UnknownMaterial(name.clone()))?,
+        })
+    }
+
+    /// Returns whether the material emits light, named materials are looked
+    /// up in `library`.
+    pub fn is_light(&self, library: &MaterialLibrary) -> bool {
+        match self {
+            MaterialDescription::DiffuseLight(_) => true,
+            MaterialDescription::Named(name) => library
+                .material(name)
+                .is_some_and(|material| material.is_emissive()),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ObjectDescription {
+    Sphere {
+        center: Point3d,
+        radius: f64,
+        material: MaterialDescription,
+    },
+    MovingSphere {
+        center0: Point3d,
+        center1: Point3d,
+        time0: f64,
+        time1: f64,
+        radius: f64,
+        material: MaterialDescription,
+    },
+    RectangleXY {
+        x0: f64,
+        x1: f64,
+        y0: f64,
+        y1: f64,
+        k: f64,
+        material: MaterialDescription,
+    },
+    RectangleXZ {
+        x0: f64,
+        x1: f64,
+        z0: f64,
+        z1: f64,
+        k: f64,
+        material: MaterialDescription,
+    },
+    RectangleYZ {
+        y0: f64,
+        y1: f64,
+        z0: f64,
+        z1: f64,
+        k: f64,
+        material: MaterialDescription,
+    },
+    Box {
+        min: Point3d,
+        max: Point3d,
+        material: MaterialDescription,
+    },
+    Triangle {
+        a: Point3d,
+        b: Point3d,
+        c: Point3d,
+        material: MaterialDescription,
+    },
+    /// See `Pyramid::build` for how the base triangle is used.
+    Pyramid {
+        base: (Point3d, Point3d, Point3d),
+        height: f64,
+        material: MaterialDescription,
+    },
+    /// A model loaded from a PLY file.
+    Model {
+        file: String,
+        material: MaterialDescription,
+    },
+    /// A group of objects stored in their own BVH.
+    Group(Vec<ObjectDescription>),
+    Translate {
+        offset: Vec3d,
+        object: Box<ObjectDescription>,
+    },
+    /// Rotation around the x-axis in degrees.
+    RotateX {
+        angle: f64,
+        object: Box<ObjectDescription>,
+    },
+    /// Rotation around the y-axis in degrees.
+    RotateY {
+        angle: f64,
+        object: Box<ObjectDescription>,
+    },
+    /// Rotation around the z-axis in degrees.
+    RotateZ {
+        angle: f64,
+        object: Box<ObjectDescription>,
+    },
+    Scale {
+        x: f64,
+        y: f64,
+        z: f64,
+        object: Box<ObjectDescription>,
+    },
+    ConstantMedium {
+        boundary: Box<ObjectDescription>,
+        texture: TextureDescription,
+        density: f64,
+    },
+    /// A scene graph node, flattened into the BVH it is placed in.
+    Node(SceneNode),
+}
+
+impl ObjectDescription {
+    /// Returns a copy of the object with all of its materials replaced, apart
+    /// from lights, like the material override of a scene. Named materials
+    /// are looked up in `library` to find the lights. The boundary of a
+    /// constant medium is not visible so it is left as is, and scene nodes
+    /// with their own material keep it.
+    pub fn with_material(
+        &self,
+        material: &MaterialDescription,
+        library: &MaterialLibrary,
+    ) -> ObjectDescription {
+        let mut object = self.clone();
+        object.set_material(material, library);
+        object
+    }
+
+    /// Returns the model and image files the object uses, not counting named
+    /// materials and textures.
+    pub fn asset_files(&self) -> Vec<&str> {
+        match self {
+            ObjectDescription::Model { file, material } => {
+                let mut files = vec![file.as_str()];
+                files.extend(material.asset_files());
+                files
+            }
+            ObjectDescription::Sphere { material, .. }
+            | ObjectDescription::MovingSphere { material, .. }
+            | ObjectDescription::RectangleXY { material, .. }
+            | ObjectDescription::RectangleXZ { material, .. }
+            | ObjectDescription::RectangleYZ { material, .. }
+            | ObjectDescription::Box { material, .. }
+            | ObjectDescription::Triangle { material, .. }
+            | ObjectDescription::Pyramid { material, .. } => material.asset_files(),
+            ObjectDescription::Group(objects) => objects
+                .iter()
+                .flat_map(ObjectDescription::asset_files)
+                .collect(),
+            ObjectDescription::Translate { object, .. }
+            | ObjectDescription::RotateX { object, .. }
+            | ObjectDescription::RotateY { object, .. }
+            | ObjectDescription::RotateZ { object, .. }
+            | ObjectDescription::Scale { object, .. } => object.asset_files(),
+            ObjectDescription::ConstantMedium {
+                boundary, texture, ..
+            } => {
+                let mut files = boundary.asset_files();
+                files.extend(texture.asset_files());
+                files
+            }
+            ObjectDescription::Node(node) => {
+                let mut files: Vec<&str> = node
+                    .material
+                    .iter()
+                    .flat_map(MaterialDescription::asset_files)
+                    .collect();
+                files.extend(
+                    node.children
+                        .iter()
+                        .flat_map(ObjectDescription::asset_files),
+                );
+                files
+            }
+        }
+    }
+
+    fn set_material(&mut self, new_material: &MaterialDescription, library: &MaterialLibrary) {
+        match self {
+            ObjectDescription::Sphere { material, .. }
+            | ObjectDescription::MovingSphere { material, .. }
+            | ObjectDescription::RectangleXY { material, .. }
+            | ObjectDescription::RectangleXZ { material, .. }
+            | ObjectDescription::RectangleYZ { material, .. }
+            | ObjectDescription::Box { material, .. }
+            | ObjectDescription::Triangle { material, .. }
+            | ObjectDescription::Pyramid { material, .. }
+            | ObjectDescription::Model { material, .. } => {
+                if !material.is_light(library) {
+                    *material = new_material.clone();
+                }
+            }
+            ObjectDescription::Group(objects) => {
+                for object in objects {
+                    object.set_material(new_material, library);
+                }
+            }
+            ObjectDescription::Translate { object, .. }
+            | ObjectDescription::RotateX { object, .. }
+            | ObjectDescription::RotateY { object, .. }
+            | ObjectDescription::RotateZ { object, .. }
+            | ObjectDescription::Scale { object, .. } => object.set_material(new_material, library),
+            ObjectDescription::ConstantMedium { .. } => {}
+            ObjectDescription::Node(node) => {
+                node.material.get_or_insert_with(|| new_material.clone());
+            }
+        }
+    }
+
+    /// Builds the object with materials from `library`, `time0` and `time1` are
+    /// used to work out the bounding boxes of moving objects.
+    pub fn build(
+        &self,
+        library: &MaterialLibrary,
+        time0: f64,
+        time1: f64,
+    ) -> Result<Arc<dyn Hittable>> {
+        Ok(match self {
+            ObjectDescription::Sphere {
+                center,
+                radius,
+                material,
+            } => Arc::new(Sphere::new(*center, *radius, material.build(library)?)),
+            ObjectDescription::MovingSphere {
+                center0,
+                center1,
+                time0,
+                time1,
+                radius,
+                material,
+            } => Arc::new(MovingSphere::new(
+                *center0,
+                *center1,
+                *time0,
+                *time1,
+                *radius,
+                material.build(library)?,
+            )),
+            ObjectDescription::RectangleXY {
+                x0,
+                x1,
+                y0,
+                y1,
+                k,
+                material,
+            } => Arc::new(RectangleXY::new(
+                *x0,
+                *x1,
+                *y0,
+                *y1,
+                *k,
+                material.build(library)?,
+            )),
+            ObjectDescription::RectangleXZ {
+                x0,
+                x1,
+                z0,
+                z1,
+                k,
+                material,
+            } => Arc::new(RectangleXZ::new(
+                *x0,
+                *x1,
+                *z0,
+                *z1,
+                *k,
+                material.build(library)?,
+            )),
+            ObjectDescription::RectangleYZ {
+                y0,
+                y1,
+                z0,
+                z1,
+                k,
+                material,
+            } => Arc::new(RectangleYZ::new(
+                *y0,
+                *y1,
+                *z0,
+                *z1,
+                *k,
+                material.build(library)?,
+            )),
+            ObjectDescription::Box { min, max, material } => {
+                Arc::new(BoxObj::new(*min, *max, material.build(library)?))
+            }
+            ObjectDescription::Triangle { a, b, c, material } => {
+                Arc::new(Triangle::new(*a, *b, *c, material.build(library)?))
+            }
+            ObjectDescription::Pyramid {
+                base,
+                height,
+                material,
+            } => Arc::new(Pyramid::build(*base, *height, material.build(library)?)),
+            ObjectDescription::Model { file, material } => {
+                Arc::new(Model::build(file, material.build(library)?)?.0)
+            }
+            ObjectDescription::Group(objects) => Arc::new(
+                Bvh::build(time0, time1, build_objects(objects, library, time0, time1)?)?.0,
+            ),
+            // Placed on its own a node is built like a group, it is only
+            // flattened when it is in a list of objects
+            ObjectDescription::Node(node) => Arc::new(
+                Bvh::build(
+                    time0,
+                    time1,
+                    build_objects(&node.flatten(library), library, time0, time1)?,
+                )?
+                .0,
+            ),
+            ObjectDescription::Translate { offset, object } => Arc::new(Translate::new(
+                *offset,
+                object.build(library, time0, time1)?,
+            )),
+            ObjectDescription::RotateX { angle, object } => Arc::new(RotateX::new(
+                *angle,
+                object.build(library, time0, time1)?,
+                time0,
+                time1,
+            )),
+            ObjectDescription::RotateY { angle, object } => Arc::new(RotateY::new(
+                *angle,
+                object.build(library, time0, time1)?,
+                time0,
+                time1,
+            )),
+            ObjectDescription::RotateZ { angle, object } => Arc::new(RotateZ::new(
+                *angle,
+                object.build(library, time0, time1)?,
+                time0,
+                time1,
+            )),
+            ObjectDescription::Scale { x, y, z, object } => {
+                Arc::new(Scale::new(*x, *y, *z, object.build(library, time0, time1)?))
+            }
+            ObjectDescription::ConstantMedium {
+                boundary,
+                texture,
+                density,
+            } => Arc::new(ConstantMedium::new(
+                boundary.build(library, time0, time1)?,
+                texture.build(library)?,
+                *density,
+            )),
+        })
+    }
+}
+
+impl ObjectDescription {
+    /// Adds the object to `lights` if it is a light that can be sampled, see
+    /// `SceneDescription::build_lights_with`. The lights are built the same way
+    /// as the objects so a ray hits both at the same distance.
+    fn build_lights(
+        &self,
+        library: &MaterialLibrary,
+        time0: f64,
+        time1: f64,
+        lights: &mut Vec<Arc<dyn Hittable>>,
+    ) -> Result<()> {
+        match self {
+            ObjectDescription::Sphere { material, .. }
+            | ObjectDescription::RectangleXY { material, .. }
+            | ObjectDescription::RectangleXZ { material, .. }
+            | ObjectDescription::RectangleYZ { material, .. }
+                if material.is_light(library) =>
+            {
+                lights.push(self.build(library, time0, time1)?);
+            }
+            ObjectDescription::Translate { offset, object } => {
+                object.build_instanced_lights(library, time0, time1, lights, |light| {
+                    Arc::new(Translate::new(*offset, light))
+                })?;
+            }
+            ObjectDescription::RotateX { angle, object } => {
+                object.build_instanced_lights(library, time0, time1, lights, |light| {
+                    Arc::new(RotateX::new(*angle, light, time0, time1))
+                })?;
+            }
+            ObjectDescription::RotateY { angle, object } => {
+                object.build_instanced_lights(library, time0, time1, lights, |light| {
+                    Arc::new(RotateY::new(*angle, light, time0, time1))
+                })?;
+            }
+            ObjectDescription::RotateZ { angle, object } => {
+                object.build_instanced_lights(library, time0, time1, lights, |light| {
+                    Arc::new(RotateZ::new(*angle, light, time0, time1))
+                })?;
+            }
+            ObjectDescription::Scale { x, y, z, object } => {
+                object.build_instanced_lights(library, time0, time1, lights, |light| {
+                    Arc::new(Scale::new(*x, *y, *z, light))
+                })?;
+            }
+            ObjectDescription::Group(objects) => {
+                for object in objects {
+                    object.build_lights(library, time0, time1, lights)?;
+                }
+            }
+            ObjectDescription::Node(node) => {
+                for object in node.flatten(library) {
+                    object.build_lights(library, time0, time1, lights)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Adds the lights in the object to `lights`, each wrapped in the
+    /// instance that `instance` builds around it.
+    fn build_instanced_lights(
+        &self,
+        library: &MaterialLibrary,
+        time0: f64,
+        time1: f64,
+        lights: &mut Vec<Arc<dyn Hittable>>,
+        instance: impl Fn(Arc<dyn Hittable>) -> Arc<dyn Hittable>,
+    ) -> Result<()> {
+        let mut instanced = Vec::new();
+        self.build_lights(library, time0, time1, &mut instanced)?;
+        lights.extend(instanced.into_iter().map(instance));
+        Ok(())
+    }
+}
+
+/// Builds a list of objects for a BVH, scene nodes are flattened so their
+/// objects go straight into the BVH.
+fn build_objects(
+    objects: &[ObjectDescription],
+    library: &MaterialLibrary,
+    time0: f64,
+    time1: f64,
+) -> Result<Vec<Box<dyn Hittable>>> {
+    let mut built: Vec<Box<dyn Hittable>> = Vec::new();
+    for object in objects {
+        match object {
+            ObjectDescription::Node(node) => {
+                built.extend(build_objects(
+                    &node.flatten(library),
+                    library,
+                    time0,
+                    time1,
+                )?);
+            }
+            object => built.push(Box::new(object.build(library, time0, time1)?)),
+        }
+    }
+    Ok(built)
+}
+
+#[cfg(test)]
+mod description_tests {
+    use super::*;
+
+    #[test]
+    fn fingerprint_should_change_with_the_files_the_scene_uses() {
+        let folder = std::env::temp_dir().join("rs_ray_tracer_description_tests");
+        fs::create_dir_all(&folder).unwrap();
+        let texture_file = folder.join("fingerprint.png");
+        fs::write(&texture_file, "before").unwrap();
+        let scene = SceneDescription {
+            objects: vec![ObjectDescription::Sphere {
+                center: Point3d::new(0.0, 0.0, 0.0),
+                radius: 1.0,
+                material: MaterialDescription::Lambertian(TextureDescription::Image {
+                    file: texture_file.to_str().unwrap().to_string(),
+                }),
+            }],
+            ..SceneDescription::default()
+        };
+
+        let before = scene.fingerprint();
+        assert_eq!(scene.fingerprint(), before);
+        fs::write(&texture_file, "changed").unwrap();
+
+        assert_ne!(scene.fingerprint(), before);
+    }
+}
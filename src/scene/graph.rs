@@ -0,0 +1,248 @@
+use crate::{materials::MaterialLibrary, vec3d::Vec3d};
+
+use super::description::{MaterialDescription, ObjectDescription};
+
+/// The local transform of a scene node. The children of the node are scaled
+/// first, then rotated around the x, y and z axes in that order, and finally
+/// translated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3d,
+    /// Rotation around each axis in degrees.
+    pub rotation: Vec3d,
+    pub scale: Vec3d,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    /// A transform that leaves objects where they are.
+    pub const IDENTITY: Transform = Transform {
+        translation: Vec3d::new(0.0, 0.0, 0.0),
+        rotation: Vec3d::new(0.0, 0.0, 0.0),
+        scale: Vec3d::new(1.0, 1.0, 1.0),
+    };
+
+    pub fn translation(x: f64, y: f64, z: f64) -> Self {
+        Self {
+            translation: Vec3d::new(x, y, z),
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::IDENTITY
+    }
+
+    /// Wraps the object in the instances needed to apply the transform, parts
+    /// of the transform that do nothing are skipped.
+    pub fn apply(&self, object: ObjectDescription) -> ObjectDescription {
+        let mut object = object;
+        if self.scale != Self::IDENTITY.scale {
+            object = ObjectDescription::Scale {
+                x: self.scale.x,
+                y: self.scale.y,
+                z: self.scale.z,
+                object: Box::new(object),
+            };
+        }
+        if self.rotation.x != 0.0 {
+            object = ObjectDescription::RotateX {
+                angle: self.rotation.x,
+                object: Box::new(object),
+            };
+        }
+        if self.rotation.y != 0.0 {
+            object = ObjectDescription::RotateY {
+                angle: self.rotation.y,
+                object: Box::new(object),
+            };
+        }
+        if self.rotation.z != 0.0 {
+            object = ObjectDescription::RotateZ {
+                angle: self.rotation.z,
+                object: Box::new(object),
+            };
+        }
+        if self.translation != Self::IDENTITY.translation {
+            object = ObjectDescription::Translate {
+                offset: self.translation,
+                object: Box::new(object),
+            };
+        }
+        object
+    }
+}
+
+/// A node in a scene graph. A node groups objects and other nodes so they can
+/// be placed together, e.g. a table with objects on it. The transform and
+/// material of a node are inherited by everything below it.
+///
+/// Unlike `ObjectDescription::Group` a node does not get its own BVH, it is
+/// flattened into the BVH it is placed in when the scene is built.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SceneNode {
+    pub name: Option<String>,
+    pub transform: Transform,
+    /// Replaces the materials of all objects below the node, unless a node
+    /// further down sets its own.
+    pub material: Option<MaterialDescription>,
+    /// Objects in the node, use `ObjectDescription::Node` for child nodes.
+    pub children: Vec<ObjectDescription>,
+}
+
+impl SceneNode {
+    pub fn new(transform: Transform, children: Vec<ObjectDescription>) -> Self {
+        Self {
+            name: None,
+            transform,
+            material: None,
+            children,
+        }
+    }
+
+    /// Returns the objects below the node with the transforms and materials of
+    /// the nodes above them applied, ready to be put into a BVH. Lights keep
+    /// their materials, named materials are looked up in `library` to find
+    /// them.
+    pub fn flatten(&self, library: &MaterialLibrary) -> Vec<ObjectDescription> {
+        self.flatten_with_material(None, library)
+    }
+
+    fn flatten_with_material(
+        &self,
+        inherited_material: Option<&MaterialDescription>,
+        library: &MaterialLibrary,
+    ) -> Vec<ObjectDescription> {
+        let material = self.material.as_ref().or(inherited_material);
+
+        let mut objects = Vec::new();
+        for child in &self.children {
+            match child {
+                ObjectDescription::Node(node) => {
+                    objects.extend(node.flatten_with_material(material, library));
+                }
+                object => objects.push(match material {
+                    Some(material) => object.with_material(material, library),
+                    None => object.clone(),
+                }),
+            }
+        }
+
+        if self.transform.is_identity() {
+            return objects;
+        }
+        objects
+            .into_iter()
+            .map(|object| self.transform.apply(object))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod graph_tests {
+    use std::sync::Arc;
+
+    use crate::{
+        colour::RGB, materials::DiffuseLight, scene::description::TextureDescription,
+        vec3d::Point3d,
+    };
+
+    use super::*;
+
+    fn sphere(material: MaterialDescription) -> ObjectDescription {
+        ObjectDescription::Sphere {
+            center: Point3d::new(0.0, 0.0, 0.0),
+            radius: 1.0,
+            material,
+        }
+    }
+
+    #[test]
+    fn flatten_should_apply_transforms_of_parent_nodes() {
+        let material = MaterialDescription::Dielectric {
+            refraction_index: 1.5,
+        };
+        let child = SceneNode {
+            transform: Transform {
+                rotation: Vec3d::new(0.0, 45.0, 0.0),
+                ..Transform::IDENTITY
+            },
+            ..SceneNode::new(Transform::IDENTITY, vec![sphere(material.clone())])
+        };
+        let root = SceneNode::new(
+            Transform::translation(1.0, 2.0, 3.0),
+            vec![ObjectDescription::Node(child), sphere(material.clone())],
+        );
+
+        let objects = root.flatten(&MaterialLibrary::new());
+
+        assert_eq!(
+            objects,
+            vec![
+                ObjectDescription::Translate {
+                    offset: Vec3d::new(1.0, 2.0, 3.0),
+                    object: Box::new(ObjectDescription::RotateY {
+                        angle: 45.0,
+                        object: Box::new(sphere(material.clone())),
+                    }),
+                },
+                ObjectDescription::Translate {
+                    offset: Vec3d::new(1.0, 2.0, 3.0),
+                    object: Box::new(sphere(material)),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn flatten_should_use_closest_material_override() {
+        let red = MaterialDescription::Diffuse(RGB(1.0, 0.0, 0.0));
+        let green = MaterialDescription::Diffuse(RGB(0.0, 1.0, 0.0));
+        let blue = MaterialDescription::Diffuse(RGB(0.0, 0.0, 1.0));
+        let child = SceneNode {
+            material: Some(green.clone()),
+            ..SceneNode::new(Transform::IDENTITY, vec![sphere(red.clone())])
+        };
+        let root = SceneNode {
+            material: Some(blue.clone()),
+            ..SceneNode::new(
+                Transform::IDENTITY,
+                vec![ObjectDescription::Node(child), sphere(red)],
+            )
+        };
+
+        let objects = root.flatten(&MaterialLibrary::new());
+
+        assert_eq!(objects, vec![sphere(green), sphere(blue)]);
+    }
+
+    #[test]
+    fn flatten_should_keep_the_materials_of_lights() {
+        let light =
+            MaterialDescription::DiffuseLight(TextureDescription::Solid(RGB(4.0, 4.0, 4.0)));
+        let red = MaterialDescription::Diffuse(RGB(1.0, 0.0, 0.0));
+        let blue = MaterialDescription::Diffuse(RGB(0.0, 0.0, 1.0));
+        let mut library = MaterialLibrary::new();
+        library.add_material(
+            "lamp",
+            Arc::new(DiffuseLight::build_from_colour(RGB(1.0, 1.0, 1.0))),
+        );
+        let lamp = MaterialDescription::Named("lamp".to_string());
+        let root = SceneNode {
+            material: Some(blue.clone()),
+            ..SceneNode::new(
+                Transform::IDENTITY,
+                vec![sphere(light.clone()), sphere(lamp.clone()), sphere(red)],
+            )
+        };
+
+        let objects = root.flatten(&library);
+
+        assert_eq!(objects, vec![sphere(light), sphere(lamp), sphere(blue)]);
+    }
+}
@@ -0,0 +1,14 @@
+mod description;
+mod graph;
+mod parser;
+mod validate;
+mod writer;
+
+pub use description::{
+    CameraDescription, DEFAULT_RESOLUTION, MaterialDescription, ObjectDescription,
+    SceneDescription, TextureDescription,
+};
+pub use graph::{SceneNode, Transform};
+pub use parser::{load_scene_file, parse_material_text, parse_scene};
+pub use validate::{Diagnostic, Severity, has_errors};
+pub use writer::{save_scene_file, write_scene};
@@ -0,0 +1,1494 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    Error, Result,
+    colour::RGB,
+    render::Background,
+    resolution::Resolution,
+    vec3d::{Point3d, Vec3d},
+};
+
+use super::{
+    description::{
+        CameraDescription, MaterialDescription, ObjectDescription, SceneDescription,
+        TextureDescription,
+    },
+    graph::SceneNode,
+};
+
+/// Reads and parses a scene file, see `docs/scene_format.md` for a description
+/// of the format. Included files are found relative to the file including
+/// them.
+pub fn load_scene_file<P: AsRef<Path>>(file_path: P) -> Result<SceneDescription> {
+    let file_path = file_path.as_ref();
+    let (nodes, _) = load_nodes(file_path, &Arguments::new(), &mut Vec::new())?;
+
+    scene_from_nodes(nodes).map_err(|error| error.with_path(file_path))
+}
+
+/// Parses the text of a scene file. Included files are found relative to the
+/// current directory.
+pub fn parse_scene(text: &str) -> Result<SceneDescription> {
+    let nodes = parse_nodes(text)?;
+    let (nodes, _) = expand_nodes(nodes, Path::new(""), &Arguments::new(), &mut Vec::new())?;
+
+    scene_from_nodes(nodes)
+}
+
+/// Parses a material written as it would be after `material` in a scene file,
+/// for example `metal 0.8 0.8 0.8 0.1` or the name of a material in the
+/// scene's library.
+pub fn parse_material_text(text: &str) -> Result<MaterialDescription> {
+    let nodes = parse_nodes(&format!("material {text}"))?;
+    match nodes.as_slice() {
+        [node] => parse_material(&Item::from_node(node).typed()?),
+        _ => Err(Error::parse(1, "expected a single material")),
+    }
+}
+
+fn scene_from_nodes(nodes: Vec<Node>) -> Result<SceneDescription> {
+    let mut scene = SceneDescription::default();
+    let mut has_resolution = false;
+    let mut has_background = false;
+    let mut has_shutter = false;
+    let mut has_material_override = false;
+
+    let mut definitions = Vec::new();
+    let nodes = take_definitions(nodes, true, &mut definitions);
+    for node in &definitions {
+        parse_definitions(&Item::from_node(node), &mut scene)?;
+    }
+
+    for node in nodes.iter() {
+        let item = Item::from_node(node);
+        match node.name.as_str() {
+            "resolution" => {
+                item.expect_unique(&mut has_resolution)?;
+                scene.resolution = Some(parse_resolution(&item)?);
+            }
+            "background" => {
+                item.expect_unique(&mut has_background)?;
+                scene.background = parse_background(&item)?;
+            }
+            "shutter" => {
+                item.expect_unique(&mut has_shutter)?;
+                let times = item.numbers(2)?;
+                scene.time0 = times[0];
+                scene.time1 = times[1];
+            }
+            "material_override" => {
+                item.expect_unique(&mut has_material_override)?;
+                scene.material_override = Some(parse_material(&item.typed()?)?);
+            }
+            "camera" => scene.cameras.push(parse_camera(&item)?),
+            _ => scene.objects.push(parse_object(&item)?),
+        }
+    }
+
+    Ok(scene)
+}
+
+/// Moves the `materials` and `textures` blocks out of the statements into
+/// `definitions`. Blocks are taken from the top level and from inside nodes,
+/// which is where the definitions of included files end up. A node left empty
+/// held a file that only defines materials, so it is removed.
+fn take_definitions(nodes: Vec<Node>, top_level: bool, definitions: &mut Vec<Node>) -> Vec<Node> {
+    let mut statements = Vec::new();
+    for mut node in nodes {
+        if top_level && ["materials", "textures"].contains(&node.name.as_str()) {
+            definitions.push(node);
+            continue;
+        }
+        let had_children = !node.children.is_empty();
+        node.children = take_definitions(node.children, node.name == "node", definitions);
+        if node.name == "node" && had_children && node.children.is_empty() {
+            continue;
+        }
+        statements.push(node);
+    }
+    statements
+}
+
+/// Parses a `materials { NAME MATERIAL.. }` or `textures { NAME TEXTURE.. }`
+/// block into the scene's library. A name may be defined more than once, for
+/// example by a file that is included twice, as long as the definitions are
+/// the same.
+fn parse_definitions(item: &Item, scene: &mut SceneDescription) -> Result<()> {
+    item.expect_no_args()?;
+    for child in item.children.iter().map(Item::from_node) {
+        let name = child.kind;
+        let is_material = item.kind == "materials";
+        let kinds = if is_material {
+            MATERIAL_KINDS
+        } else {
+            TEXTURE_KINDS
+        };
+        if kinds.contains(&name) {
+            return Err(child.error(format!(
+                "'{name}' is a {} type so can not be used as a name",
+                &item.kind[..item.kind.len() - 1]
+            )));
+        }
+        let redefined = if is_material {
+            let material = parse_material(&child.typed()?)?;
+            scene
+                .materials
+                .insert(name.to_string(), material.clone())
+                .is_some_and(|previous| previous != material)
+        } else {
+            let texture = parse_texture(&child.typed()?)?;
+            scene
+                .textures
+                .insert(name.to_string(), texture.clone())
+                .is_some_and(|previous| previous != texture)
+        };
+        if redefined {
+            return Err(child.error(format!(
+                "{} '{name}' is already defined differently",
+                &item.kind[..item.kind.len() - 1]
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Values of the parameters set by an `include`, by parameter name.
+type Arguments = HashMap<String, Vec<Value>>;
+
+/// Reads a scene file and expands its parameters and includes, see
+/// `expand_nodes`. `including` holds the files that are including this one so
+/// include cycles can be found.
+fn load_nodes(
+    file_path: &Path,
+    arguments: &Arguments,
+    including: &mut Vec<PathBuf>,
+) -> Result<(Vec<Node>, HashSet<String>)> {
+    let text = fs::read_to_string(file_path).map_err(|source| Error::Io {
+        path: file_path.to_path_buf(),
+        source,
+    })?;
+
+    including.push(fs::canonicalize(file_path).unwrap_or_else(|_| file_path.to_path_buf()));
+    let folder = file_path.parent().unwrap_or(Path::new(""));
+    let result =
+        parse_nodes(&text).and_then(|nodes| expand_nodes(nodes, folder, arguments, including));
+    including.pop();
+
+    result.map_err(|error| error.with_path(file_path))
+}
+
+/// Reads the `param` declarations at the top level of a file, replaces `$name`
+/// values with the values of the parameters, and replaces `include`
+/// statements with nodes holding the objects of the included files. Returns
+/// the statements and the names of the parameters declared.
+fn expand_nodes(
+    nodes: Vec<Node>,
+    folder: &Path,
+    arguments: &Arguments,
+    including: &mut Vec<PathBuf>,
+) -> Result<(Vec<Node>, HashSet<String>)> {
+    let mut parameters = Arguments::new();
+    let mut statements = Vec::new();
+    for node in nodes {
+        if node.name != "param" {
+            statements.push(node);
+            continue;
+        }
+        let (name, default) = name_and_values(&node)?;
+        let value = arguments
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| default.to_vec());
+        if parameters.insert(name.clone(), value).is_some() {
+            return Err(Error::parse(
+                node.line,
+                format!("parameter '{name}' is declared more than once"),
+            ));
+        }
+    }
+
+    let nodes = statements
+        .into_iter()
+        .map(|node| expand_node(node, &parameters, folder, including))
+        .collect::<Result<_>>()?;
+    Ok((nodes, parameters.into_keys().collect()))
+}
+
+/// Returns the name and values of a `param NAME VALUE..` or
+/// `set NAME VALUE..` statement.
+fn name_and_values(node: &Node) -> Result<(&String, &[Value])> {
+    match node.args.split_first() {
+        Some((Value::Word(name), values)) if !values.is_empty() && node.children.is_empty() => {
+            Ok((name, values))
+        }
+        _ => Err(Error::parse(
+            node.line,
+            format!("expected '{} NAME VALUE..'", node.name),
+        )),
+    }
+}
+
+fn expand_node(
+    mut node: Node,
+    parameters: &Arguments,
+    folder: &Path,
+    including: &mut Vec<PathBuf>,
+) -> Result<Node> {
+    let mut args = Vec::new();
+    for arg in node.args {
+        match arg {
+            Value::Word(word) if word.starts_with('$') => match parameters.get(&word[1..]) {
+                Some(values) => args.extend(values.iter().cloned()),
+                None => {
+                    return Err(Error::parse(
+                        node.line,
+                        format!("unknown parameter '{word}'"),
+                    ));
+                }
+            },
+            arg => args.push(arg),
+        }
+    }
+    node.args = args;
+    node.children = node
+        .children
+        .into_iter()
+        .map(|child| expand_node(child, parameters, folder, including))
+        .collect::<Result<_>>()?;
+
+    match node.name.as_str() {
+        "include" => expand_include(node, folder, including),
+        _ => Ok(node),
+    }
+}
+
+/// Expands `include "FILE" { .. }` into a node holding the objects in the
+/// file. The block may set the properties of the node, and set the
+/// parameters of the included file with `set NAME VALUE..`. Only objects and
+/// material and texture definitions are included, the settings and cameras of
+/// the included file are left out.
+fn expand_include(node: Node, folder: &Path, including: &mut Vec<PathBuf>) -> Result<Node> {
+    let file_path = match node.args.as_slice() {
+        [Value::Str(file)] => folder.join(file),
+        _ => {
+            return Err(Error::parse(
+                node.line,
+                "'include' expects a single quoted file name",
+            ));
+        }
+    };
+
+    let mut properties = Vec::new();
+    let mut arguments = Arguments::new();
+    let mut set_lines = Vec::new();
+    for child in node.children {
+        match child.name.as_str() {
+            "name" | "translate" | "rotate" | "scale" | "material" => properties.push(child),
+            "set" => {
+                let (name, value) = name_and_values(&child)?;
+                if arguments.insert(name.clone(), value.to_vec()).is_some() {
+                    return Err(Error::parse(
+                        child.line,
+                        format!("parameter '{name}' is set more than once"),
+                    ));
+                }
+                set_lines.push((name.clone(), child.line));
+            }
+            other => {
+                return Err(Error::parse(
+                    child.line,
+                    format!(
+                        "unknown property '{other}' for 'include', expected 'name', 'translate', 'rotate', 'scale', 'material' or 'set'"
+                    ),
+                ));
+            }
+        }
+    }
+
+    let canonical_path = fs::canonicalize(&file_path).unwrap_or_else(|_| file_path.clone());
+    if including.contains(&canonical_path) {
+        return Err(Error::parse(
+            node.line,
+            format!("'{}' includes itself", file_path.display()),
+        ));
+    }
+    let (nodes, parameters) = load_nodes(&file_path, &arguments, including)?;
+    for (name, line) in set_lines {
+        if !parameters.contains(&name) {
+            return Err(Error::parse(
+                line,
+                format!("'{}' has no parameter '{name}'", file_path.display()),
+            ));
+        }
+    }
+    // Check the included objects here so problems with them are reported
+    // against the included file
+    scene_from_nodes(nodes.clone()).map_err(|error| error.with_path(&file_path))?;
+
+    let (definitions, objects): (Vec<Node>, Vec<Node>) = nodes
+        .into_iter()
+        .filter(|node| {
+            ![
+                "resolution",
+                "background",
+                "shutter",
+                "camera",
+                "material_override",
+            ]
+            .contains(&node.name.as_str())
+        })
+        .partition(|node| ["materials", "textures"].contains(&node.name.as_str()));
+    if objects.is_empty() && definitions.is_empty() {
+        return Err(Error::parse(
+            node.line,
+            format!(
+                "'{}' does not contain any objects or definitions",
+                file_path.display()
+            ),
+        ));
+    }
+    // A file of definitions is only a library, there is nothing to place
+    if objects.is_empty() {
+        properties.clear();
+    }
+    properties.extend(definitions);
+    properties.extend(objects);
+
+    Ok(Node {
+        name: "node".to_string(),
+        args: Vec::new(),
+        children: properties,
+        line: node.line,
+    })
+}
+
+fn parse_resolution(item: &Item) -> Result<Resolution> {
+    item.expect_no_args()?;
+    let properties = item.properties(&["width", "height", "samples", "max_depth"])?;
+
+    Ok(Resolution::new(
+        properties.required("width")?.integer()?,
+        properties.required("height")?.integer()?,
+        properties.required("samples")?.integer()?,
+        properties.required("max_depth")?.integer()?,
+    ))
+}
+
+/// A background is `sky`, `black` or a colour.
+fn parse_background(item: &Item) -> Result<Background> {
+    match item.args {
+        [Value::Word(word)] if word == "sky" => Ok(Background::Sky),
+        [Value::Word(word)] if word == "black" => Ok(Background::BLACK),
+        [Value::Word(other)] => Err(item.error(format!(
+            "unknown background '{other}', expected 'sky', 'black' or a colour"
+        ))),
+        _ => Ok(Background::Colour(item.colour()?)),
+    }
+}
+
+fn parse_camera(item: &Item) -> Result<CameraDescription> {
+    item.expect_no_args()?;
+    let properties = item.properties(&[
+        "look_from",
+        "look_at",
+        "view_up",
+        "fov",
+        "aperture",
+        "focus_distance",
+    ])?;
+
+    let mut camera = CameraDescription::new(
+        properties.required("look_from")?.vec3()?,
+        properties.required("look_at")?.vec3()?,
+        properties.required("fov")?.number()?,
+    );
+    if let Some(view_up) = properties.optional("view_up") {
+        camera.view_up = view_up.vec3()?;
+    }
+    if let Some(aperture) = properties.optional("aperture") {
+        camera.aperture = aperture.number()?;
+    }
+    if let Some(focus_distance) = properties.optional("focus_distance") {
+        camera.focus_distance = focus_distance.number()?;
+    }
+
+    Ok(camera)
+}
+
+fn parse_object(item: &Item) -> Result<ObjectDescription> {
+    let object = match item.kind {
+        "sphere" => {
+            item.expect_no_args()?;
+            let properties = item.properties(&["center", "radius", "material"])?;
+            ObjectDescription::Sphere {
+                center: properties.required("center")?.vec3()?,
+                radius: properties.required("radius")?.number()?,
+                material: properties.material()?,
+            }
+        }
+        "moving_sphere" => {
+            item.expect_no_args()?;
+            let properties =
+                item.properties(&["center0", "center1", "time0", "time1", "radius", "material"])?;
+            ObjectDescription::MovingSphere {
+                center0: properties.required("center0")?.vec3()?,
+                center1: properties.required("center1")?.vec3()?,
+                time0: properties.required("time0")?.number()?,
+                time1: properties.required("time1")?.number()?,
+                radius: properties.required("radius")?.number()?,
+                material: properties.material()?,
+            }
+        }
+        "rectangle_xy" => {
+            item.expect_no_args()?;
+            let properties = item.properties(&["x", "y", "z", "material"])?;
+            let x = properties.required("x")?.numbers(2)?;
+            let y = properties.required("y")?.numbers(2)?;
+            ObjectDescription::RectangleXY {
+                x0: x[0],
+                x1: x[1],
+                y0: y[0],
+                y1: y[1],
+                k: properties.required("z")?.number()?,
+                material: properties.material()?,
+            }
+        }
+        "rectangle_xz" => {
+            item.expect_no_args()?;
+            let properties = item.properties(&["x", "y", "z", "material"])?;
+            let x = properties.required("x")?.numbers(2)?;
+            let z = properties.required("z")?.numbers(2)?;
+            ObjectDescription::RectangleXZ {
+                x0: x[0],
+                x1: x[1],
+                z0: z[0],
+                z1: z[1],
+                k: properties.required("y")?.number()?,
+                material: properties.material()?,
+            }
+        }
+        "rectangle_yz" => {
+            item.expect_no_args()?;
+            let properties = item.properties(&["x", "y", "z", "material"])?;
+            let y = properties.required("y")?.numbers(2)?;
+            let z = properties.required("z")?.numbers(2)?;
+            ObjectDescription::RectangleYZ {
+                y0: y[0],
+                y1: y[1],
+                z0: z[0],
+                z1: z[1],
+                k: properties.required("x")?.number()?,
+                material: properties.material()?,
+            }
+        }
+        "box" => {
+            item.expect_no_args()?;
+            let properties = item.properties(&["min", "max", "material"])?;
+            ObjectDescription::Box {
+                min: properties.required("min")?.vec3()?,
+                max: properties.required("max")?.vec3()?,
+                material: properties.material()?,
+            }
+        }
+        "triangle" => {
+            item.expect_no_args()?;
+            let properties = item.properties(&["a", "b", "c", "material"])?;
+            ObjectDescription::Triangle {
+                a: properties.required("a")?.vec3()?,
+                b: properties.required("b")?.vec3()?,
+                c: properties.required("c")?.vec3()?,
+                material: properties.material()?,
+            }
+        }
+        "pyramid" => {
+            item.expect_no_args()?;
+            let properties = item.properties(&["base", "height", "material"])?;
+            let base = properties.required("base")?.numbers(9)?;
+            ObjectDescription::Pyramid {
+                base: (
+                    Point3d::new(base[0], base[1], base[2]),
+                    Point3d::new(base[3], base[4], base[5]),
+                    Point3d::new(base[6], base[7], base[8]),
+                ),
+                height: properties.required("height")?.number()?,
+                material: properties.material()?,
+            }
+        }
+        "model" => {
+            item.expect_no_args()?;
+            let properties = item.properties(&["file", "material"])?;
+            ObjectDescription::Model {
+                file: properties.required("file")?.string()?.to_string(),
+                material: properties.material()?,
+            }
+        }
+        "group" => {
+            item.expect_no_args()?;
+            ObjectDescription::Group(parse_children(item)?)
+        }
+        "translate" => {
+            let offset = item.values(3)?;
+            ObjectDescription::Translate {
+                offset: Vec3d::new(offset[0], offset[1], offset[2]),
+                object: Box::new(parse_child(item)?),
+            }
+        }
+        "rotate_x" => ObjectDescription::RotateX {
+            angle: item.values(1)?[0],
+            object: Box::new(parse_child(item)?),
+        },
+        "rotate_y" => ObjectDescription::RotateY {
+            angle: item.values(1)?[0],
+            object: Box::new(parse_child(item)?),
+        },
+        "rotate_z" => ObjectDescription::RotateZ {
+            angle: item.values(1)?[0],
+            object: Box::new(parse_child(item)?),
+        },
+        "scale" => {
+            let factors = scale_factors(item)?;
+            ObjectDescription::Scale {
+                x: factors.x,
+                y: factors.y,
+                z: factors.z,
+                object: Box::new(parse_child(item)?),
+            }
+        }
+        "node" => {
+            item.expect_no_args()?;
+            ObjectDescription::Node(parse_node(item)?)
+        }
+        "constant_medium" => {
+            item.expect_no_args()?;
+            let properties = item.properties(&["boundary", "texture", "density"])?;
+            let boundary = properties.required("boundary")?.typed()?;
+            ObjectDescription::ConstantMedium {
+                boundary: Box::new(parse_object(&boundary)?),
+                texture: parse_texture(&properties.required("texture")?.typed()?)?,
+                density: properties.required("density")?.number()?,
+            }
+        }
+        other => return Err(item.error(format!("unknown object '{other}'"))),
+    };
+
+    Ok(object)
+}
+
+/// A single value scales all axes equally.
+fn scale_factors(item: &Item) -> Result<Vec3d> {
+    let factors = match item.args.len() {
+        1 => vec![item.number_at(0)?; 3],
+        _ => item.values(3)?,
+    };
+    Ok(Vec3d::new(factors[0], factors[1], factors[2]))
+}
+
+/// Parses a scene graph node. The statements in a node are either properties
+/// of the node or objects in it. `translate` and `scale` are properties unless
+/// they have a block, in which case they are instances.
+fn parse_node(item: &Item) -> Result<SceneNode> {
+    let mut node = SceneNode::default();
+    let mut seen = HashSet::new();
+    for child in item.children.iter().map(Item::from_node) {
+        let is_property = match child.kind {
+            "name" | "material" | "rotate" => true,
+            "translate" | "scale" => child.children.is_empty(),
+            _ => false,
+        };
+        if !is_property {
+            node.children.push(parse_object(&child)?);
+            continue;
+        }
+        if !seen.insert(child.kind) {
+            return Err(child.error(format!("'{}' is set more than once", child.kind)));
+        }
+        match child.kind {
+            "name" => node.name = Some(child.string()?.to_string()),
+            "material" => node.material = Some(parse_material(&child.typed()?)?),
+            "translate" => node.transform.translation = child.vec3()?,
+            "rotate" => node.transform.rotation = child.vec3()?,
+            _ => node.transform.scale = scale_factors(&child)?,
+        }
+    }
+
+    if node.children.is_empty() {
+        return Err(item.error("'node' must contain at least one object"));
+    }
+    Ok(node)
+}
+
+/// Parses the children of an instance, multiple children are put into a
+/// group.
+fn parse_child(item: &Item) -> Result<ObjectDescription> {
+    let mut children = parse_children(item)?;
+    match children.len() {
+        1 => Ok(children.pop().unwrap()),
+        _ => Ok(ObjectDescription::Group(children)),
+    }
+}
+
+fn parse_children(item: &Item) -> Result<Vec<ObjectDescription>> {
+    if item.children.is_empty() {
+        return Err(item.error(format!("'{}' must contain at least one object", item.kind)));
+    }
+    item.children
+        .iter()
+        .map(|node| parse_object(&Item::from_node(node)))
+        .collect()
+}
+
+/// Material types, these can not be used as the names of library materials.
+const MATERIAL_KINDS: &[&str] = &[
+    "diffuse",
+    "lambertian",
+    "metal",
+    "dielectric",
+    "diffuse_light",
+    "isotropic",
+];
+
+/// Texture types, these can not be used as the names of library textures.
+const TEXTURE_KINDS: &[&str] = &["solid", "checker", "image", "noise", "turbulence"];
+
+/// Parses a material, a name on its own refers to a material in the scene's
+/// library.
+fn parse_material(item: &Item) -> Result<MaterialDescription> {
+    let material = match item.kind {
+        "diffuse" => MaterialDescription::Diffuse(item.colour()?),
+        "lambertian" => MaterialDescription::Lambertian(parse_texture_or_colour(item)?),
+        "metal" => {
+            item.expect_no_children()?;
+            let (albedo, used) = parse_colour(item, 0)?;
+            if item.args.len() != used + 1 {
+                return Err(item.error("expected a colour followed by a fuzz value"));
+            }
+            MaterialDescription::Metal {
+                albedo,
+                fuzz: item.number_at(used)?,
+            }
+        }
+        "dielectric" => MaterialDescription::Dielectric {
+            refraction_index: item.number()?,
+        },
+        "diffuse_light" => MaterialDescription::DiffuseLight(parse_texture_or_colour(item)?),
+        "isotropic" => MaterialDescription::Isotropic(parse_texture_or_colour(item)?),
+        name if item.args.is_empty() && item.children.is_empty() => {
+            MaterialDescription::Named(name.to_string())
+        }
+        other => return Err(item.error(format!("unknown material '{other}'"))),
+    };
+
+    Ok(material)
+}
+
+/// Materials that take a texture can also be given a colour as a shorthand for
+/// a solid colour texture.
+fn parse_texture_or_colour(item: &Item) -> Result<TextureDescription> {
+    if item.args.is_empty() {
+        let properties = item.properties(&["texture"])?;
+        parse_texture(&properties.required("texture")?.typed()?)
+    } else {
+        Ok(TextureDescription::Solid(item.colour()?))
+    }
+}
+
+/// Parses a texture, a name on its own refers to a texture in the scene's
+/// library.
+fn parse_texture(item: &Item) -> Result<TextureDescription> {
+    let texture = match item.kind {
+        "solid" => TextureDescription::Solid(item.colour()?),
+        "checker" => {
+            item.expect_no_args()?;
+            let properties = item.properties(&["odd", "even"])?;
+            TextureDescription::Checker {
+                odd: Box::new(parse_texture(&properties.required("odd")?.typed()?)?),
+                even: Box::new(parse_texture(&properties.required("even")?.typed()?)?),
+            }
+        }
+        "image" => TextureDescription::Image {
+            file: item.string()?.to_string(),
+        },
+        "noise" => {
+            let (scale, seed) = parse_noise(item)?;
+            TextureDescription::Noise { scale, seed }
+        }
+        "turbulence" => {
+            let (scale, seed) = parse_noise(item)?;
+            TextureDescription::Turbulence { scale, seed }
+        }
+        name if item.args.is_empty() && item.children.is_empty() => {
+            TextureDescription::Named(name.to_string())
+        }
+        other => return Err(item.error(format!("unknown texture '{other}'"))),
+    };
+
+    Ok(texture)
+}
+
+/// Noise takes a scale and an optional seed.
+fn parse_noise(item: &Item) -> Result<(f64, Option<u64>)> {
+    item.expect_no_children()?;
+    let scale = item.number_at(0)?;
+    let seed = match item.args.len() {
+        1 => None,
+        2 => {
+            let seed = item.number_at(1)?;
+            if seed < 0.0 || seed.fract() != 0.0 {
+                return Err(item.error(format!("seed must be a whole number, found {seed}")));
+            }
+            Some(seed as u64)
+        }
+        _ => return Err(item.error(format!("'{}' expects a scale and a seed", item.kind))),
+    };
+    Ok((scale, seed))
+}
+
+/// Parses a colour starting at the argument `start`. A colour is either three
+/// numbers or a hash string like "#ffd700". Returns the colour and the number of
+/// arguments used.
+fn parse_colour(item: &Item, start: usize) -> Result<(RGB, usize)> {
+    match item.args.get(start) {
+        Some(Value::Str(hash)) => RGB::from_hash(hash)
+            .map(|colour| (colour, 1))
+            .map_err(|error| item.error(error.to_string())),
+        _ => Ok((
+            RGB(
+                item.number_at(start)?,
+                item.number_at(start + 1)?,
+                item.number_at(start + 2)?,
+            ),
+            3,
+        )),
+    }
+}
+
+/// A single value on a line of a scene file.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(f64),
+    Word(String),
+    Str(String),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(number) => write!(f, "{number}"),
+            Value::Word(word) => write!(f, "{word}"),
+            Value::Str(string) => write!(f, "\"{string}\""),
+        }
+    }
+}
+
+/// A statement in a scene file; a name followed by some values and an optional
+/// block of child statements.
+#[derive(Debug, Clone, PartialEq)]
+struct Node {
+    name: String,
+    args: Vec<Value>,
+    children: Vec<Node>,
+    line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Open,
+    Close,
+    /// End of a statement, either a new line or a semicolon.
+    End,
+}
+
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => {
+                tokens.push((Token::End, line));
+                line += 1;
+            }
+            ';' => tokens.push((Token::End, line)),
+            '{' => tokens.push((Token::Open, line)),
+            '}' => tokens.push((Token::Close, line)),
+            '#' => {
+                // Comment, skip to the end of the line
+                while chars.peek().is_some_and(|c| *c != '\n') {
+                    chars.next();
+                }
+            }
+            '"' => {
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        // Only quotes and backslashes are escaped, other
+                        // backslashes are kept, e.g. in Windows paths
+                        Some('\\') if chars.peek().is_some_and(|c| matches!(c, '"' | '\\')) => {
+                            string.extend(chars.next());
+                        }
+                        Some('\n') | None => {
+                            return Err(Error::parse(line, "unterminated string"));
+                        }
+                        Some(c) => string.push(c),
+                    }
+                }
+                tokens.push((Token::Str(string), line));
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut word = String::from(c);
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "{};#\"".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push((Token::Word(word), line));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn parse_nodes(text: &str) -> Result<Vec<Node>> {
+    let tokens = tokenize(text)?;
+    let mut position = 0;
+    parse_block(&tokens, &mut position, None)
+}
+
+/// Parses statements until the end of the block. `open_line` is the line of
+/// the opening brace, or `None` at the top level of the file.
+fn parse_block(
+    tokens: &[(Token, usize)],
+    position: &mut usize,
+    open_line: Option<usize>,
+) -> Result<Vec<Node>> {
+    let mut nodes = Vec::new();
+
+    loop {
+        let Some((token, line)) = tokens.get(*position) else {
+            return match open_line {
+                Some(open_line) => Err(Error::parse(open_line, "unclosed '{'")),
+                None => Ok(nodes),
+            };
+        };
+        *position += 1;
+
+        match token {
+            Token::End => {}
+            Token::Close => {
+                return match open_line {
+                    Some(_) => Ok(nodes),
+                    None => Err(Error::parse(*line, "unexpected '}'")),
+                };
+            }
+            Token::Open => return Err(Error::parse(*line, "expected a name before '{'")),
+            Token::Str(string) => {
+                return Err(Error::parse(
+                    *line,
+                    format!("expected a name, found \"{string}\""),
+                ));
+            }
+            Token::Word(name) => {
+                let mut node = Node {
+                    name: name.clone(),
+                    args: Vec::new(),
+                    children: Vec::new(),
+                    line: *line,
+                };
+                while let Some((token, line)) = tokens.get(*position) {
+                    match token {
+                        Token::Word(word) => node.args.push(parse_word(word)),
+                        Token::Str(string) => node.args.push(Value::Str(string.clone())),
+                        Token::Open => {
+                            *position += 1;
+                            node.children = parse_block(tokens, position, Some(*line))?;
+                            break;
+                        }
+                        Token::Close | Token::End => break,
+                    }
+                    *position += 1;
+                }
+                nodes.push(node);
+            }
+        }
+    }
+}
+
+fn parse_word(word: &str) -> Value {
+    let looks_like_number = word
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_digit() || "+-.".contains(c));
+    match word.parse::<f64>() {
+        Ok(number) if looks_like_number => Value::Number(number),
+        _ => Value::Word(word.to_string()),
+    }
+}
+
+/// A view of a statement as a typed item. For most statements the kind of the
+/// item is the name of the statement, for example `sphere { .. }`. For
+/// properties that hold an item, such as `material metal 0.8 0.8 0.8 0.1`, the
+/// kind is the first value.
+struct Item<'a> {
+    kind: &'a str,
+    args: &'a [Value],
+    children: &'a [Node],
+    line: usize,
+}
+
+impl<'a> Item<'a> {
+    fn from_node(node: &'a Node) -> Self {
+        Self {
+            kind: &node.name,
+            args: &node.args,
+            children: &node.children,
+            line: node.line,
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> Error {
+        Error::parse(self.line, message)
+    }
+
+    /// Converts a property holding an item to that item.
+    fn typed(&self) -> Result<Item<'a>> {
+        match self.args.first() {
+            Some(Value::Word(kind)) => Ok(Item {
+                kind,
+                args: &self.args[1..],
+                children: self.children,
+                line: self.line,
+            }),
+            _ => Err(self.error(format!("expected a type after '{}'", self.kind))),
+        }
+    }
+
+    fn expect_unique(&self, seen: &mut bool) -> Result<()> {
+        if *seen {
+            return Err(self.error(format!("'{}' is set more than once", self.kind)));
+        }
+        *seen = true;
+        Ok(())
+    }
+
+    fn expect_no_args(&self) -> Result<()> {
+        match self.args.first() {
+            Some(arg) => Err(self.error(format!("unexpected value {arg} after '{}'", self.kind))),
+            None => Ok(()),
+        }
+    }
+
+    fn expect_no_children(&self) -> Result<()> {
+        match self.children.first() {
+            Some(child) => Err(Error::parse(
+                child.line,
+                format!("'{}' does not take a block", self.kind),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    fn number_at(&self, index: usize) -> Result<f64> {
+        match self.args.get(index) {
+            Some(Value::Number(number)) => Ok(*number),
+            Some(other) => Err(self.error(format!(
+                "expected a number after '{}', found {other}",
+                self.kind
+            ))),
+            None => Err(self.error(format!("missing value after '{}'", self.kind))),
+        }
+    }
+
+    /// Returns exactly `count` numbers.
+    fn numbers(&self, count: usize) -> Result<Vec<f64>> {
+        self.expect_no_children()?;
+        self.values(count)
+    }
+
+    /// Returns exactly `count` numbers, the item may also have a block.
+    fn values(&self, count: usize) -> Result<Vec<f64>> {
+        if self.args.len() > count {
+            return Err(self.error(format!(
+                "'{}' expects {count} values, found {}",
+                self.kind,
+                self.args.len()
+            )));
+        }
+        (0..count).map(|i| self.number_at(i)).collect()
+    }
+
+    fn number(&self) -> Result<f64> {
+        Ok(self.numbers(1)?[0])
+    }
+
+    fn integer(&self) -> Result<usize> {
+        let number = self.number()?;
+        if number < 0.0 || number.fract() != 0.0 {
+            return Err(self.error(format!(
+                "'{}' must be a whole number, found {number}",
+                self.kind
+            )));
+        }
+        Ok(number as usize)
+    }
+
+    fn vec3(&self) -> Result<Vec3d> {
+        let numbers = self.numbers(3)?;
+        Ok(Vec3d::new(numbers[0], numbers[1], numbers[2]))
+    }
+
+    fn colour(&self) -> Result<RGB> {
+        self.expect_no_children()?;
+        let (colour, used) = parse_colour(self, 0)?;
+        if self.args.len() != used {
+            return Err(self.error(format!("'{}' expects a single colour", self.kind)));
+        }
+        Ok(colour)
+    }
+
+    fn string(&self) -> Result<&'a str> {
+        self.expect_no_children()?;
+        match self.args {
+            [Value::Str(string)] => Ok(string),
+            _ => Err(self.error(format!("'{}' expects a single quoted string", self.kind))),
+        }
+    }
+
+    /// Returns the child statements of the item as named properties, only the
+    /// given names are allowed and each may only appear once.
+    fn properties(&self, allowed: &[&str]) -> Result<Properties<'a>> {
+        let mut items = HashMap::new();
+        for node in self.children {
+            if !allowed.contains(&node.name.as_str()) {
+                return Err(Error::parse(
+                    node.line,
+                    format!(
+                        "unknown property '{}' for '{}', expected one of: {}",
+                        node.name,
+                        self.kind,
+                        allowed.join(", ")
+                    ),
+                ));
+            }
+            if items.insert(node.name.as_str(), node).is_some() {
+                return Err(Error::parse(
+                    node.line,
+                    format!("'{}' is set more than once", node.name),
+                ));
+            }
+        }
+
+        Ok(Properties {
+            kind: self.kind,
+            line: self.line,
+            items,
+        })
+    }
+}
+
+struct Properties<'a> {
+    kind: &'a str,
+    line: usize,
+    items: HashMap<&'a str, &'a Node>,
+}
+
+impl<'a> Properties<'a> {
+    fn optional(&self, name: &str) -> Option<Item<'a>> {
+        self.items.get(name).map(|node| Item::from_node(node))
+    }
+
+    fn required(&self, name: &str) -> Result<Item<'a>> {
+        self.optional(name).ok_or_else(|| {
+            Error::parse(
+                self.line,
+                format!("'{}' is missing property '{name}'", self.kind),
+            )
+        })
+    }
+
+    fn material(&self) -> Result<MaterialDescription> {
+        parse_material(&self.required("material")?.typed()?)
+    }
+}
+
+#[cfg(test)]
+mod parser_tests {
+    use super::*;
+
+    const CORNELL_BOX: &str = "
+        # A small Cornell box
+        resolution {
+            width 400; height 400
+            samples 10; max_depth 5
+        }
+        background black
+
+        camera {
+            look_from 278 278 -800
+            look_at 278 278 0
+            fov 40
+        }
+
+        rectangle_yz { y 0 555; z 0 555; x 555; material lambertian 0.12 0.45 0.15 }
+        rectangle_xz {
+            x 213 343
+            z 227 332
+            y 554
+            material diffuse_light 15 15 15
+        }
+        translate 265 0 295 {
+            rotate_y 15 {
+                box { min 0 0 0; max 165 330 165; material metal \"#ffd700\" 0.8 }
+            }
+        }
+    ";
+
+    #[test]
+    fn parse_scene_should_read_settings_cameras_and_objects() {
+        let scene = parse_scene(CORNELL_BOX).unwrap();
+
+        assert_eq!(scene.resolution, Some(Resolution::new(400, 400, 10, 5)));
+        assert_eq!(scene.background, Background::BLACK);
+        assert_eq!(scene.cameras.len(), 1);
+        assert_eq!(
+            scene.cameras[0].look_from,
+            Point3d::new(278.0, 278.0, -800.0)
+        );
+        assert_eq!(scene.cameras[0].view_up, Vec3d::new(0.0, 1.0, 0.0));
+        assert_eq!(scene.objects.len(), 3);
+        assert_eq!(
+            scene.objects[1],
+            ObjectDescription::RectangleXZ {
+                x0: 213.0,
+                x1: 343.0,
+                z0: 227.0,
+                z1: 332.0,
+                k: 554.0,
+                material: MaterialDescription::DiffuseLight(TextureDescription::Solid(RGB(
+                    15.0, 15.0, 15.0
+                ))),
+            }
+        );
+        assert_eq!(
+            scene.objects[2],
+            ObjectDescription::Translate {
+                offset: Vec3d::new(265.0, 0.0, 295.0),
+                object: Box::new(ObjectDescription::RotateY {
+                    angle: 15.0,
+                    object: Box::new(ObjectDescription::Box {
+                        min: Point3d::new(0.0, 0.0, 0.0),
+                        max: Point3d::new(165.0, 330.0, 165.0),
+                        material: MaterialDescription::Metal {
+                            albedo: RGB::from_hash("#ffd700").unwrap(),
+                            fuzz: 0.8,
+                        },
+                    }),
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn load_scene_file_should_load_and_build_example_scenes() {
+        for name in [
+            "basic",
+            "simple_light",
+            "cornell_box",
+            "cornell_box_with_smoke_boxes",
+        ] {
+            let file_path = format!("{}/scenes/{name}.scene", env!("CARGO_MANIFEST_DIR"));
+            let renderer = load_scene_file(&file_path)
+                .and_then(|description| description.renderer()?.build())
+                .unwrap_or_else(|error| panic!("{error}"));
+
+            assert_eq!(renderer.num_cameras(), 1);
+        }
+    }
+
+    #[test]
+    fn parse_scene_should_put_multiple_instance_children_in_a_group() {
+        let scene = parse_scene(
+            "scale 2 {
+                sphere { center 0 0 0; radius 1; material dielectric 1.5 }
+                sphere { center 0 2 0; radius 1; material dielectric 1.5 }
+            }",
+        )
+        .unwrap();
+
+        match &scene.objects[0] {
+            ObjectDescription::Scale { x, object, .. } => {
+                assert_eq!(*x, 2.0);
+                assert!(matches!(**object, ObjectDescription::Group(ref g) if g.len() == 2));
+            }
+            other => panic!("expected a scale, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_scene_should_parse_node_properties_and_children() {
+        let scene = parse_scene(
+            "node {
+                name \"table\"
+                translate 1 2 3
+                rotate 0 90 0
+                material diffuse 0.5 0.5 0.5
+                box { min 0 0 0; max 1 1 1; material dielectric 1.5 }
+                translate 0 1 0 {
+                    sphere { center 0 0 0; radius 1; material dielectric 1.5 }
+                }
+            }",
+        )
+        .unwrap();
+
+        match &scene.objects[0] {
+            ObjectDescription::Node(node) => {
+                assert_eq!(node.name.as_deref(), Some("table"));
+                assert_eq!(node.transform.translation, Vec3d::new(1.0, 2.0, 3.0));
+                assert_eq!(node.transform.rotation, Vec3d::new(0.0, 90.0, 0.0));
+                assert_eq!(node.transform.scale, Vec3d::new(1.0, 1.0, 1.0));
+                assert!(node.material.is_some());
+                assert!(matches!(node.children[0], ObjectDescription::Box { .. }));
+                assert!(matches!(
+                    node.children[1],
+                    ObjectDescription::Translate { .. }
+                ));
+            }
+            other => panic!("expected a node, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_scene_should_report_line_of_unknown_property() {
+        let result = parse_scene("sphere {\n    center 0 0 0\n    radios 1\n}");
+
+        match result {
+            Err(Error::Parse { line, message, .. }) => {
+                assert_eq!(line, 3);
+                assert!(message.contains("radios"), "{message}");
+            }
+            other => panic!("expected a parse error, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_scene_should_report_missing_property() {
+        let result = parse_scene("sphere { center 0 0 0; radius 1 }");
+
+        assert!(matches!(result, Err(Error::Parse { line: 1, .. })));
+    }
+
+    #[test]
+    fn parse_scene_should_report_unclosed_block() {
+        let result =
+            parse_scene("\ngroup {\n  sphere { center 0 0 0; radius 1; material dielectric 1.5 }");
+
+        assert!(matches!(result, Err(Error::Parse { line: 2, .. })));
+    }
+
+    /// Writes a scene file to a folder for the test, so tests can include
+    /// files from each other.
+    fn write_test_file(test: &str, name: &str, text: &str) -> PathBuf {
+        let folder = std::env::temp_dir()
+            .join("rs_ray_tracer_parser_tests")
+            .join(test);
+        fs::create_dir_all(&folder).unwrap();
+        let file_path = folder.join(name);
+        fs::write(&file_path, text).unwrap();
+        file_path
+    }
+
+    #[test]
+    fn load_scene_file_should_include_files_with_parameters() {
+        let file_path = format!(
+            "{}/scenes/cornell_box_with_smoke_boxes.scene",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        let scene = load_scene_file(&file_path).unwrap();
+
+        match &scene.objects[0] {
+            ObjectDescription::Node(node) => {
+                assert_eq!(node.children.len(), 6);
+                assert_eq!(
+                    node.children[2],
+                    ObjectDescription::RectangleXZ {
+                        x0: 113.0,
+                        x1: 443.0,
+                        z0: 127.0,
+                        z1: 432.0,
+                        k: 554.0,
+                        material: MaterialDescription::DiffuseLight(TextureDescription::Solid(
+                            RGB(7.0, 7.0, 7.0)
+                        )),
+                    }
+                );
+            }
+            other => panic!("expected a node, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn load_scene_file_should_apply_include_transform_and_material() {
+        write_test_file(
+            "include_transform",
+            "ball.scene",
+            "camera { look_from 0 0 -5; look_at 0 0 0; fov 40 }
+            sphere { center 0 0 0; radius 1; material dielectric 1.5 }",
+        );
+        let file_path = write_test_file(
+            "include_transform",
+            "shot.scene",
+            "include \"ball.scene\" { translate 0 2 0; material diffuse 1 0 0 }",
+        );
+
+        let scene = load_scene_file(file_path).unwrap();
+
+        // The camera of the included file is left out
+        assert!(scene.cameras.is_empty());
+        match &scene.objects[0] {
+            ObjectDescription::Node(node) => {
+                assert_eq!(node.transform.translation, Vec3d::new(0.0, 2.0, 0.0));
+                assert_eq!(
+                    node.material,
+                    Some(MaterialDescription::Diffuse(RGB(1.0, 0.0, 0.0)))
+                );
+                assert!(matches!(
+                    node.children[..],
+                    [ObjectDescription::Sphere { .. }]
+                ));
+            }
+            other => panic!("expected a node, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn load_scene_file_should_report_errors_in_included_file_against_that_file() {
+        let included = write_test_file(
+            "include_error",
+            "ball.scene",
+            "\nsphere { center 0 0 0; radios 1; material dielectric 1.5 }",
+        );
+        let file_path = write_test_file("include_error", "shot.scene", "include \"ball.scene\"");
+
+        match load_scene_file(file_path) {
+            Err(Error::Parse {
+                path: Some(path),
+                line,
+                ..
+            }) => {
+                assert_eq!(path, included);
+                assert_eq!(line, 2);
+            }
+            other => panic!("expected a parse error, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn load_scene_file_should_report_include_cycle() {
+        write_test_file("include_cycle", "a.scene", "include \"b.scene\"");
+        let file_path = write_test_file("include_cycle", "b.scene", "\ninclude \"a.scene\"");
+
+        let result = load_scene_file(file_path);
+
+        assert!(
+            matches!(result, Err(Error::Parse { ref message, .. }) if message.contains("includes itself")),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn parse_scene_should_report_unknown_parameter() {
+        let result = parse_scene(
+            "param size 1\nsphere { center 0 0 0; radius $radius; material dielectric 1.5 }",
+        );
+
+        match result {
+            Err(Error::Parse { line, message, .. }) => {
+                assert_eq!(line, 2);
+                assert!(message.contains("$radius"), "{message}");
+            }
+            other => panic!("expected a parse error, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_scene_should_read_named_materials_and_textures() {
+        let scene = parse_scene(
+            "
+            textures { marble turbulence 4 7 }
+            materials {
+                gold metal \"#ffd700\" 0.8
+                stone lambertian { texture marble }
+            }
+            material_override diffuse 0.8 0.8 0.8
+            sphere { center 0 0 0; radius 1; material gold }
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            scene.textures["marble"],
+            TextureDescription::Turbulence {
+                scale: 4.0,
+                seed: Some(7)
+            }
+        );
+        assert_eq!(
+            scene.materials["stone"],
+            MaterialDescription::Lambertian(TextureDescription::Named("marble".to_string()))
+        );
+        assert_eq!(
+            scene.material_override,
+            Some(MaterialDescription::Diffuse(RGB(0.8, 0.8, 0.8)))
+        );
+        assert!(matches!(
+            &scene.objects[..],
+            [ObjectDescription::Sphere { material: MaterialDescription::Named(name), .. }] if name == "gold"
+        ));
+    }
+
+    #[test]
+    fn parse_scene_should_report_conflicting_definitions() {
+        let result = parse_scene(
+            "materials { gold metal 1 0.84 0 0.8 }
+            materials { gold metal 1 0.84 0 0.1 }",
+        );
+
+        assert!(
+            matches!(result, Err(Error::Parse { line: 2, ref message, .. }) if message.contains("'gold'")),
+            "{result:?}"
+        );
+        assert!(parse_scene("materials { metal diffuse 1 1 1 }").is_err());
+    }
+
+    #[test]
+    fn load_scene_file_should_share_definitions_of_included_files() {
+        write_test_file(
+            "include_library",
+            "library.scene",
+            "materials { gold metal 1 0.84 0 0.8 }",
+        );
+        let file_path = write_test_file(
+            "include_library",
+            "shot.scene",
+            "include \"library.scene\"
+            include \"library.scene\"
+            sphere { center 0 0 0; radius 1; material gold }",
+        );
+
+        let scene = load_scene_file(file_path).unwrap();
+
+        assert_eq!(scene.materials.len(), 1);
+        assert_eq!(scene.objects.len(), 1);
+    }
+}
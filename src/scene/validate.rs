@@ -0,0 +1,705 @@
+use std::{fmt, path::Path};
+
+use crate::{
+    render::Background,
+    vec3d::{Point3d, Vec3d},
+};
+
+use super::{
+    description::{MaterialDescription, ObjectDescription, SceneDescription, TextureDescription},
+    graph::SceneNode,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The scene can be rendered but probably not as intended.
+    Warning,
+    /// The scene can not be built or will not render correctly.
+    Error,
+}
+
+/// A problem found while validating a scene.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Where the problem is, e.g. `objects/3/translate/rotate_y/box`. Numbers
+    /// are positions in a list of objects and named nodes are shown as
+    /// `node "name"`.
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{severity}: {}: {}", self.path, self.message)
+    }
+}
+
+/// Returns whether any of the diagnostics are errors.
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == Severity::Error)
+}
+
+/// See `SceneDescription::validate`.
+pub(super) fn validate(scene: &SceneDescription) -> Vec<Diagnostic> {
+    let mut validator = Validator {
+        scene,
+        diagnostics: Vec::new(),
+        path: Vec::new(),
+        has_light: false,
+        in_boundary: false,
+    };
+
+    if scene.time0 > scene.time1 {
+        validator.error("shutter", "shutter opens after it closes");
+    }
+    if scene.cameras.is_empty() {
+        validator.error("cameras", "scene has no cameras");
+    }
+    for (i, camera) in scene.cameras.iter().enumerate() {
+        validator.path.push(format!("cameras/{i}"));
+        if camera.look_from == camera.look_at {
+            validator.push(Severity::Error, "camera looks at its own position");
+        } else if (camera.look_at - camera.look_from)
+            .cross(&camera.view_up)
+            .len_squared()
+            == 0.0
+        {
+            validator.push(Severity::Error, "view_up is parallel to the view direction");
+        }
+        if camera.vertical_fov <= 0.0 || camera.vertical_fov >= 180.0 {
+            validator.push(Severity::Error, "fov must be between 0 and 180 degrees");
+        }
+        validator.path.pop();
+    }
+
+    for (name, texture) in &scene.textures {
+        validator.path.push(format!("textures/{name}"));
+        if validator.is_cyclic_texture(name) {
+            validator.push(
+                Severity::Error,
+                format!("'{name}' is defined in terms of itself"),
+            );
+        }
+        validator.texture(texture);
+        validator.path.pop();
+    }
+    for (name, material) in &scene.materials {
+        validator.path.push(format!("materials/{name}"));
+        if validator.is_cyclic_material(name) {
+            validator.push(
+                Severity::Error,
+                format!("'{name}' is defined in terms of itself"),
+            );
+        }
+        validator.material(material);
+        validator.path.pop();
+    }
+    // Lights in the library only light the scene if an object uses them
+    validator.has_light = false;
+    if let Some(material) = &scene.material_override {
+        validator.path.push("material_override".to_string());
+        validator.material(material);
+        validator.path.pop();
+        validator.has_light = false;
+    }
+
+    if scene.objects.is_empty() {
+        validator.error("objects", "scene has no objects");
+    }
+    validator.path.push("objects".to_string());
+    validator.objects(&scene.objects, None);
+    validator.path.pop();
+
+    if !validator.has_light && scene.background == Background::BLACK && !scene.objects.is_empty() {
+        validator.warning(
+            "objects",
+            "scene has no lights and a black background, so will render black",
+        );
+    }
+
+    validator.diagnostics
+}
+
+struct Validator<'a> {
+    scene: &'a SceneDescription,
+    diagnostics: Vec<Diagnostic>,
+    path: Vec<String>,
+    has_light: bool,
+    /// Whether the objects are the boundary of a medium, whose materials are
+    /// not used.
+    in_boundary: bool,
+}
+
+impl<'a> Validator<'a> {
+    fn push(&mut self, severity: Severity, message: impl Into<String>) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            path: self.path.join("/"),
+            message: message.into(),
+        });
+    }
+
+    fn warning(&mut self, segment: &str, message: impl Into<String>) {
+        self.path.push(segment.to_string());
+        self.push(Severity::Warning, message);
+        self.path.pop();
+    }
+
+    fn error(&mut self, segment: &str, message: impl Into<String>) {
+        self.path.push(segment.to_string());
+        self.push(Severity::Error, message);
+        self.path.pop();
+    }
+
+    /// `material_override` is the material set by the closest scene node
+    /// above the objects, if any.
+    fn objects(
+        &mut self,
+        objects: &[ObjectDescription],
+        material_override: Option<&MaterialDescription>,
+    ) {
+        for (i, object) in objects.iter().enumerate() {
+            self.path.push(i.to_string());
+            self.object(object, material_override);
+            self.path.pop();
+        }
+    }
+
+    fn object(
+        &mut self,
+        object: &ObjectDescription,
+        material_override: Option<&MaterialDescription>,
+    ) {
+        let segment = match object {
+            ObjectDescription::Node(SceneNode {
+                name: Some(name), ..
+            }) => format!("node \"{name}\""),
+            object => object_kind(object).to_string(),
+        };
+        self.path.push(segment);
+
+        if let Some(material) = object_material(object) {
+            self.material(material_override.unwrap_or(material));
+            // Scene nodes do not replace the materials of lights
+            let material = match self.is_light(material) {
+                true => material,
+                false => material_override.unwrap_or(material),
+            };
+            if !self.in_boundary && self.is_light(material) && !is_sampled(object) {
+                self.push(
+                    Severity::Warning,
+                    "only spheres and rectangles are sampled as lights, so this light is only \
+                     found by rays that happen to hit it and will be noisy",
+                );
+            }
+        }
+
+        match object {
+            ObjectDescription::Sphere { radius, .. }
+            | ObjectDescription::MovingSphere { radius, .. } => {
+                if *radius == 0.0 {
+                    self.push(Severity::Error, "radius is zero");
+                }
+            }
+            ObjectDescription::RectangleXY {
+                x0,
+                x1,
+                y0,
+                y1,
+                material,
+                ..
+            } => self.rectangle(
+                (*x0, *x1),
+                (*y0, *y1),
+                material_override.unwrap_or(material),
+            ),
+            ObjectDescription::RectangleXZ {
+                x0,
+                x1,
+                z0,
+                z1,
+                material,
+                ..
+            } => self.rectangle(
+                (*x0, *x1),
+                (*z0, *z1),
+                material_override.unwrap_or(material),
+            ),
+            ObjectDescription::RectangleYZ {
+                y0,
+                y1,
+                z0,
+                z1,
+                material,
+                ..
+            } => self.rectangle(
+                (*y0, *y1),
+                (*z0, *z1),
+                material_override.unwrap_or(material),
+            ),
+            ObjectDescription::Box { min, max, .. } => {
+                if min.x > max.x || min.y > max.y || min.z > max.z {
+                    self.push(Severity::Error, "min is greater than max");
+                } else if min.x == max.x || min.y == max.y || min.z == max.z {
+                    self.push(Severity::Warning, "box is flat");
+                }
+            }
+            ObjectDescription::Triangle { a, b, c, .. } => {
+                if is_degenerate_triangle(*a, *b, *c) {
+                    self.push(
+                        Severity::Error,
+                        "vertices are collinear, so the triangle has no normal",
+                    );
+                }
+            }
+            ObjectDescription::Pyramid { base, height, .. } => {
+                if is_degenerate_triangle(base.0, base.1, base.2) {
+                    self.push(Severity::Error, "base vertices are collinear");
+                }
+                if *height == 0.0 {
+                    self.push(Severity::Error, "height is zero");
+                }
+            }
+            ObjectDescription::Model { file, .. } => self.file(file),
+            ObjectDescription::Group(objects) => {
+                if objects.is_empty() {
+                    self.push(
+                        Severity::Error,
+                        "group has no objects, so it has no bounding box",
+                    );
+                }
+                self.objects(objects, material_override);
+            }
+            ObjectDescription::Translate { object, .. }
+            | ObjectDescription::RotateX { object, .. }
+            | ObjectDescription::RotateY { object, .. }
+            | ObjectDescription::RotateZ { object, .. } => self.object(object, material_override),
+            ObjectDescription::Scale { x, y, z, object } => {
+                if *x <= 0.0 || *y <= 0.0 || *z <= 0.0 {
+                    self.push(Severity::Error, "scale factors must be greater than zero");
+                }
+                self.object(object, material_override);
+            }
+            ObjectDescription::ConstantMedium {
+                boundary,
+                texture,
+                density,
+            } => {
+                if *density <= 0.0 {
+                    self.push(Severity::Error, "density must be greater than zero");
+                }
+                if !is_closed(boundary) {
+                    self.push(
+                        Severity::Error,
+                        "boundary is not a closed surface, so rays can not pass through it",
+                    );
+                }
+                self.texture(texture);
+                // The boundary is only used to find where rays enter and
+                // leave the medium, so its material does not matter
+                self.path.push("boundary".to_string());
+                let (has_light, in_boundary) = (self.has_light, self.in_boundary);
+                self.in_boundary = true;
+                self.object(boundary, None);
+                self.has_light = has_light;
+                self.in_boundary = in_boundary;
+                self.path.pop();
+            }
+            ObjectDescription::Node(node) => {
+                if node.children.is_empty() {
+                    self.push(
+                        Severity::Error,
+                        "node has no objects, so it has no bounding box",
+                    );
+                }
+                let scale = node.transform.scale;
+                if scale.x <= 0.0 || scale.y <= 0.0 || scale.z <= 0.0 {
+                    self.push(Severity::Error, "scale factors must be greater than zero");
+                }
+                if let Some(material) = &node.material {
+                    self.material(material);
+                }
+                self.objects(&node.children, node.material.as_ref().or(material_override));
+            }
+        }
+
+        self.path.pop();
+    }
+
+    fn rectangle(&mut self, a: (f64, f64), b: (f64, f64), material: &MaterialDescription) {
+        if a.0 > a.1 || b.0 > b.1 {
+            self.push(
+                Severity::Error,
+                "rectangle bounds must be given smallest first",
+            );
+        } else if a.0 == a.1 || b.0 == b.1 {
+            let severity = match self.resolve_material(material) {
+                Some(MaterialDescription::DiffuseLight(_)) => Severity::Error,
+                _ => Severity::Warning,
+            };
+            self.push(severity, "rectangle has zero area");
+        }
+    }
+
+    fn material(&mut self, material: &MaterialDescription) {
+        match material {
+            MaterialDescription::Diffuse(_) | MaterialDescription::Metal { .. } => {}
+            MaterialDescription::Dielectric { refraction_index } => {
+                if *refraction_index <= 0.0 {
+                    self.push(
+                        Severity::Error,
+                        "refraction index must be greater than zero",
+                    );
+                }
+            }
+            MaterialDescription::DiffuseLight(texture) => {
+                self.has_light = true;
+                self.texture(texture);
+            }
+            MaterialDescription::Lambertian(texture) | MaterialDescription::Isotropic(texture) => {
+                self.texture(texture)
+            }
+            // The definition is validated on its own, only whether it exists
+            // and is a light matter here
+            MaterialDescription::Named(name) => {
+                if !self.scene.materials.contains_key(name) {
+                    self.push(Severity::Error, format!("unknown material '{name}'"));
+                } else if let Some(MaterialDescription::DiffuseLight(_)) =
+                    self.resolve_material(material)
+                {
+                    self.has_light = true;
+                }
+            }
+        }
+    }
+
+    /// Follows named materials to their definition, returns `None` if a name
+    /// is unknown or the names form a cycle.
+    fn resolve_material(
+        &self,
+        mut material: &'a MaterialDescription,
+    ) -> Option<&'a MaterialDescription> {
+        for _ in 0..=self.scene.materials.len() {
+            match material {
+                MaterialDescription::Named(name) => material = self.scene.materials.get(name)?,
+                material => return Some(material),
+            }
+        }
+        None
+    }
+
+    fn is_light(&self, material: &'a MaterialDescription) -> bool {
+        matches!(
+            self.resolve_material(material),
+            Some(MaterialDescription::DiffuseLight(_))
+        )
+    }
+
+    /// Returns whether the named material is defined in terms of itself.
+    fn is_cyclic_material(&self, name: &str) -> bool {
+        let mut seen = vec![name];
+        let mut current = name;
+        while let Some(MaterialDescription::Named(next)) = self.scene.materials.get(current) {
+            if seen.contains(&next.as_str()) {
+                // Names that only lead into a cycle are not part of it
+                return next == name;
+            }
+            seen.push(next);
+            current = next;
+        }
+        false
+    }
+
+    /// Returns whether the named texture refers back to itself.
+    fn is_cyclic_texture(&self, name: &str) -> bool {
+        let mut stack = match self.scene.textures.get(name) {
+            Some(texture) => texture.named_textures(),
+            None => return false,
+        };
+        let mut seen = Vec::new();
+        while let Some(next) = stack.pop() {
+            if next == name {
+                return true;
+            }
+            if !seen.contains(&next) {
+                seen.push(next);
+                if let Some(texture) = self.scene.textures.get(next) {
+                    stack.extend(texture.named_textures());
+                }
+            }
+        }
+        false
+    }
+
+    fn texture(&mut self, texture: &TextureDescription) {
+        match texture {
+            TextureDescription::Checker { odd, even } => {
+                self.texture(odd);
+                self.texture(even);
+            }
+            TextureDescription::Image { file } => self.file(file),
+            TextureDescription::Named(name) => {
+                if !self.scene.textures.contains_key(name) {
+                    self.push(Severity::Error, format!("unknown texture '{name}'"));
+                }
+            }
+            TextureDescription::Solid(_)
+            | TextureDescription::Noise { .. }
+            | TextureDescription::Turbulence { .. } => {}
+        }
+    }
+
+    fn file(&mut self, file: &str) {
+        if !Path::new(file).is_file() {
+            self.push(Severity::Error, format!("file \"{file}\" does not exist"));
+        }
+    }
+}
+
+/// Returns the name used for the object in scene files.
+fn object_kind(object: &ObjectDescription) -> &'static str {
+    match object {
+        ObjectDescription::Sphere { .. } => "sphere",
+        ObjectDescription::MovingSphere { .. } => "moving_sphere",
+        ObjectDescription::RectangleXY { .. } => "rectangle_xy",
+        ObjectDescription::RectangleXZ { .. } => "rectangle_xz",
+        ObjectDescription::RectangleYZ { .. } => "rectangle_yz",
+        ObjectDescription::Box { .. } => "box",
+        ObjectDescription::Triangle { .. } => "triangle",
+        ObjectDescription::Pyramid { .. } => "pyramid",
+        ObjectDescription::Model { .. } => "model",
+        ObjectDescription::Group(_) => "group",
+        ObjectDescription::Translate { .. } => "translate",
+        ObjectDescription::RotateX { .. } => "rotate_x",
+        ObjectDescription::RotateY { .. } => "rotate_y",
+        ObjectDescription::RotateZ { .. } => "rotate_z",
+        ObjectDescription::Scale { .. } => "scale",
+        ObjectDescription::ConstantMedium { .. } => "constant_medium",
+        ObjectDescription::Node(_) => "node",
+    }
+}
+
+fn object_material(object: &ObjectDescription) -> Option<&MaterialDescription> {
+    match object {
+        ObjectDescription::Sphere { material, .. }
+        | ObjectDescription::MovingSphere { material, .. }
+        | ObjectDescription::RectangleXY { material, .. }
+        | ObjectDescription::RectangleXZ { material, .. }
+        | ObjectDescription::RectangleYZ { material, .. }
+        | ObjectDescription::Box { material, .. }
+        | ObjectDescription::Triangle { material, .. }
+        | ObjectDescription::Pyramid { material, .. }
+        | ObjectDescription::Model { material, .. } => Some(material),
+        _ => None,
+    }
+}
+
+/// Returns whether the object can be sampled as a light, see
+/// `SceneDescription::build_lights_with`.
+fn is_sampled(object: &ObjectDescription) -> bool {
+    matches!(
+        object,
+        ObjectDescription::Sphere { .. }
+            | ObjectDescription::RectangleXY { .. }
+            | ObjectDescription::RectangleXZ { .. }
+            | ObjectDescription::RectangleYZ { .. }
+    )
+}
+
+fn is_degenerate_triangle(a: Point3d, b: Point3d, c: Point3d) -> bool {
+    let normal: Vec3d = (b - a).cross(&(c - a));
+    normal.len_squared() == 0.0
+}
+
+/// Returns whether the object is a closed surface, so that a ray entering it
+/// will also leave it. Models are assumed to be closed.
+fn is_closed(object: &ObjectDescription) -> bool {
+    match object {
+        ObjectDescription::Sphere { .. }
+        | ObjectDescription::MovingSphere { .. }
+        | ObjectDescription::Box { .. }
+        | ObjectDescription::Pyramid { .. }
+        | ObjectDescription::Model { .. } => true,
+        ObjectDescription::RectangleXY { .. }
+        | ObjectDescription::RectangleXZ { .. }
+        | ObjectDescription::RectangleYZ { .. }
+        | ObjectDescription::Triangle { .. }
+        | ObjectDescription::ConstantMedium { .. } => false,
+        ObjectDescription::Translate { object, .. }
+        | ObjectDescription::RotateX { object, .. }
+        | ObjectDescription::RotateY { object, .. }
+        | ObjectDescription::RotateZ { object, .. }
+        | ObjectDescription::Scale { object, .. } => is_closed(object),
+        // Several closed objects together are treated as closed, even if they
+        // overlap
+        ObjectDescription::Group(objects) => !objects.is_empty() && objects.iter().all(is_closed),
+        ObjectDescription::Node(node) => {
+            !node.children.is_empty() && node.children.iter().all(is_closed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod validate_tests {
+    use crate::{colour::RGB, scene::parse_scene};
+
+    use super::*;
+
+    fn diagnostics(text: &str) -> Vec<Diagnostic> {
+        parse_scene(text).unwrap().validate()
+    }
+
+    const CAMERA: &str = "background sky; camera { look_from 0 0 -10; look_at 0 0 0; fov 40 }\n";
+
+    #[test]
+    fn validate_should_accept_valid_scene() {
+        let result = diagnostics(&format!(
+            "{CAMERA}sphere {{ center 0 0 0; radius 1; material diffuse 0.5 0.5 0.5 }}"
+        ));
+
+        assert_eq!(result, Vec::new());
+    }
+
+    #[test]
+    fn validate_should_report_zero_area_light_with_path() {
+        let result = diagnostics(&format!(
+            "{CAMERA}
+            sphere {{ center 0 0 0; radius 1; material diffuse 0.5 0.5 0.5 }}
+            translate 0 1 0 {{
+                rectangle_xz {{ x 1 1; z 0 1; y 0; material diffuse_light 4 4 4 }}
+            }}"
+        ));
+
+        assert_eq!(
+            result,
+            vec![Diagnostic {
+                severity: Severity::Error,
+                path: "objects/1/translate/rectangle_xz".to_string(),
+                message: "rectangle has zero area".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn validate_should_report_collinear_triangle_in_named_node() {
+        let result = diagnostics(&format!(
+            "{CAMERA}
+            node {{
+                name \"table\"
+                triangle {{ a 0 0 0; b 1 1 1; c 2 2 2; material diffuse 0.5 0.5 0.5 }}
+            }}"
+        ));
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].path, "objects/0/node \"table\"/0/triangle");
+        assert!(has_errors(&result));
+    }
+
+    #[test]
+    fn validate_should_report_open_constant_medium_boundary() {
+        let result = diagnostics(&format!(
+            "{CAMERA}
+            constant_medium {{
+                density 0.01
+                texture solid 1 1 1
+                boundary rectangle_xy {{ x 0 1; y 0 1; z 0; material dielectric 1.5 }}
+            }}"
+        ));
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].path, "objects/0/constant_medium");
+        assert!(result[0].message.contains("closed"), "{}", result[0]);
+    }
+
+    #[test]
+    fn validate_should_report_empty_group_and_missing_camera() {
+        let scene = SceneDescription {
+            objects: vec![
+                ObjectDescription::Group(Vec::new()),
+                ObjectDescription::Sphere {
+                    center: Point3d::new(0.0, 0.0, 0.0),
+                    radius: 1.0,
+                    material: MaterialDescription::DiffuseLight(TextureDescription::Solid(RGB(
+                        1.0, 1.0, 1.0,
+                    ))),
+                },
+            ],
+            ..SceneDescription::default()
+        };
+
+        let paths: Vec<String> = scene.validate().into_iter().map(|d| d.path).collect();
+
+        assert_eq!(paths, vec!["cameras", "objects/0/group"]);
+    }
+
+    #[test]
+    fn validate_should_warn_when_scene_has_no_light() {
+        let result = diagnostics(
+            "camera { look_from 0 0 -10; look_at 0 0 0; fov 40 }
+            sphere { center 0 0 0; radius 1; material diffuse 0.5 0.5 0.5 }",
+        );
+
+        assert_eq!(result.len(), 1);
+        assert!(result[0].message.contains("no lights"), "{}", result[0]);
+    }
+
+    #[test]
+    fn validate_should_report_unknown_and_cyclic_names() {
+        let result = diagnostics(&format!(
+            "{CAMERA}
+            materials {{ a b; b a; c lambertian {{ texture wood }} }}
+            sphere {{ center 0 0 0; radius 1; material gold }}"
+        ));
+
+        let messages: Vec<String> = result.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "error: materials/a: 'a' is defined in terms of itself",
+                "error: materials/b: 'b' is defined in terms of itself",
+                "error: materials/c: unknown texture 'wood'",
+                "error: objects/0/sphere: unknown material 'gold'",
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_should_find_lights_through_named_materials() {
+        let result = diagnostics(
+            "camera { look_from 0 0 -10; look_at 0 0 0; fov 40 }
+            materials { lamp diffuse_light 4 4 4 }
+            rectangle_xz { x 0 1; z 1 1; y 2; material lamp }
+            sphere { center 0 0 0; radius 1; material diffuse 0.5 0.5 0.5 }",
+        );
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].severity, Severity::Error);
+        assert_eq!(result[0].message, "rectangle has zero area");
+    }
+
+    #[test]
+    fn validate_should_warn_about_lights_that_can_not_be_sampled() {
+        let result = diagnostics(&format!(
+            "{CAMERA}
+            rotate_y 30 {{
+                box {{ min 0 0 0; max 1 1 1; material diffuse_light 4 4 4 }}
+                sphere {{ center 0 3 0; radius 1; material diffuse_light 4 4 4 }}
+            }}
+            constant_medium {{
+                boundary box {{ min 0 0 0; max 1 1 1; material diffuse_light 4 4 4 }}
+                texture solid 1 1 1
+                density 0.5
+            }}"
+        ));
+
+        let paths: Vec<String> = result.into_iter().map(|d| d.path).collect();
+        assert_eq!(paths, vec!["objects/0/rotate_y/group/0/box"]);
+    }
+}
@@ -0,0 +1,590 @@
+use std::{fs, path::Path};
+
+use crate::{Error, Result, colour::RGB, render::Background, resolution::Resolution, vec3d::Vec3d};
+
+use super::{
+    description::{
+        CameraDescription, MaterialDescription, ObjectDescription, SceneDescription,
+        TextureDescription,
+    },
+    graph::{SceneNode, Transform},
+};
+
+/// Statements in a block are written on one line if they fit in this many
+/// characters.
+const MAX_LINE_LENGTH: usize = 100;
+
+/// Writes a scene to a scene file that `load_scene_file` reads back to the
+/// same description.
+pub fn save_scene_file<P: AsRef<Path>>(file_path: P, scene: &SceneDescription) -> Result<()> {
+    let file_path = file_path.as_ref();
+    fs::write(file_path, write_scene(scene)).map_err(|source| Error::Io {
+        path: file_path.to_path_buf(),
+        source,
+    })
+}
+
+/// Writes a scene in the scene file format, see `docs/scene_format.md`.
+/// Numbers are written with enough digits to be read back exactly, so a
+/// written scene renders the same as the original.
+pub fn write_scene(scene: &SceneDescription) -> String {
+    let mut statements = Vec::new();
+    if let Some(resolution) = scene.resolution {
+        statements.push(write_resolution(resolution));
+    }
+    statements.push(write_background(scene.background));
+    if (scene.time0, scene.time1) != (0.0, 1.0) {
+        statements.push(Statement::new(format!(
+            "shutter {}",
+            numbers(&[scene.time0, scene.time1])
+        )));
+    }
+    if !scene.textures.is_empty() {
+        let textures = scene
+            .textures
+            .iter()
+            .map(|(name, texture)| write_texture(name, texture));
+        statements.push(Statement::block("textures", textures.collect()));
+    }
+    if !scene.materials.is_empty() {
+        let materials = scene
+            .materials
+            .iter()
+            .map(|(name, material)| write_material(name, material));
+        statements.push(Statement::block("materials", materials.collect()));
+    }
+    if let Some(material) = &scene.material_override {
+        statements.push(write_material("material_override", material));
+    }
+    statements.extend(scene.cameras.iter().map(write_camera));
+    statements.extend(scene.objects.iter().map(write_object));
+
+    let mut text = String::new();
+    for statement in &statements {
+        statement.write(&mut text, 0);
+    }
+    text
+}
+
+/// A statement to write; a line of text and an optional block of child
+/// statements.
+struct Statement {
+    text: String,
+    children: Vec<Statement>,
+}
+
+impl Statement {
+    fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            children: Vec::new(),
+        }
+    }
+
+    fn block(text: impl Into<String>, children: Vec<Statement>) -> Self {
+        Self {
+            text: text.into(),
+            children,
+        }
+    }
+
+    /// Puts a property name in front of the statement, for properties that hold
+    /// an item such as `material` or `boundary`.
+    fn property(name: &str, statement: Statement) -> Self {
+        Self {
+            text: format!("{name} {}", statement.text),
+            children: statement.children,
+        }
+    }
+
+    /// Returns the statement on a single line if it fits, e.g.
+    /// `sphere { center 0 1 0; radius 1 }`.
+    fn single_line(&self) -> Option<String> {
+        if self.children.is_empty() {
+            return Some(self.text.clone());
+        }
+        let children = self
+            .children
+            .iter()
+            .map(|child| match child.children.is_empty() {
+                true => Some(child.text.as_str()),
+                false => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let line = format!("{} {{ {} }}", self.text, children.join("; "));
+        (line.len() <= MAX_LINE_LENGTH).then_some(line)
+    }
+
+    fn write(&self, text: &mut String, indent: usize) {
+        let padding = "    ".repeat(indent);
+        if let Some(line) = self.single_line() {
+            text.push_str(&format!("{padding}{line}\n"));
+            return;
+        }
+        text.push_str(&format!("{padding}{} {{\n", self.text));
+        for child in &self.children {
+            child.write(text, indent + 1);
+        }
+        text.push_str(&format!("{padding}}}\n"));
+    }
+}
+
+fn numbers(values: &[f64]) -> String {
+    values
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Returns a string in quotes, with the quotes and backslashes in it escaped
+/// so that it is read back the same.
+fn quoted(string: &str) -> String {
+    format!("\"{}\"", string.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn vec3(vec: Vec3d) -> String {
+    numbers(&[vec.x, vec.y, vec.z])
+}
+
+fn colour(colour: RGB) -> String {
+    numbers(&[colour.0, colour.1, colour.2])
+}
+
+fn write_resolution(resolution: Resolution) -> Statement {
+    Statement::block(
+        "resolution",
+        vec![
+            Statement::new(format!("width {}", resolution.image_width)),
+            Statement::new(format!("height {}", resolution.image_height)),
+            Statement::new(format!("samples {}", resolution.num_samples)),
+            Statement::new(format!("max_depth {}", resolution.max_depth)),
+        ],
+    )
+}
+
+fn write_background(background: Background) -> Statement {
+    match background {
+        Background::Sky => Statement::new("background sky"),
+        Background::BLACK => Statement::new("background black"),
+        Background::Colour(background) => {
+            Statement::new(format!("background {}", colour(background)))
+        }
+    }
+}
+
+fn write_camera(camera: &CameraDescription) -> Statement {
+    Statement::block(
+        "camera",
+        vec![
+            Statement::new(format!("look_from {}", vec3(camera.look_from))),
+            Statement::new(format!("look_at {}", vec3(camera.look_at))),
+            Statement::new(format!("view_up {}", vec3(camera.view_up))),
+            Statement::new(format!("fov {}", camera.vertical_fov)),
+            Statement::new(format!("aperture {}", camera.aperture)),
+            Statement::new(format!("focus_distance {}", camera.focus_distance)),
+        ],
+    )
+}
+
+fn write_object(object: &ObjectDescription) -> Statement {
+    match object {
+        ObjectDescription::Sphere {
+            center,
+            radius,
+            material,
+        } => Statement::block(
+            "sphere",
+            vec![
+                Statement::new(format!("center {}", vec3(*center))),
+                Statement::new(format!("radius {radius}")),
+                write_material("material", material),
+            ],
+        ),
+        ObjectDescription::MovingSphere {
+            center0,
+            center1,
+            time0,
+            time1,
+            radius,
+            material,
+        } => Statement::block(
+            "moving_sphere",
+            vec![
+                Statement::new(format!("center0 {}", vec3(*center0))),
+                Statement::new(format!("center1 {}", vec3(*center1))),
+                Statement::new(format!("time0 {time0}")),
+                Statement::new(format!("time1 {time1}")),
+                Statement::new(format!("radius {radius}")),
+                write_material("material", material),
+            ],
+        ),
+        ObjectDescription::RectangleXY {
+            x0,
+            x1,
+            y0,
+            y1,
+            k,
+            material,
+        } => Statement::block(
+            "rectangle_xy",
+            vec![
+                Statement::new(format!("x {}", numbers(&[*x0, *x1]))),
+                Statement::new(format!("y {}", numbers(&[*y0, *y1]))),
+                Statement::new(format!("z {k}")),
+                write_material("material", material),
+            ],
+        ),
+        ObjectDescription::RectangleXZ {
+            x0,
+            x1,
+            z0,
+            z1,
+            k,
+            material,
+        } => Statement::block(
+            "rectangle_xz",
+            vec![
+                Statement::new(format!("x {}", numbers(&[*x0, *x1]))),
+                Statement::new(format!("z {}", numbers(&[*z0, *z1]))),
+                Statement::new(format!("y {k}")),
+                write_material("material", material),
+            ],
+        ),
+        ObjectDescription::RectangleYZ {
+            y0,
+            y1,
+            z0,
+            z1,
+            k,
+            material,
+        } => Statement::block(
+            "rectangle_yz",
+            vec![
+                Statement::new(format!("y {}", numbers(&[*y0, *y1]))),
+                Statement::new(format!("z {}", numbers(&[*z0, *z1]))),
+                Statement::new(format!("x {k}")),
+                write_material("material", material),
+            ],
+        ),
+        ObjectDescription::Box { min, max, material } => Statement::block(
+            "box",
+            vec![
+                Statement::new(format!("min {}", vec3(*min))),
+                Statement::new(format!("max {}", vec3(*max))),
+                write_material("material", material),
+            ],
+        ),
+        ObjectDescription::Triangle { a, b, c, material } => Statement::block(
+            "triangle",
+            vec![
+                Statement::new(format!("a {}", vec3(*a))),
+                Statement::new(format!("b {}", vec3(*b))),
+                Statement::new(format!("c {}", vec3(*c))),
+                write_material("material", material),
+            ],
+        ),
+        ObjectDescription::Pyramid {
+            base,
+            height,
+            material,
+        } => Statement::block(
+            "pyramid",
+            vec![
+                Statement::new(format!(
+                    "base {} {} {}",
+                    vec3(base.0),
+                    vec3(base.1),
+                    vec3(base.2)
+                )),
+                Statement::new(format!("height {height}")),
+                write_material("material", material),
+            ],
+        ),
+        ObjectDescription::Model { file, material } => Statement::block(
+            "model",
+            vec![
+                Statement::new(format!("file {}", quoted(file))),
+                write_material("material", material),
+            ],
+        ),
+        ObjectDescription::Group(objects) => {
+            Statement::block("group", objects.iter().map(write_object).collect())
+        }
+        // Instances always hold a single statement, a group is written as a
+        // `group` so that it is read back as one
+        ObjectDescription::Translate { offset, object } => Statement::block(
+            format!("translate {}", vec3(*offset)),
+            vec![write_object(object)],
+        ),
+        ObjectDescription::RotateX { angle, object } => {
+            Statement::block(format!("rotate_x {angle}"), vec![write_object(object)])
+        }
+        ObjectDescription::RotateY { angle, object } => {
+            Statement::block(format!("rotate_y {angle}"), vec![write_object(object)])
+        }
+        ObjectDescription::RotateZ { angle, object } => {
+            Statement::block(format!("rotate_z {angle}"), vec![write_object(object)])
+        }
+        ObjectDescription::Scale { x, y, z, object } => Statement::block(
+            format!("scale {}", numbers(&[*x, *y, *z])),
+            vec![write_object(object)],
+        ),
+        ObjectDescription::ConstantMedium {
+            boundary,
+            texture,
+            density,
+        } => Statement::block(
+            "constant_medium",
+            vec![
+                Statement::property("boundary", write_object(boundary)),
+                write_texture("texture", texture),
+                Statement::new(format!("density {density}")),
+            ],
+        ),
+        ObjectDescription::Node(node) => write_node(node),
+    }
+}
+
+fn write_node(node: &SceneNode) -> Statement {
+    let mut properties = Vec::new();
+    if let Some(name) = &node.name {
+        properties.push(Statement::new(format!("name {}", quoted(name))));
+    }
+    let transform = node.transform;
+    if transform.translation != Transform::IDENTITY.translation {
+        properties.push(Statement::new(format!(
+            "translate {}",
+            vec3(transform.translation)
+        )));
+    }
+    if transform.rotation != Transform::IDENTITY.rotation {
+        properties.push(Statement::new(format!(
+            "rotate {}",
+            vec3(transform.rotation)
+        )));
+    }
+    if transform.scale != Transform::IDENTITY.scale {
+        properties.push(Statement::new(format!("scale {}", vec3(transform.scale))));
+    }
+    if let Some(material) = &node.material {
+        properties.push(write_material("material", material));
+    }
+    properties.extend(node.children.iter().map(write_object));
+
+    Statement::block("node", properties)
+}
+
+fn write_material(name: &str, material: &MaterialDescription) -> Statement {
+    let material = match material {
+        MaterialDescription::Diffuse(albedo) => {
+            Statement::new(format!("diffuse {}", colour(*albedo)))
+        }
+        MaterialDescription::Lambertian(texture) => write_textured("lambertian", texture),
+        MaterialDescription::Metal { albedo, fuzz } => {
+            Statement::new(format!("metal {} {fuzz}", colour(*albedo)))
+        }
+        MaterialDescription::Dielectric { refraction_index } => {
+            Statement::new(format!("dielectric {refraction_index}"))
+        }
+        MaterialDescription::DiffuseLight(texture) => write_textured("diffuse_light", texture),
+        MaterialDescription::Isotropic(texture) => write_textured("isotropic", texture),
+        MaterialDescription::Named(library_name) => Statement::new(library_name.as_str()),
+    };
+    Statement::property(name, material)
+}
+
+/// Materials with a solid colour texture use the colour shorthand.
+fn write_textured(kind: &str, texture: &TextureDescription) -> Statement {
+    match texture {
+        TextureDescription::Solid(solid) => Statement::new(format!("{kind} {}", colour(*solid))),
+        texture => Statement::block(kind, vec![write_texture("texture", texture)]),
+    }
+}
+
+fn write_texture(name: &str, texture: &TextureDescription) -> Statement {
+    let texture = match texture {
+        TextureDescription::Solid(solid) => Statement::new(format!("solid {}", colour(*solid))),
+        TextureDescription::Checker { odd, even } => Statement::block(
+            "checker",
+            vec![write_texture("odd", odd), write_texture("even", even)],
+        ),
+        TextureDescription::Image { file } => Statement::new(format!("image {}", quoted(file))),
+        TextureDescription::Noise { scale, seed } => write_noise("noise", *scale, *seed),
+        TextureDescription::Turbulence { scale, seed } => write_noise("turbulence", *scale, *seed),
+        TextureDescription::Named(library_name) => Statement::new(library_name.as_str()),
+    };
+    Statement::property(name, texture)
+}
+
+fn write_noise(kind: &str, scale: f64, seed: Option<u64>) -> Statement {
+    match seed {
+        Some(seed) => Statement::new(format!("{kind} {scale} {seed}")),
+        None => Statement::new(format!("{kind} {scale}")),
+    }
+}
+
+#[cfg(test)]
+mod writer_tests {
+    use std::collections::BTreeMap;
+
+    use crate::{scene::parse_scene, vec3d::Point3d};
+
+    use super::*;
+
+    fn white() -> MaterialDescription {
+        MaterialDescription::Lambertian(TextureDescription::Solid(RGB(0.73, 0.73, 0.73)))
+    }
+
+    #[test]
+    fn write_scene_should_be_read_back_to_the_same_description() {
+        let sphere = ObjectDescription::Sphere {
+            center: Point3d::new(0.1 + 0.2, -1.0 / 3.0, 1e-7),
+            radius: 1.0,
+            material: MaterialDescription::Dielectric {
+                refraction_index: 1.5,
+            },
+        };
+        let scene = SceneDescription {
+            resolution: Some(Resolution::new(40, 30, 8, 5)),
+            background: Background::Colour(RGB(0.1, 0.2, 0.3)),
+            time0: 0.25,
+            time1: 0.75,
+            cameras: vec![CameraDescription::new(
+                Point3d::new(13.0, 2.0, 3.0),
+                Point3d::new(0.0, 0.0, 0.0),
+                20.0,
+            )],
+            objects: vec![
+                sphere.clone(),
+                ObjectDescription::MovingSphere {
+                    center0: Point3d::new(0.0, 0.0, 0.0),
+                    center1: Point3d::new(0.0, 1.0, 0.0),
+                    time0: 0.0,
+                    time1: 1.0,
+                    radius: 0.2,
+                    material: MaterialDescription::Metal {
+                        albedo: RGB(0.8, 0.6, 0.2),
+                        fuzz: 0.3,
+                    },
+                },
+                ObjectDescription::RectangleXZ {
+                    x0: 213.0,
+                    x1: 343.0,
+                    z0: 227.0,
+                    z1: 332.0,
+                    k: 554.0,
+                    material: MaterialDescription::DiffuseLight(TextureDescription::Solid(RGB(
+                        15.0, 15.0, 15.0,
+                    ))),
+                },
+                ObjectDescription::Translate {
+                    offset: Vec3d::new(265.0, 0.0, 295.0),
+                    object: Box::new(ObjectDescription::RotateY {
+                        angle: 15.0,
+                        object: Box::new(ObjectDescription::Group(vec![ObjectDescription::Box {
+                            min: Point3d::new(0.0, 0.0, 0.0),
+                            max: Point3d::new(165.0, 330.0, 165.0),
+                            material: white(),
+                        }])),
+                    }),
+                },
+                ObjectDescription::ConstantMedium {
+                    boundary: Box::new(sphere.clone()),
+                    texture: TextureDescription::Solid(RGB(1.0, 1.0, 1.0)),
+                    density: 0.0001,
+                },
+                ObjectDescription::Model {
+                    file: "assets/model.ply".to_string(),
+                    material: MaterialDescription::Lambertian(TextureDescription::Checker {
+                        odd: Box::new(TextureDescription::Noise {
+                            scale: 4.0,
+                            seed: Some(123456),
+                        }),
+                        even: Box::new(TextureDescription::Image {
+                            file: "images\\earthmap.jpg".to_string(),
+                        }),
+                    }),
+                },
+                ObjectDescription::Node(SceneNode {
+                    name: Some("table".to_string()),
+                    transform: Transform {
+                        translation: Vec3d::new(200.0, 0.0, 300.0),
+                        rotation: Vec3d::new(0.0, 45.0, 0.0),
+                        scale: Vec3d::new(2.0, 2.0, 2.0),
+                    },
+                    material: Some(white()),
+                    children: vec![ObjectDescription::Scale {
+                        x: 1.0,
+                        y: 2.0,
+                        z: 3.0,
+                        object: Box::new(sphere),
+                    }],
+                }),
+                ObjectDescription::Sphere {
+                    center: Point3d::new(0.0, -1000.0, 0.0),
+                    radius: 1000.0,
+                    material: MaterialDescription::Named("floor".to_string()),
+                },
+            ],
+            materials: BTreeMap::from([
+                (
+                    "floor".to_string(),
+                    MaterialDescription::Lambertian(TextureDescription::Named("tiles".to_string())),
+                ),
+                ("white".to_string(), white()),
+            ]),
+            textures: BTreeMap::from([(
+                "tiles".to_string(),
+                TextureDescription::Checker {
+                    odd: Box::new(TextureDescription::Solid(RGB(0.2, 0.3, 0.1))),
+                    even: Box::new(TextureDescription::Solid(RGB(0.9, 0.9, 0.9))),
+                },
+            )]),
+            material_override: Some(MaterialDescription::Named("white".to_string())),
+        };
+
+        let text = write_scene(&scene);
+
+        assert_eq!(parse_scene(&text).unwrap(), scene, "{text}");
+    }
+
+    #[test]
+    fn write_scene_should_escape_quotes_and_backslashes() {
+        let mut node = SceneNode::new(
+            Transform::IDENTITY,
+            vec![ObjectDescription::Model {
+                file: r#"models\"quoted\" \teapot.ply"#.to_string(),
+                material: white(),
+            }],
+        );
+        node.name = Some(r#"the "best" node \"#.to_string());
+        let scene = SceneDescription {
+            objects: vec![ObjectDescription::Node(node)],
+            ..SceneDescription::default()
+        };
+
+        let text = write_scene(&scene);
+
+        assert_eq!(parse_scene(&text).unwrap(), scene, "{text}");
+    }
+
+    #[test]
+    fn write_scene_should_put_short_objects_on_one_line() {
+        let scene = SceneDescription {
+            objects: vec![ObjectDescription::Sphere {
+                center: Point3d::new(0.0, 1.0, 0.0),
+                radius: 1.0,
+                material: MaterialDescription::Dielectric {
+                    refraction_index: 1.5,
+                },
+            }],
+            ..SceneDescription::default()
+        };
+
+        assert_eq!(
+            write_scene(&scene),
+            "background black\nsphere { center 0 1 0; radius 1; material dielectric 1.5 }\n"
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{
+    scene::CameraDescription,
+    vec3d::{Point3d, Vec3d},
+};
+
+pub(super) fn get_standard_camera() -> Vec<CameraDescription> {
+    vec![CameraDescription {
+        look_from: Point3d::new(13.0, 2.0, 3.0),
+        look_at: Point3d::new(0.0, 0.0, 0.0),
+        view_up: Vec3d::new(0.0, 1.0, 0.0),
+        vertical_fov: 20.0,
+        aperture: 0.1,
+        focus_distance: 10.0,
+    }]
+}
+
+#[allow(dead_code)]
+pub(super) fn get_standard_multi_cameras() -> Vec<CameraDescription> {
+    // Camera
+    const FOV: f64 = 20.0; // degrees
+    const APERTURE: f64 = 0.1;
+
+    vec![
+        CameraDescription {
+            look_from: Point3d::new(13.0, 2.0, 3.0),
+            look_at: Point3d::new(0.0, 0.0, 0.0),
+            view_up: Vec3d::new(0.0, 1.0, 0.0),
+            vertical_fov: FOV,
+            aperture: APERTURE,
+            focus_distance: 10.0,
+        },
+        CameraDescription {
+            look_from: Point3d::new(5.0, 5.0, 13.0),
+            look_at: Point3d::new(0.0, 0.0, 0.0),
+            view_up: Vec3d::new(0.0, 1.0, 0.0),
+            vertical_fov: FOV,
+            aperture: APERTURE,
+            focus_distance: 13.3,
+        },
+        CameraDescription {
+            look_from: Point3d::new(-6.0, 1.0, -10.0),
+            look_at: Point3d::new(4.0, 0.0, 0.0),
+            view_up: Vec3d::new(0.0, 1.0, 0.0),
+            vertical_fov: FOV,
+            aperture: APERTURE,
+            focus_distance: 16.0,
+        },
+    ]
+}
+
+pub(super) fn get_cornell_box_camera() -> Vec<CameraDescription> {
+    vec![CameraDescription {
+        look_from: Point3d::new(278.0, 278.0, -800.0),
+        look_at: Point3d::new(278.0, 278.0, 0.0),
+        view_up: Vec3d::new(0.0, 1.0, 0.0),
+        vertical_fov: 40.0,
+        aperture: 0.0,
+        focus_distance: 10.0,
+    }]
+}
+
+pub(super) fn get_final_scene_book2_camera() -> Vec<CameraDescription> {
+    vec![CameraDescription {
+        look_from: Point3d::new(478.0, 278.0, -600.0),
+        look_at: Point3d::new(278.0, 278.0, 0.0),
+        view_up: Vec3d::new(0.0, 1.0, 0.0),
+        vertical_fov: 40.0,
+        aperture: 0.0,
+        focus_distance: 10.0,
+    }]
+}
+
+pub(super) fn get_final_scene_cameras() -> Vec<CameraDescription> {
+    vec![
+        CameraDescription {
+            look_from: Point3d::new(478.0, 278.0, -600.0),
+            look_at: Point3d::new(200.0, 278.0, 280.0),
+            view_up: Vec3d::new(0.0, 1.0, 0.0),
+            vertical_fov: 40.0,
+            aperture: 0.0,
+            focus_distance: 922.0,
+        },
+        // CameraDescription {
+        //     look_from: Point3d::new(0.0, 278.0, -600.0),
+        //     look_at: Point3d::new(200.0, 278.0, 280.0),
+        //     view_up: Vec3d::new(0.0, 1.0, 0.0),
+        //     vertical_fov: 40.0,
+        //     aperture: 0.0,
+        //     focus_distance: 10.0,
+        // },
+        // CameraDescription {
+        //     look_from: Point3d::new(232.0, 478.0, -600.0),
+        //     look_at: Point3d::new(200.0, 278.0, 280.0),
+        //     view_up: Vec3d::new(0.0, 1.0, 0.0),
+        //     vertical_fov: 40.0,
+        //     aperture: 0.0,
+        //     focus_distance: 10.0,
+        // },
+    ]
+}
@@ -0,0 +1,651 @@
+use crate::{
+    colour::RGB,
+    scene::{MaterialDescription, ObjectDescription, TextureDescription},
+    utilities::{random, random_rgb, random_rng, random_seed, random_vec_rng},
+    vec3d::{Point3d, Vec3d},
+};
+
+use MaterialDescription::{Dielectric, Diffuse, Metal};
+
+fn sphere(center: Point3d, radius: f64, material: MaterialDescription) -> ObjectDescription {
+    ObjectDescription::Sphere {
+        center,
+        radius,
+        material,
+    }
+}
+
+fn moving_sphere(
+    center0: Point3d,
+    center1: Point3d,
+    radius: f64,
+    material: MaterialDescription,
+) -> ObjectDescription {
+    ObjectDescription::MovingSphere {
+        center0,
+        center1,
+        time0: 0.0,
+        time1: 1.0,
+        radius,
+        material,
+    }
+}
+
+fn box_obj(min: Point3d, max: Point3d, material: MaterialDescription) -> ObjectDescription {
+    ObjectDescription::Box { min, max, material }
+}
+
+fn translate(offset: Vec3d, object: ObjectDescription) -> ObjectDescription {
+    ObjectDescription::Translate {
+        offset,
+        object: Box::new(object),
+    }
+}
+
+fn rotate_y(angle: f64, object: ObjectDescription) -> ObjectDescription {
+    ObjectDescription::RotateY {
+        angle,
+        object: Box::new(object),
+    }
+}
+
+fn constant_medium(boundary: ObjectDescription, colour: RGB, density: f64) -> ObjectDescription {
+    ObjectDescription::ConstantMedium {
+        boundary: Box::new(boundary),
+        texture: TextureDescription::Solid(colour),
+        density,
+    }
+}
+
+fn lambertian(colour: RGB) -> MaterialDescription {
+    MaterialDescription::Lambertian(TextureDescription::Solid(colour))
+}
+
+fn diffuse_light(colour: RGB) -> MaterialDescription {
+    MaterialDescription::DiffuseLight(TextureDescription::Solid(colour))
+}
+
+fn dielectric(refraction_index: f64) -> MaterialDescription {
+    Dielectric { refraction_index }
+}
+
+/// Perlin noise with turbulence. The seed is drawn from the random number
+/// generator so the noise is fixed once the scene has been generated.
+fn turbulence(scale: f64) -> TextureDescription {
+    TextureDescription::Turbulence {
+        scale,
+        seed: Some(random_seed()),
+    }
+}
+
+/// The five walls and light of a Cornell box, the light is centred on the
+/// ceiling with the given size.
+fn cornell_box_walls(light_width: f64, light_depth: f64, light: RGB) -> Vec<ObjectDescription> {
+    let red = lambertian(RGB(0.65, 0.05, 0.05));
+    let green = lambertian(RGB(0.12, 0.45, 0.15));
+    let white = lambertian(RGB(0.73, 0.73, 0.73));
+    let (x0, z0) = (278.0 - light_width / 2.0, 279.5 - light_depth / 2.0);
+
+    vec![
+        rectangle_yz(0.0, 555.0, 0.0, 555.0, 0.0, red),
+        rectangle_yz(0.0, 555.0, 0.0, 555.0, 555.0, green),
+        rectangle_xz(
+            x0,
+            x0 + light_width,
+            z0,
+            z0 + light_depth,
+            554.0,
+            diffuse_light(light),
+        ),
+        rectangle_xz(0.0, 555.0, 0.0, 555.0, 0.0, white.clone()),
+        rectangle_xz(0.0, 555.0, 0.0, 555.0, 555.0, white.clone()),
+        rectangle_xy(0.0, 555.0, 0.0, 555.0, 555.0, white),
+    ]
+}
+
+fn rectangle_xy(
+    x0: f64,
+    x1: f64,
+    y0: f64,
+    y1: f64,
+    k: f64,
+    material: MaterialDescription,
+) -> ObjectDescription {
+    ObjectDescription::RectangleXY {
+        x0,
+        x1,
+        y0,
+        y1,
+        k,
+        material,
+    }
+}
+
+fn rectangle_xz(
+    x0: f64,
+    x1: f64,
+    z0: f64,
+    z1: f64,
+    k: f64,
+    material: MaterialDescription,
+) -> ObjectDescription {
+    ObjectDescription::RectangleXZ {
+        x0,
+        x1,
+        z0,
+        z1,
+        k,
+        material,
+    }
+}
+
+fn rectangle_yz(
+    y0: f64,
+    y1: f64,
+    z0: f64,
+    z1: f64,
+    k: f64,
+    material: MaterialDescription,
+) -> ObjectDescription {
+    ObjectDescription::RectangleYZ {
+        y0,
+        y1,
+        z0,
+        z1,
+        k,
+        material,
+    }
+}
+
+pub(super) fn generate_basic_scene() -> Vec<ObjectDescription> {
+    // Basic scene
+    let material_ground = Diffuse(RGB(0.8, 0.8, 0.0));
+    let material_centre = Diffuse(RGB(0.1, 0.2, 0.5));
+    let material_left = dielectric(1.5);
+    let material_right = Metal {
+        albedo: RGB(0.8, 0.6, 0.2),
+        fuzz: 0.0,
+    };
+
+    vec![
+        sphere(Vec3d::new(0.0, -100.5, 1.0), 100.0, material_ground),
+        sphere(Vec3d::new(0.0, 0.0, -1.0), 0.5, material_centre),
+        sphere(Vec3d::new(-1.0, 0.0, -1.0), 0.5, material_left.clone()),
+        sphere(Vec3d::new(-1.0, 0.0, -1.0), -0.45, material_left),
+        sphere(Vec3d::new(1.0, 0.0, -1.0), 0.5, material_right),
+    ]
+}
+
+/// The ground and three large spheres shared by the random scenes.
+fn random_scene_large_spheres(ground: MaterialDescription) -> Vec<ObjectDescription> {
+    vec![
+        sphere(Point3d::new(0.0, -1000.0, 0.0), 1000.0, ground),
+        sphere(Point3d::new(0.0, 1.0, 0.0), 1.0, dielectric(1.5)),
+        sphere(
+            Point3d::new(-4.0, 1.0, 0.0),
+            1.0,
+            Diffuse(RGB(0.4, 0.2, 0.1)),
+        ),
+        sphere(
+            Point3d::new(4.0, 1.0, 0.0),
+            1.0,
+            Metal {
+                albedo: RGB(0.7, 0.6, 0.5),
+                fuzz: 0.0,
+            },
+        ),
+    ]
+}
+
+/// Returns a random material for one of the small spheres in the random
+/// scenes; mostly diffuse, some metal, and a few glass.
+fn random_sphere_material(choose_mat: f64) -> MaterialDescription {
+    match choose_mat {
+        x if x < 0.8 => Diffuse(random_rgb() * random_rgb()),
+        x if x < 0.95 => Metal {
+            albedo: random_rgb(),
+            fuzz: random_rng(0.0, 0.5),
+        },
+        _ => dielectric(1.5),
+    }
+}
+
+pub(super) fn generate_random_complex_scene() -> Vec<ObjectDescription> {
+    let mut scene = random_scene_large_spheres(Diffuse(RGB(0.5, 0.5, 0.5)));
+
+    // Add several random spheres
+    for a in -11..11 {
+        for b in -11..11 {
+            let choose_mat = random();
+            let center = Point3d::new(a as f64 + 0.9 * random(), 0.2, b as f64 + 0.9 * random());
+
+            if (center - Point3d::new(4.0, 0.2, 0.0)).len() > 0.9 {
+                scene.push(sphere(center, 0.2, random_sphere_material(choose_mat)));
+            }
+        }
+    }
+
+    scene
+}
+
+fn checker_ground() -> MaterialDescription {
+    MaterialDescription::Lambertian(TextureDescription::Checker {
+        odd: Box::new(TextureDescription::Solid(RGB(0.2, 0.3, 0.1))),
+        even: Box::new(TextureDescription::Solid(RGB(0.9, 0.9, 0.9))),
+    })
+}
+
+pub(super) fn generate_random_complex_scene_moving_spheres() -> Vec<ObjectDescription> {
+    // Use a checkered texture for the ground
+    let mut scene = random_scene_large_spheres(checker_ground());
+
+    // Add several random spheres
+    for a in -11..11 {
+        for b in -11..11 {
+            let choose_mat = random();
+            let center0 = Point3d::new(a as f64 + 0.9 * random(), 0.2, b as f64 + 0.9 * random());
+            let center1 = center0 + Vec3d::new(0.0, random_rng(0.0, 0.5), 0.0);
+
+            if (center0 - Point3d::new(4.0, 0.2, 0.0)).len() > 0.9 {
+                let material = random_sphere_material(choose_mat);
+                scene.push(moving_sphere(center0, center1, 0.2, material));
+            }
+        }
+    }
+
+    scene
+}
+
+pub(super) fn generate_two_checkered_spheres() -> Vec<ObjectDescription> {
+    let material_checker = checker_ground();
+
+    vec![
+        sphere(Vec3d::new(0.0, -10.0, 0.0), 10.0, material_checker.clone()),
+        sphere(Vec3d::new(0.0, 10.0, 0.0), 10.0, material_checker),
+    ]
+}
+
+pub(super) fn generate_two_perlin_noise_spheres() -> Vec<ObjectDescription> {
+    let noise_material = MaterialDescription::Lambertian(TextureDescription::Noise {
+        scale: 4.0,
+        seed: Some(random_seed()),
+    });
+
+    vec![
+        sphere(
+            Vec3d::new(0.0, -1000.0, 0.0),
+            1000.0,
+            noise_material.clone(),
+        ),
+        sphere(Vec3d::new(0.0, 2.0, 0.0), 2.0, noise_material),
+    ]
+}
+
+pub(super) fn generate_two_perlin_noise_turbulence_spheres() -> Vec<ObjectDescription> {
+    let turbulence_material = MaterialDescription::Lambertian(turbulence(4.0));
+
+    vec![
+        sphere(
+            Vec3d::new(0.0, -1000.0, 0.0),
+            1000.0,
+            turbulence_material.clone(),
+        ),
+        sphere(Vec3d::new(0.0, 2.0, 0.0), 2.0, turbulence_material),
+    ]
+}
+
+fn earth_material() -> MaterialDescription {
+    MaterialDescription::Lambertian(TextureDescription::Image {
+        file: "images\\earthmap.jpg".to_string(),
+    })
+}
+
+pub(super) fn generate_earth_scene() -> Vec<ObjectDescription> {
+    vec![sphere(Vec3d::new(0.0, 0.0, 0.0), 2.0, earth_material())]
+}
+
+pub(super) fn generate_simple_light() -> Vec<ObjectDescription> {
+    let turbulence_material = MaterialDescription::Lambertian(turbulence(4.0));
+
+    // Note the light is brighter than (1, 1, 1) this allows it to light other
+    // things.
+    let diff_light = diffuse_light(RGB(4.0, 4.0, 4.0));
+
+    vec![
+        sphere(
+            Vec3d::new(-8.0, -1003.0, 0.0),
+            1000.0,
+            turbulence_material.clone(),
+        ),
+        sphere(Vec3d::new(-8.0, -1.0, 0.0), 2.0, turbulence_material),
+        rectangle_xy(-5.0, -3.0, -2.0, 1.0, -2.0, diff_light.clone()),
+        sphere(Vec3d::new(-8.0, 3.0, 0.0), 1.0, diff_light),
+    ]
+}
+
+/// The tall and short boxes in the Cornell box.
+fn cornell_boxes() -> (ObjectDescription, ObjectDescription) {
+    let white = lambertian(RGB(0.73, 0.73, 0.73));
+    let box0 = box_obj(
+        Point3d::new(0.0, 0.0, 0.0),
+        Point3d::new(165.0, 330.0, 165.0),
+        white.clone(),
+    );
+    let box0 = translate(Vec3d::new(265.0, 0.0, 295.0), rotate_y(15.0, box0));
+    let box1 = box_obj(
+        Point3d::new(0.0, 0.0, 0.0),
+        Point3d::new(165.0, 165.0, 165.0),
+        white,
+    );
+    let box1 = translate(Vec3d::new(130.0, 0.0, 65.0), rotate_y(-18.0, box1));
+
+    (box0, box1)
+}
+
+pub(super) fn generate_cornell_box() -> Vec<ObjectDescription> {
+    let mut scene = cornell_box_walls(130.0, 105.0, RGB(15.0, 15.0, 15.0));
+
+    let (box0, box1) = cornell_boxes();
+    scene.push(box0);
+    scene.push(box1);
+
+    scene
+}
+
+pub(super) fn generate_cornell_box_with_smoke_boxes() -> Vec<ObjectDescription> {
+    // Larger dimmer light than standard Cornell
+    let mut scene = cornell_box_walls(330.0, 305.0, RGB(7.0, 7.0, 7.0));
+
+    let (box0, box1) = cornell_boxes();
+    scene.push(constant_medium(box0, RGB(0.0, 0.0, 0.0), 0.01)); // dark smoke box
+    scene.push(constant_medium(box1, RGB(1.0, 1.0, 1.0), 0.01)); // light smoke box
+
+    scene
+}
+
+pub(super) fn generate_final_scene_book2() -> Vec<ObjectDescription> {
+    // Make the ground a 20x20 grid of random height boxes
+    let mut ground_boxes = Vec::new();
+    let ground = lambertian(RGB(0.48, 0.83, 0.53));
+    for i in 0..20 {
+        for j in 0..20 {
+            let width = 100.0;
+            let x0 = -1000.0 + (i as f64) * width;
+            let y0 = 0.0;
+            let z0 = -1000.0 + (j as f64) * width;
+            let x1 = x0 + width;
+            let y1 = random_rng(1.0, 101.0);
+            let z1 = z0 + width;
+
+            ground_boxes.push(box_obj(
+                Point3d::new(x0, y0, z0),
+                Point3d::new(x1, y1, z1),
+                ground.clone(),
+            ));
+        }
+    }
+    let mut scene = vec![ObjectDescription::Group(ground_boxes)];
+
+    // Make a light
+    let light = rectangle_xz(
+        123.0,
+        423.0,
+        147.0,
+        412.0,
+        554.0,
+        diffuse_light(RGB(7.0, 7.0, 7.0)),
+    );
+    scene.push(light);
+
+    // Make a moving sphere
+    let center0 = Point3d::new(400.0, 400.0, 200.0);
+    let center1 = center0 + Vec3d::new(30.0, 0.0, 0.0);
+    let moving_sphere_material = lambertian(RGB(0.7, 0.3, 0.1));
+    scene.push(moving_sphere(
+        center0,
+        center1,
+        50.0,
+        moving_sphere_material,
+    ));
+
+    // Add a dielectric (glass) sphere
+    scene.push(sphere(
+        Point3d::new(260.0, 150.0, 45.0),
+        50.0,
+        dielectric(1.5),
+    ));
+
+    // Add a metal sphere
+    scene.push(sphere(
+        Point3d::new(0.0, 150.0, 145.0),
+        50.0,
+        Metal {
+            albedo: RGB(0.8, 0.8, 0.9),
+            fuzz: 1.0,
+        },
+    ));
+
+    // Add a blue subsurface reflection sphere by putting a volume inside a
+    // dielectric sphere.
+    let boundary0 = sphere(Point3d::new(360.0, 150.0, 145.0), 70.0, dielectric(1.5));
+    scene.push(boundary0.clone());
+    scene.push(constant_medium(boundary0.clone(), RGB(0.2, 0.4, 0.9), 0.2));
+
+    // Fill the whole scene with a faint mist
+    let boundary1 = sphere(Point3d::new(0.0, 0.0, 0.0), 5000.0, dielectric(1.5));
+    scene.push(boundary1);
+    scene.push(constant_medium(boundary0, RGB(1.0, 1.0, 1.0), 0.0001));
+
+    // Add an Earth sphere
+    scene.push(sphere(
+        Vec3d::new(400.0, 200.0, 400.0),
+        100.0,
+        earth_material(),
+    ));
+
+    // Add a perlin noise sphere
+    let perlin_material = MaterialDescription::Lambertian(turbulence(0.001));
+    scene.push(sphere(
+        Point3d::new(220.0, 280.0, 300.0),
+        80.0,
+        perlin_material,
+    ));
+
+    // Add a random assortment of white spheres in a translated rotated box
+    let white = lambertian(RGB(0.73, 0.73, 0.73));
+    let spheres = (0..1000)
+        .map(|_| sphere(random_vec_rng(0.0, 165.0), 10.0, white.clone()))
+        .collect();
+    scene.push(translate(
+        Point3d::new(-100.0, 270.0, 395.0),
+        rotate_y(15.0, ObjectDescription::Group(spheres)),
+    ));
+
+    scene
+}
+
+/// A pyramid with a 200x200 base in the xz plane.
+fn cornell_pyramid(height: f64, material: MaterialDescription) -> ObjectDescription {
+    ObjectDescription::Pyramid {
+        base: (
+            Point3d::new(0.0, 0.0, 0.0),
+            Point3d::new(200.0, 0.0, 0.0),
+            Point3d::new(0.0, 0.0, 200.0),
+        ),
+        height,
+        material,
+    }
+}
+
+pub(super) fn generate_cornell_box_with_pyramids() -> Vec<ObjectDescription> {
+    let mut scene = cornell_box_walls(230.0, 205.0, RGB(12.0, 12.0, 12.0));
+
+    let white = lambertian(RGB(0.73, 0.73, 0.73));
+    let pyr0 = cornell_pyramid(330.0, white.clone());
+    scene.push(translate(
+        Vec3d::new(265.0, 0.0, 295.0),
+        rotate_y(15.0, pyr0),
+    ));
+    let pyr1 = cornell_pyramid(165.0, white);
+    scene.push(translate(
+        Vec3d::new(130.0, 0.0, 65.0),
+        rotate_y(-18.0, pyr1),
+    ));
+
+    scene
+}
+
+/// The Stanford dragon in gold, scaled up by `scale`.
+fn gold_dragon(scale: f64) -> ObjectDescription {
+    let dragon = ObjectDescription::Model {
+        file: "assets/stanford_dragon/dragon_vrip.ply".to_string(),
+        material: Metal {
+            albedo: RGB::from_hash("#ffd700").unwrap(),
+            fuzz: 0.8,
+        },
+    };
+    let dragon = ObjectDescription::Scale {
+        x: scale,
+        y: scale,
+        z: scale,
+        object: Box::new(dragon),
+    };
+    rotate_y(-167.0, dragon)
+}
+
+pub(super) fn generate_cornell_box_with_dragon() -> Vec<ObjectDescription> {
+    let mut scene = vec![translate(
+        Vec3d::new(265.0, -140.0, 295.0),
+        gold_dragon(2600.0),
+    )];
+
+    // Add the walls (top, back, bottom, right red and left green) and light
+    scene.extend(cornell_box_walls(230.0, 205.0, RGB(12.0, 12.0, 12.0)));
+
+    scene
+}
+
+pub(super) fn generate_final_scene() -> Vec<ObjectDescription> {
+    // Make the ground a 20x20 grid of random height boxes with a platform in the middle
+    // box width: 100
+    // x in [-1000, 1000]
+    // z in [-1000, 1000]
+    let mut ground_boxes = Vec::new();
+    let ground = lambertian(RGB(0.48, 0.83, 0.53));
+    let width = 100.0;
+    for i in 0..20 {
+        for j in 0..20 {
+            let x0 = -1000.0 + (i as f64) * width;
+            let y0 = 0.0;
+            let z0 = -1000.0 + (j as f64) * width;
+            let x1 = x0 + width;
+            let y1 = match (i, j) {
+                (i, j) if (10..14).contains(&i) && (10..14).contains(&j) => 100.0,
+                _ => random_rng(1.0, 96.0),
+            };
+            let z1 = z0 + width;
+
+            ground_boxes.push(box_obj(
+                Point3d::new(x0, y0, z0),
+                Point3d::new(x1, y1, z1),
+                ground.clone(),
+            ));
+        }
+    }
+    let mut scene = vec![ObjectDescription::Group(ground_boxes)];
+
+    // Make a light
+    // Center: (200.0, 554.0, 280.0)
+    let light = rectangle_xz(
+        -123.0, // x0
+        523.0,  // x1
+        147.0,  // z0
+        412.0,  // z1
+        554.0,  // k
+        diffuse_light(RGB(7.0, 7.0, 7.0)),
+    );
+    scene.push(light);
+
+    // Make a gold dragon
+    scene.push(translate(
+        Vec3d::new(200.0, -10.0, 200.0),
+        gold_dragon(2000.0),
+    ));
+
+    // Add a moving sphere
+    let center0 = Point3d::new(430.0, 400.0, 500.0);
+    let center1 = center0 + Vec3d::new(30.0, 0.0, 0.0);
+    let moving_sphere_material = lambertian(RGB(0.7, 0.3, 0.1));
+    scene.push(moving_sphere(
+        center0,
+        center1,
+        50.0,
+        moving_sphere_material,
+    ));
+
+    // Add a dielectric (glass) sphere
+    scene.push(sphere(
+        Point3d::new(175.0, 150.0, 45.0),
+        50.0,
+        dielectric(1.5),
+    ));
+
+    // Add a red pyramid
+    let pyr0 = ObjectDescription::Pyramid {
+        base: (
+            Point3d::new(0.0, 0.0, 0.0),
+            Point3d::new(0.0, 0.0, 75.0),
+            Point3d::new(75.0, 0.0, 0.0),
+        ),
+        height: 75.0,
+        material: lambertian(RGB(0.65, 0.05, 0.05)),
+    };
+    // Point3d::new(0.0, 150.0, 145.0),
+    scene.push(translate(
+        Vec3d::new(8.0, 100.0, 142.0),
+        rotate_y(38.0, pyr0),
+    ));
+
+    // Add a metal sphere
+    scene.push(sphere(
+        Point3d::new(-50.0, 150.0, 156.0),
+        50.0,
+        Metal {
+            albedo: RGB(0.8, 0.8, 0.9),
+            fuzz: 0.1,
+        },
+    ));
+
+    // Add a blue subsurface reflection sphere by putting a volume inside a
+    // dielectric sphere.
+    let boundary0 = sphere(Point3d::new(460.0, 160.0, 145.0), 60.0, dielectric(1.5));
+    scene.push(boundary0.clone());
+    scene.push(constant_medium(boundary0, RGB(0.2, 0.4, 0.9), 0.1));
+
+    // Add an Earth sphere
+    let earth_sphere = sphere(Vec3d::new(0.0, 0.0, 0.0), 100.0, earth_material());
+    scene.push(translate(
+        Vec3d::new(500.0, 200.0, 400.0),
+        rotate_y(78.0, earth_sphere),
+    ));
+
+    // Add a perlin noise sphere
+    let perlin_material = MaterialDescription::Lambertian(turbulence(2.0));
+    scene.push(sphere(
+        Point3d::new(82.0, 370.0, 484.0),
+        80.0,
+        perlin_material,
+    ));
+
+    // Add a random assortment of white spheres in a translated rotated box
+    let white = lambertian(RGB(0.73, 0.73, 0.73));
+    let spheres = (0..1000)
+        .map(|_| sphere(random_vec_rng(0.0, 165.0), 10.0, white.clone()))
+        .collect();
+    scene.push(translate(
+        Point3d::new(-250.0, 270.0, 395.0),
+        rotate_y(15.0, ObjectDescription::Group(spheres)),
+    ));
+
+    scene
+}
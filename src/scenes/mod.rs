@@ -0,0 +1,250 @@
+//! Built-in demo scenes, mostly from the "Ray Tracing in One Weekend" book
+//! series. Look a scene up by name with `get`, e.g. `scenes::get("cornell_box")`.
+
+mod cameras;
+mod generators;
+
+use crate::{
+    Result,
+    render::{Background, RendererBuilder},
+    resolution::Resolution,
+    scene::{CameraDescription, ObjectDescription, SceneDescription},
+};
+
+use cameras::*;
+use generators::*;
+
+/// Shutter open time used by all of the built-in scenes.
+pub const TIME0: f64 = 0.0;
+/// Shutter close time used by all of the built-in scenes.
+pub const TIME1: f64 = 1.0;
+
+/// Returns the objects in a scene.
+type SceneGenerator = fn() -> Vec<ObjectDescription>;
+
+/// Returns the cameras for a scene.
+type CameraSetup = fn() -> Vec<CameraDescription>;
+
+/// A scene defined in code along with the cameras used to view it and the
+/// settings it should be rendered with.
+#[derive(Debug)]
+pub struct BuiltInScene {
+    pub name: &'static str,
+    /// Suggested resolution to render the scene at.
+    pub resolution: Resolution,
+    pub background: Background,
+    generate: SceneGenerator,
+    cameras: CameraSetup,
+}
+
+impl BuiltInScene {
+    /// Generates the objects in the scene. Some scenes are randomly generated
+    /// so each call may return different objects, see
+    /// `utilities::seed_random` to make them repeatable.
+    pub fn objects(&self) -> Vec<ObjectDescription> {
+        (self.generate)()
+    }
+
+    pub fn cameras(&self) -> Vec<CameraDescription> {
+        (self.cameras)()
+    }
+
+    /// Generates a description of the scene. The description holds one
+    /// particular layout of a randomly generated scene, it can be saved with
+    /// `scene::save_scene_file` to render the same layout again later.
+    pub fn description(&self) -> SceneDescription {
+        SceneDescription {
+            resolution: Some(self.resolution),
+            background: self.background,
+            time0: TIME0,
+            time1: TIME1,
+            cameras: self.cameras(),
+            objects: self.objects(),
+            ..SceneDescription::default()
+        }
+    }
+
+    /// Generates the objects in the scene and returns a renderer set up with
+    /// them, the cameras, and the scene's suggested settings.
+    pub fn renderer(&self) -> Result<RendererBuilder> {
+        self.description().renderer()
+    }
+}
+
+/// Returns the built-in scene with the given name.
+pub fn get(name: &str) -> Option<&'static BuiltInScene> {
+    SCENES.iter().find(|scene| scene.name == name)
+}
+
+/// Returns the names of all of the built-in scenes.
+pub fn names() -> impl Iterator<Item = &'static str> {
+    SCENES.iter().map(|scene| scene.name)
+}
+
+/// All of the built-in scenes, in the order they appear in the books.
+pub static SCENES: [BuiltInScene; 14] = [
+    BuiltInScene {
+        name: "basic",
+        resolution: Resolution::low(),
+        background: Background::Sky,
+        generate: generate_basic_scene,
+        cameras: get_standard_camera,
+    },
+    BuiltInScene {
+        name: "random_complex",
+        resolution: Resolution::low(),
+        background: Background::Sky,
+        generate: generate_random_complex_scene,
+        cameras: get_standard_camera,
+    },
+    BuiltInScene {
+        name: "random_complex_moving_spheres",
+        resolution: Resolution::low(),
+        background: Background::Sky,
+        generate: generate_random_complex_scene_moving_spheres,
+        cameras: get_standard_camera,
+    },
+    BuiltInScene {
+        name: "two_checkered_spheres",
+        resolution: Resolution::low(),
+        background: Background::Sky,
+        generate: generate_two_checkered_spheres,
+        cameras: get_standard_camera,
+    },
+    BuiltInScene {
+        name: "two_perlin_noise_spheres",
+        resolution: Resolution::low(),
+        background: Background::Sky,
+        generate: generate_two_perlin_noise_spheres,
+        cameras: get_standard_camera,
+    },
+    BuiltInScene {
+        name: "two_perlin_noise_turbulence_spheres",
+        resolution: Resolution::low(),
+        background: Background::Sky,
+        generate: generate_two_perlin_noise_turbulence_spheres,
+        cameras: get_standard_camera,
+    },
+    BuiltInScene {
+        name: "earth",
+        resolution: Resolution::low(),
+        background: Background::Sky,
+        generate: generate_earth_scene,
+        cameras: get_standard_camera,
+    },
+    BuiltInScene {
+        name: "simple_light",
+        resolution: Resolution::low(),
+        background: Background::BLACK,
+        generate: generate_simple_light,
+        cameras: get_standard_camera,
+    },
+    BuiltInScene {
+        name: "cornell_box",
+        resolution: Resolution::cornell_square(),
+        background: Background::BLACK,
+        generate: generate_cornell_box,
+        cameras: get_cornell_box_camera,
+    },
+    BuiltInScene {
+        name: "cornell_box_with_smoke_boxes",
+        resolution: Resolution::cornell_square(),
+        background: Background::BLACK,
+        generate: generate_cornell_box_with_smoke_boxes,
+        cameras: get_cornell_box_camera,
+    },
+    BuiltInScene {
+        name: "final_scene_book2",
+        resolution: Resolution::cornell_square(),
+        background: Background::BLACK,
+        generate: generate_final_scene_book2,
+        cameras: get_final_scene_book2_camera,
+    },
+    BuiltInScene {
+        name: "cornell_box_with_pyramids",
+        resolution: Resolution::cornell_square(),
+        background: Background::BLACK,
+        generate: generate_cornell_box_with_pyramids,
+        cameras: get_cornell_box_camera,
+    },
+    BuiltInScene {
+        name: "cornell_box_with_dragon",
+        resolution: Resolution::cornell_square(),
+        background: Background::BLACK,
+        generate: generate_cornell_box_with_dragon,
+        cameras: get_cornell_box_camera,
+    },
+    BuiltInScene {
+        name: "final_scene",
+        resolution: Resolution::low(),
+        background: Background::BLACK,
+        generate: generate_final_scene,
+        cameras: get_final_scene_cameras,
+    },
+];
+
+#[cfg(test)]
+mod scenes_tests {
+    use crate::{
+        scene::{parse_scene, write_scene},
+        utilities::seed_random,
+    };
+
+    use super::*;
+
+    #[test]
+    fn get_should_return_scene_with_name() {
+        let scene = get("cornell_box").unwrap();
+
+        assert_eq!(scene.name, "cornell_box");
+        assert!(get("not_a_scene").is_none());
+    }
+
+    #[test]
+    fn build_should_build_scenes_without_assets() {
+        for name in [
+            "basic",
+            "simple_light",
+            "cornell_box",
+            "cornell_box_with_smoke_boxes",
+        ] {
+            let renderer = get(name).unwrap().renderer().unwrap().build().unwrap();
+
+            assert_eq!(renderer.num_cameras(), 1);
+        }
+    }
+
+    #[test]
+    fn description_should_be_read_back_from_a_scene_file() {
+        seed_random(3);
+        for scene in SCENES.iter() {
+            let description = scene.description();
+
+            let text = write_scene(&description);
+
+            assert_eq!(parse_scene(&text).unwrap(), description, "{}", scene.name);
+        }
+    }
+
+    #[test]
+    fn saved_random_scene_should_render_the_same_as_the_original() {
+        let render = |description: &SceneDescription| {
+            let renderer = description.renderer().unwrap();
+            renderer
+                .size(12, 8)
+                .samples(2)
+                .seed(11)
+                .build()
+                .unwrap()
+                .render(0)
+        };
+
+        seed_random(5);
+        for name in ["random_complex_moving_spheres", "two_perlin_noise_spheres"] {
+            let description = get(name).unwrap().description();
+            let loaded = parse_scene(&write_scene(&description)).unwrap();
+
+            assert_eq!(render(&loaded), render(&description), "{name}");
+        }
+    }
+}
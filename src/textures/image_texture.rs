@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use crate::{
+    Result,
     colour::RGB,
     utilities::{clamp, read_image_file},
     vec3d::Point3d,
@@ -25,9 +26,9 @@ impl ImageTexture {
         }
     }
 
-    pub fn build(file_name: &str) -> Self {
-        let (width, height, pixels) = read_image_file(file_name);
-        Self::new(width, height, pixels)
+    pub fn build(file_name: &str) -> Result<Self> {
+        let (width, height, pixels) = read_image_file(file_name)?;
+        Ok(Self::new(width, height, pixels))
     }
 }
 
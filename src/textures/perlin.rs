@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use crate::{
-    utilities::{random_rng_int, random_vec_rng},
+    utilities::{random_rng_int, random_vec_rng, with_seeded_random},
     vec3d::Point3d,
     vec3d::Vec3d,
 };
@@ -45,6 +45,11 @@ impl Perlin {
         }
     }
 
+    /// Builds the same noise every time for a given seed.
+    pub fn build_from_seed(seed: u64) -> Perlin {
+        with_seeded_random(seed, Self::build_random)
+    }
+
     #[allow(clippy::needless_range_loop)]
     pub fn noise(&self, p: Point3d) -> f64 {
         let (u, i) = Self::float_to_index(p.x);
@@ -94,7 +99,7 @@ impl Perlin {
     }
 
     fn float_to_index(x: f64) -> (f64, usize) {
-        let x_pos = if x < 0.0 { -1.0 * x } else { x };
+        let x_pos = if x < 0.0 { -x } else { x };
         let u = x_pos - x_pos.floor();
         (u * u * (3.0 - (2.0 * u)), x_pos.floor() as usize)
     }
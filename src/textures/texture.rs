@@ -1,5 +1,13 @@
+use std::sync::Arc;
+
 use crate::{colour::RGB, vec3d::Point3d};
 
 pub trait Texture: Send + Sync {
     fn value(&self, u: f64, v: f64, p: &Point3d) -> RGB;
 }
+
+impl Texture for Arc<dyn Texture> {
+    fn value(&self, u: f64, v: f64, p: &Point3d) -> RGB {
+        self.as_ref().value(u, v, p)
+    }
+}
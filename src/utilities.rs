@@ -1,6 +1,7 @@
-use image::{ImageBuffer, ImageReader, RgbImage};
-use rand::Rng;
+use image::{ImageBuffer, ImageError, ImageReader, Rgb32FImage, RgbImage};
+use rand::{Rng, SeedableRng, rngs::SmallRng};
 use std::{
+    cell::RefCell,
     cmp::{max_by, min_by},
     f64::consts::PI,
     fs::File,
@@ -8,28 +9,66 @@ use std::{
     sync::Arc,
 };
 
-use crate::{bvh::bounding_box::BoundingBox, colour::RGB, vec3d::Vec3d};
+use crate::{Error, Result, bvh::bounding_box::BoundingBox, colour::RGB, vec3d::Vec3d};
 
 pub fn degrees_to_radians(degrees: f64) -> f64 {
     degrees * PI / 180.0
 }
 
+thread_local! {
+    /// Random number generator for the current thread, seeded from the OS
+    /// unless `seed_random` is called.
+    static RNG: RefCell<SmallRng> = RefCell::new(SmallRng::from_os_rng());
+}
+
+/// Seeds the random number generator of the current thread, the random
+/// functions below will then produce the same sequence of numbers on every run.
+pub fn seed_random(seed: u64) {
+    RNG.with_borrow_mut(|rng| *rng = SmallRng::seed_from_u64(seed));
+}
+
+/// Runs `f` with the random number generator of the current thread seeded
+/// with `seed`, afterwards the generator carries on from where it was before.
+pub fn with_seeded_random<T>(seed: u64, f: impl FnOnce() -> T) -> T {
+    let previous = RNG.replace(SmallRng::seed_from_u64(seed));
+    let result = f();
+    RNG.set(previous);
+    result
+}
+
+/// Returns a seed for the `index`th of many streams of random numbers drawn
+/// from `seed`. The two are hashed together (splitmix64) so that nearby
+/// seeds and indexes give unrelated streams rather than overlapping ones.
+pub fn mix_seed(seed: u64, index: u64) -> u64 {
+    splitmix64(seed ^ splitmix64(index))
+}
+
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Returns a random seed for `with_seeded_random`. Seeds are kept below 2^32
+/// so they can be written to a scene file and read back exactly.
+pub fn random_seed() -> u64 {
+    RNG.with_borrow_mut(|rng| rng.random::<u32>() as u64)
+}
+
 /// Returns a random number in [0, 1)
 pub fn random() -> f64 {
-    let mut rng = rand::rng();
-    rng.random::<f64>()
+    RNG.with_borrow_mut(|rng| rng.random::<f64>())
 }
 
 /// Returns a random number in [min, max)
 pub fn random_rng(min: f64, max: f64) -> f64 {
-    let mut rng = rand::rng();
-    rng.random_range(min..max)
+    RNG.with_borrow_mut(|rng| rng.random_range(min..max))
 }
 
 /// Returns a random integer in [min, max)
 pub fn random_rng_int(min: usize, max: usize) -> usize {
-    let mut rng = rand::rng();
-    rng.random_range(min..max)
+    RNG.with_borrow_mut(|rng| rng.random_range(min..max))
 }
 
 /// Returns a random vector where x, y, and z are all in [0, 1)
@@ -89,8 +128,7 @@ pub fn reflect_vec(vec_in: &Vec3d, normal: &Vec3d) -> Vec3d {
 pub fn refract_vec(vec_in: &Vec3d, normal: &Vec3d, refraction_index: f64) -> Vec3d {
     let cos_theta = f64::min(-vec_in.dot(normal), 1.0);
     let vec_out_perpendicular = refraction_index * (*vec_in + cos_theta * *normal);
-    let vec_out_parallel =
-        -1.0 * (1.0 - vec_out_perpendicular.len_squared()).abs().sqrt() * *normal;
+    let vec_out_parallel = -(1.0 - vec_out_perpendicular.len_squared()).abs().sqrt() * *normal;
     vec_out_perpendicular + vec_out_parallel
 }
 
@@ -116,16 +154,20 @@ pub fn save_as_ppm(
     file_name: &str,
     image_width: usize,
     image_height: usize,
-    image: &Vec<RGB>,
+    image: &[RGB],
     num_samples: usize,
-) {
+) -> Result<()> {
     let mut image_string: String = format!("P3\n{image_width} {image_height}\n255\n").to_string();
     for colour in image {
         image_string.push_str(&colour.write_colour(num_samples));
     }
 
-    let mut output_file = File::create(file_name).unwrap();
-    output_file.write_all(image_string.as_bytes()).unwrap();
+    File::create(file_name)
+        .and_then(|mut output_file| output_file.write_all(image_string.as_bytes()))
+        .map_err(|source| Error::Io {
+            path: file_name.into(),
+            source,
+        })
 }
 
 pub fn save_as_png(
@@ -134,7 +176,7 @@ pub fn save_as_png(
     image_height: usize,
     image: &[RGB],
     num_samples: usize,
-) {
+) -> Result<()> {
     let mut image_buffer: RgbImage = ImageBuffer::new(image_width as u32, image_height as u32);
     for (x, y, colour) in image_buffer.enumerate_pixels_mut() {
         let i = (y as usize * image_width) + x as usize;
@@ -142,14 +184,37 @@ pub fn save_as_png(
         let (ir, ig, ib) = pixel.to_integers(num_samples);
         colour.0 = [ir as u8, ig as u8, ib as u8];
     }
-    image_buffer.save(file_name).unwrap();
+    image_buffer
+        .save(file_name)
+        .map_err(|source| image_error(file_name, source))
+}
+
+/// Saves linear colours as 32-bit floats in an OpenEXR file, without gamma
+/// correction or clamping.
+pub fn save_as_exr(
+    file_name: &str,
+    image_width: usize,
+    image_height: usize,
+    image: &[RGB],
+) -> Result<()> {
+    let mut image_buffer: Rgb32FImage = ImageBuffer::new(image_width as u32, image_height as u32);
+    for (x, y, colour) in image_buffer.enumerate_pixels_mut() {
+        let RGB(r, g, b) = image[(y as usize * image_width) + x as usize];
+        colour.0 = [r as f32, g as f32, b as f32];
+    }
+    image_buffer
+        .save(file_name)
+        .map_err(|source| image_error(file_name, source))
 }
 
-pub fn read_image_file(file_name: &str) -> (usize, usize, Arc<[RGB]>) {
+pub fn read_image_file(file_name: &str) -> Result<(usize, usize, Arc<[RGB]>)> {
     let img = ImageReader::open(file_name)
-        .unwrap()
+        .map_err(|source| Error::Io {
+            path: file_name.into(),
+            source,
+        })?
         .decode()
-        .unwrap()
+        .map_err(|source| image_error(file_name, source))?
         .into_rgb8();
     let width = img.width() as usize;
     let height = img.height() as usize;
@@ -164,7 +229,22 @@ pub fn read_image_file(file_name: &str) -> (usize, usize, Arc<[RGB]>) {
         })
         .collect();
 
-    (width, height, pixels)
+    Ok((width, height, pixels))
+}
+
+/// Keeps IO errors from the image crate as `Error::Io` so they are reported
+/// the same way as other IO errors.
+fn image_error(file_name: &str, source: ImageError) -> Error {
+    match source {
+        ImageError::IoError(source) => Error::Io {
+            path: file_name.into(),
+            source,
+        },
+        source => Error::Image {
+            path: file_name.into(),
+            source,
+        },
+    }
 }
 
 /// Returns the box surrounding two `BoundingBox`s.
@@ -196,6 +276,14 @@ pub fn surrounding_box_option(
     }
 }
 
+/// Returns the 64-bit FNV-1a hash of some bytes. Unlike `std::hash` it is
+/// the same on every run and platform, so it can be saved to a file.
+pub fn fnv1a_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
 /// Given a point on the unit sphere returns the coordinates of that point in
 /// the form (u, v) where;
 ///
@@ -257,4 +345,27 @@ mod tests {
             assert_eq!(result, (0.75, 0.5));
         }
     }
+
+    mod mix_seed_tests {
+        use super::*;
+
+        #[test]
+        fn neighbouring_seeds_should_not_share_streams() {
+            // Adding the index to the seed gave (1, 1) and (2, 0) the same
+            // stream
+            assert_ne!(mix_seed(1, 1), mix_seed(2, 0));
+            assert_ne!(mix_seed(0, 1), mix_seed(1, 0));
+            assert_eq!(mix_seed(7, 3), mix_seed(7, 3));
+        }
+
+        #[test]
+        fn with_seeded_random_should_restore_the_generator() {
+            seed_random(9);
+            let expected = [random(), random()];
+            seed_random(9);
+            let first = random();
+            with_seeded_random(mix_seed(9, 1), random);
+            assert_eq!([first, random()], expected);
+        }
+    }
 }
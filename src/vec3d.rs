@@ -9,7 +9,7 @@ pub struct Vec3d {
 }
 
 impl Vec3d {
-    pub fn new(x: f64, y: f64, z: f64) -> Vec3d {
+    pub const fn new(x: f64, y: f64, z: f64) -> Vec3d {
         Vec3d { x, y, z }
     }
 
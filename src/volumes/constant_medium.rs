@@ -60,7 +60,7 @@ where
     THittable: Hittable + Clone,
     TTexture: Texture + Clone + Sync,
 {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
         // Check if the ray hits the boundary anywhere on it's length
         let hr1 = self.boundary.hit(ray, f64::NEG_INFINITY, f64::INFINITY);
         hr1.as_ref()?;
@@ -102,15 +102,19 @@ where
         let t = hr1.t + hit_distance / ray_length;
         let point = ray.at(t);
 
-        Some(HitRecord::new(
-            point,
-            Vec3d::new(1.0, 0.0, 0.0), // Arbitrary
-            &self.phase_function,
-            t,
-            0.0,  // Arbitrary
-            0.0,  // Arbitrary
-            true, // Arbitrary
-        ))
+        // The medium moves with its boundary
+        Some(
+            HitRecord::new(
+                point,
+                Vec3d::new(1.0, 0.0, 0.0), // Arbitrary
+                &self.phase_function,
+                t,
+                0.0,  // Arbitrary
+                0.0,  // Arbitrary
+                true, // Arbitrary
+            )
+            .with_velocity(hr1.velocity),
+        )
     }
 
     fn bounding_box(&self, time0: f64, time1: f64) -> Option<BoundingBox> {